@@ -16,6 +16,10 @@ struct CSTestResult {
     message: String,
     points: Vec<String>,
     error_stack_trace: Vec<String>,
+    #[serde(default)]
+    duration_ms: Option<u64>,
+    #[serde(default)]
+    output: Option<String>,
 }
 
 impl From<CSTestResult> for TestResult {
@@ -26,6 +30,9 @@ impl From<CSTestResult> for TestResult {
             message: test_result.message,
             exception: test_result.error_stack_trace,
             points: test_result.points,
+            duration_ms: test_result.duration_ms,
+            stdout: test_result.output,
+            stderr: None,
         }
     }
 }
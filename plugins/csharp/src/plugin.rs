@@ -6,10 +6,11 @@ pub use policy::CSharpStudentFilePolicy;
 use crate::{CSTestResult, CSharpError};
 
 use tmc_langs_framework::{
-    command::{OutputWithTimeout, TmcCommand},
+    command::{OutputWithTimeout, ResourceLimits, TmcCommand},
     domain::{
-        ExerciseDesc, RunResult, RunStatus, Strategy, TestDesc, TestResult, ValidationResult,
+        ExerciseDesc, RunResult, RunStatus, Strategy, TestDesc, TestFilter, ValidationResult,
     },
+    io::archive::Archive,
     plugin::Language,
     zip::ZipArchive,
     LanguagePlugin, TmcError,
@@ -19,7 +20,7 @@ use std::collections::HashMap;
 use std::env;
 use std::ffi::{OsStr, OsString};
 use std::fs::{self, File};
-use std::io::{BufReader, Cursor, Read, Seek, Write};
+use std::io::{BufReader, Cursor, Read, Write};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use walkdir::WalkDir;
@@ -130,12 +131,8 @@ impl LanguagePlugin for CSharpPlugin {
     }
 
     /// Finds .csproj files and checks whether they are in a X/src/ directory, returning X if so.
-    fn find_project_dir_in_zip<R: Read + Seek>(
-        zip_archive: &mut ZipArchive<R>,
-    ) -> Result<PathBuf, TmcError> {
-        for i in 0..zip_archive.len() {
-            let file = zip_archive.by_index(i)?;
-            let file_path = file.sanitized_name();
+    fn find_project_dir_in_archive(archive: &mut Archive) -> Result<PathBuf, TmcError> {
+        for (file_path, _kind) in archive.list_entries()? {
             if file_path.extension() == Some(OsStr::new("csproj")) {
                 if let Some(csproj_parent) = file_path.parent().and_then(Path::parent) {
                     if csproj_parent.file_name() == Some(OsStr::new("src")) {
@@ -189,25 +186,29 @@ impl LanguagePlugin for CSharpPlugin {
         &self,
         path: &Path,
         timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
     ) -> Result<RunResult, TmcError> {
         let test_results_path = path.join(".tmc_test_results.json");
         if test_results_path.exists() {
             fs::remove_file(&test_results_path)
                 .map_err(|e| CSharpError::RemoveFile(test_results_path.clone(), e))?;
         }
+        if !filter.is_empty() {
+            // the bundled runner only supports --generate-points-file and --run-tests with --project-dir and --output-file
+            log::warn!(
+                "the C# runner cannot select tests, running all of them and discarding the results of the tests that were not selected"
+            );
+        }
         let mut command = TmcCommand::new("dotnet");
         command
             .current_dir(path)
             .arg(Self::get_bootstrap_path()?)
             .arg("--run-tests");
-        let output = if let Some(timeout) = timeout {
-            let output = command.wait_with_timeout(timeout)?;
-            log::trace!("stdout: {}", String::from_utf8_lossy(output.stdout()));
-            log::debug!("stderr: {}", String::from_utf8_lossy(output.stderr()));
-            output
-        } else {
-            OutputWithTimeout::Output(command.output()?)
-        };
+        command.resource_limits(resource_limits);
+        let output = command.output_with_timeout(timeout)?;
+        log::trace!("stdout: {}", String::from_utf8_lossy(output.stdout()));
+        log::debug!("stderr: {}", String::from_utf8_lossy(output.stderr()));
 
         match output {
             OutputWithTimeout::Output(output) => {
@@ -227,21 +228,10 @@ impl LanguagePlugin for CSharpPlugin {
                         logs,
                     });
                 }
-                Self::parse_test_results(&test_results_path).map_err(|e| e.into())
+                let run_result = Self::parse_test_results(&test_results_path)?;
+                Ok(filter.apply(run_result))
             }
-            OutputWithTimeout::Timeout { .. } => Ok(RunResult {
-                status: RunStatus::TestsFailed,
-                test_results: vec![TestResult {
-                    name: "Timeout test".to_string(),
-                    successful: false,
-                    points: vec![],
-                    message:
-                        "Tests timed out.\nMake sure you don't have an infinite loop in your code."
-                            .to_string(),
-                    exception: vec![],
-                }],
-                logs: HashMap::new(),
-            }),
+            OutputWithTimeout::Timeout { .. } => Ok(RunResult::timed_out()),
         }
     }
 
@@ -278,6 +268,7 @@ mod test {
     use super::*;
     use std::sync::Once;
     use tempfile::TempDir;
+    use tmc_langs_framework::io::tmc_zip::UnzipLimits;
 
     static INIT_RUNNER: Once = Once::new();
 
@@ -310,6 +301,43 @@ mod test {
         temp
     }
 
+    #[test]
+    fn parses_test_results_with_timing() {
+        init();
+        let temp = tempfile::tempdir().unwrap();
+        let test_results_path = temp.path().join(".tmc_test_results.json");
+        fs::write(
+            &test_results_path,
+            r#"[
+    {
+        "Name": "PassingTest",
+        "Passed": true,
+        "Message": "",
+        "Points": ["1"],
+        "ErrorStackTrace": [],
+        "DurationMs": 12,
+        "Output": "out"
+    },
+    {
+        "Name": "FailingTest",
+        "Passed": false,
+        "Message": "fail",
+        "Points": ["2"],
+        "ErrorStackTrace": []
+    }
+]"#,
+        )
+        .unwrap();
+
+        let run_result = CSharpPlugin::parse_test_results(&test_results_path).unwrap();
+        assert_eq!(run_result.status, RunStatus::TestsFailed);
+        assert_eq!(run_result.test_results[0].duration_ms, Some(12));
+        assert_eq!(run_result.test_results[0].stdout.as_deref(), Some("out"));
+        // the timing and output are optional
+        assert_eq!(run_result.test_results[1].duration_ms, None);
+        assert_eq!(run_result.test_results[1].stdout, None);
+    }
+
     #[test]
     fn exercise_type_is_correct() {
         init();
@@ -328,17 +356,17 @@ mod test {
 
     #[test]
     fn finds_project_dir_in_zip() {
-        let file = File::open("tests/data/Zipped.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = CSharpPlugin::find_project_dir_in_zip(&mut zip).unwrap();
+        let mut archive =
+            Archive::open(Path::new("tests/data/Zipped.zip"), UnzipLimits::default()).unwrap();
+        let dir = CSharpPlugin::find_project_dir_in_archive(&mut archive).unwrap();
         assert_eq!(dir, Path::new("Outer/Inner/PassingProject"))
     }
 
     #[test]
     fn no_project_dir_in_zip() {
-        let file = File::open("tests/data/test.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = CSharpPlugin::find_project_dir_in_zip(&mut zip);
+        let mut archive =
+            Archive::open(Path::new("tests/data/test.zip"), UnzipLimits::default()).unwrap();
+        let dir = CSharpPlugin::find_project_dir_in_archive(&mut archive);
         assert!(dir.is_err())
     }
 
@@ -368,6 +396,18 @@ mod test {
         assert!(res.logs.is_empty());
     }
 
+    #[test]
+    fn runs_tests_filtered() {
+        init();
+        let plugin = CSharpPlugin::new();
+        let temp = copy_test_dir("tests/data/PassingProject");
+        let filter = TestFilter::new(vec![], vec!["1.2".to_string()]);
+        let res = plugin.run_tests_filtered(temp.path(), &filter).unwrap();
+        assert_eq!(res.status, RunStatus::Passed);
+        assert_eq!(res.test_results.len(), 1);
+        assert!(res.test_results[0].points.contains(&"1.2".to_string()));
+    }
+
     #[test]
     fn runs_tests_failing() {
         init();
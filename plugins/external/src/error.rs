@@ -0,0 +1,36 @@
+//! Error type for the external plugin
+
+use thiserror::Error;
+use tmc_langs_framework::TmcError;
+
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+#[derive(Debug, Error)]
+pub enum ExternalError {
+    #[error("No external plugin configured for {0}")]
+    NoConfig(PathBuf),
+    #[error("External plugin {0} failed with status {1}. stderr: {2}")]
+    CommandStatus(PathBuf, ExitStatus, String),
+    #[error("External plugin {0} timed out after {2:?} during {1}")]
+    Timeout(PathBuf, &'static str, Duration),
+
+    #[error("Failed to read plugin manifest directory {0}")]
+    ManifestDirRead(PathBuf, #[source] std::io::Error),
+    #[error("Failed to open plugin manifest {0}")]
+    ManifestOpen(PathBuf, #[source] std::io::Error),
+    #[error("Failed to deserialize plugin manifest {0}")]
+    ManifestDeserialize(PathBuf, #[source] serde_yaml::Error),
+
+    #[error("Failed to serialize request to JSON")]
+    JsonSerialize(#[source] serde_json::Error),
+    #[error("Failed to deserialize the response of external plugin {0} to {1} from JSON")]
+    JsonDeserialize(PathBuf, &'static str, #[source] serde_json::Error),
+}
+
+impl From<ExternalError> for TmcError {
+    fn from(err: ExternalError) -> TmcError {
+        TmcError::Plugin(Box::new(err))
+    }
+}
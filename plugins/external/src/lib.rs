@@ -0,0 +1,13 @@
+//! Language plugin that delegates to an external executable, see the README for the protocol
+
+mod error;
+mod manifest;
+mod plugin;
+mod policy;
+mod protocol;
+
+pub use error::ExternalError;
+pub use manifest::ExternalPluginManifest;
+pub use plugin::ExternalPlugin;
+pub use policy::ExternalStudentFilePolicy;
+pub use protocol::{Request, PROTOCOL_VERSION};
@@ -0,0 +1,190 @@
+//! Finds the external plugin configured for an exercise
+
+use crate::error::ExternalError;
+use serde::Deserialize;
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tmc_langs_framework::{
+    domain::{ExternalPluginConfig, TmcProjectYml},
+    TmcError,
+};
+
+/// A plugin manifest in the plugin directory of the config directory.
+/// Any exercise that contains one of the detection paths uses the plugin.
+#[derive(Debug, Deserialize)]
+pub struct ExternalPluginManifest {
+    pub name: String,
+    /// Files or directories, relative to the exercise root, that mark an exercise as this plugin's.
+    pub detect: Vec<PathBuf>,
+    #[serde(flatten)]
+    pub config: ExternalPluginConfig,
+}
+
+/// The timeout for the requests other than running tests if the configuration does not set one.
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The external plugin configuration for an exercise, with the command resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ResolvedConfig {
+    pub command: PathBuf,
+    pub args: Vec<String>,
+    pub student_file_paths: Vec<PathBuf>,
+    pub timeout: Duration,
+}
+
+impl ResolvedConfig {
+    fn new(config: ExternalPluginConfig, base_dir: &Path) -> Self {
+        // a bare name is looked up from PATH, other relative paths are relative to the config
+        let command = if config.command.is_relative() && config.command.components().count() > 1 {
+            base_dir.join(config.command)
+        } else {
+            config.command
+        };
+        Self {
+            command,
+            args: config.args,
+            student_file_paths: config.student_file_paths,
+            timeout: config
+                .timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+/// The directory plugin manifests are read from, $TMC_LANGS_CONFIG_DIR/tmc-langs/plugins or the equivalent in the user's config directory.
+pub(crate) fn manifest_dir() -> Option<PathBuf> {
+    let config_dir = match env::var("TMC_LANGS_CONFIG_DIR") {
+        Ok(v) => Some(PathBuf::from(v)),
+        Err(_) => dirs::config_dir(),
+    };
+    config_dir.map(|d| d.join("tmc-langs").join("plugins"))
+}
+
+/// Finds the external plugin for the exercise. The exercise's .tmcproject.yml takes precedence over the manifests.
+pub(crate) fn find_config(
+    exercise_path: &Path,
+    manifest_dir: Option<&Path>,
+) -> Result<Option<ResolvedConfig>, TmcError> {
+    if let Some(config) = TmcProjectYml::from(exercise_path)?.external_plugin {
+        return Ok(Some(ResolvedConfig::new(config, exercise_path)));
+    }
+
+    if let Some(manifest_dir) = manifest_dir {
+        for manifest in read_manifests(manifest_dir)? {
+            if manifest
+                .detect
+                .iter()
+                .any(|p| exercise_path.join(p).exists())
+            {
+                log::debug!("using external plugin {}", manifest.name);
+                return Ok(Some(ResolvedConfig::new(manifest.config, manifest_dir)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Reads the .yml and .yaml manifests in the directory, sorted by file name.
+fn read_manifests(manifest_dir: &Path) -> Result<Vec<ExternalPluginManifest>, ExternalError> {
+    if !manifest_dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut paths = vec![];
+    for entry in fs::read_dir(manifest_dir)
+        .map_err(|e| ExternalError::ManifestDirRead(manifest_dir.to_path_buf(), e))?
+    {
+        let path = entry
+            .map_err(|e| ExternalError::ManifestDirRead(manifest_dir.to_path_buf(), e))?
+            .path();
+        let is_yaml = path
+            .extension()
+            .map(|e| e == "yml" || e == "yaml")
+            .unwrap_or(false);
+        if is_yaml && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut manifests = vec![];
+    for path in paths {
+        let file = File::open(&path).map_err(|e| ExternalError::ManifestOpen(path.clone(), e))?;
+        let manifest = serde_yaml::from_reader(file)
+            .map_err(|e| ExternalError::ManifestDeserialize(path.clone(), e))?;
+        manifests.push(manifest);
+    }
+    Ok(manifests)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn finds_config_from_tmcproject_yml() {
+        init();
+
+        let config = find_config(Path::new("tests/data/exercise"), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.command, Path::new("tests/data/exercise/./plugin.sh"));
+        assert_eq!(config.student_file_paths, &[Path::new("src")]);
+        assert_eq!(config.timeout, DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn finds_config_from_manifest() {
+        init();
+
+        let manifests = tempfile::tempdir().unwrap();
+        fs::write(
+            manifests.path().join("haskell.yml"),
+            r#"name: haskell
+detect:
+  - stack.yaml
+command: bin/tmc-haskell
+student_file_paths:
+  - app
+timeout_secs: 300
+"#,
+        )
+        .unwrap();
+        fs::write(
+            manifests.path().join("lisp.yml"),
+            r#"name: lisp
+detect:
+  - project.lisp
+command: tmc-lisp
+"#,
+        )
+        .unwrap();
+
+        let exercise = tempfile::tempdir().unwrap();
+        assert!(find_config(exercise.path(), Some(manifests.path()))
+            .unwrap()
+            .is_none());
+
+        fs::write(exercise.path().join("stack.yaml"), "").unwrap();
+        let config = find_config(exercise.path(), Some(manifests.path()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.command, manifests.path().join("bin/tmc-haskell"));
+        assert_eq!(config.student_file_paths, &[Path::new("app")]);
+        assert_eq!(config.timeout, Duration::from_secs(300));
+
+        fs::remove_file(exercise.path().join("stack.yaml")).unwrap();
+        fs::write(exercise.path().join("project.lisp"), "").unwrap();
+        let config = find_config(exercise.path(), Some(manifests.path()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.command, Path::new("tmc-lisp"));
+    }
+}
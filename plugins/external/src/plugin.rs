@@ -0,0 +1,346 @@
+// Contains the ExternalPlugin
+
+use crate::error::ExternalError;
+use crate::manifest::{self, ResolvedConfig};
+use crate::protocol::{Request, VersionedRequest, PROTOCOL_VERSION};
+use crate::ExternalStudentFilePolicy;
+
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tmc_langs_framework::{
+    command::{OutputWithTimeout, ResourceLimits, TmcCommand},
+    domain::{ExerciseDesc, RunResult, TestFilter, ValidationResult},
+    plugin::Language,
+    LanguagePlugin, TmcError,
+};
+
+/// Implements the plugin by calling the executable configured for the exercise,
+/// either in its .tmcproject.yml or in a manifest in the plugin directory.
+#[derive(Default)]
+pub struct ExternalPlugin {}
+
+impl ExternalPlugin {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn find_config(path: &Path) -> Result<ResolvedConfig, TmcError> {
+        manifest::find_config(path, manifest::manifest_dir().as_deref())?
+            .ok_or_else(|| ExternalError::NoConfig(path.to_path_buf()).into())
+    }
+
+    /// Sends the request to the external plugin and returns its stdout,
+    /// or None if the timeout was reached.
+    fn call(
+        &self,
+        path: &Path,
+        config: &ResolvedConfig,
+        request: Request,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+    ) -> Result<Option<Vec<u8>>, TmcError> {
+        let name = request.name();
+        let request = VersionedRequest {
+            protocol_version: PROTOCOL_VERSION,
+            request,
+        };
+        let request = serde_json::to_vec(&request).map_err(ExternalError::JsonSerialize)?;
+        log::debug!(
+            "calling external plugin {} with {}",
+            config.command.display(),
+            String::from_utf8_lossy(&request)
+        );
+
+        let mut command = TmcCommand::named("external plugin", &config.command);
+        command
+            .input(request)
+            .resource_limits(resource_limits)
+            .args(&config.args)
+            .current_dir(path);
+        let output = match command.output_with_timeout(timeout)? {
+            OutputWithTimeout::Output(output) => output,
+            OutputWithTimeout::Timeout { .. } => {
+                log::warn!("external plugin timed out during {}", name);
+                return Ok(None);
+            }
+        };
+
+        log::trace!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        log::debug!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+
+        if !output.status.success() {
+            return Err(ExternalError::CommandStatus(
+                config.command.clone(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            )
+            .into());
+        }
+        Ok(Some(output.stdout))
+    }
+
+    /// Sends a request other than running tests with the configured timeout,
+    /// returning an error if the plugin does not respond in time.
+    fn call_with_config_timeout(&self, path: &Path, request: Request) -> Result<Vec<u8>, TmcError> {
+        let config = Self::find_config(path)?;
+        let name = request.name();
+        match self.call(
+            path,
+            &config,
+            request,
+            Some(config.timeout),
+            ResourceLimits::default(),
+        )? {
+            Some(stdout) => Ok(stdout),
+            None => Err(ExternalError::Timeout(config.command, name, config.timeout).into()),
+        }
+    }
+
+    fn call_and_parse<T: DeserializeOwned>(
+        &self,
+        path: &Path,
+        request: Request,
+    ) -> Result<T, TmcError> {
+        let name = request.name();
+        let stdout = self.call_with_config_timeout(path, request)?;
+        parse_response(path, name, &stdout)
+    }
+}
+
+fn parse_response<T: DeserializeOwned>(
+    path: &Path,
+    name: &'static str,
+    stdout: &[u8],
+) -> Result<T, TmcError> {
+    serde_json::from_slice(stdout).map_err(|e| {
+        let command = ExternalPlugin::find_config(path)
+            .map(|c| c.command)
+            .unwrap_or_else(|_| PathBuf::from("unknown"));
+        ExternalError::JsonDeserialize(command, name, e).into()
+    })
+}
+
+// the exercise path is made absolute so that the plugin doesn't need to know our working directory
+fn absolute(path: &Path) -> Result<PathBuf, TmcError> {
+    path.canonicalize()
+        .map_err(|e| TmcError::Canonicalize(path.to_path_buf(), e))
+}
+
+impl LanguagePlugin for ExternalPlugin {
+    const PLUGIN_NAME: &'static str = "external";
+    type StudentFilePolicy = ExternalStudentFilePolicy;
+
+    fn scan_exercise(&self, path: &Path, exercise_name: String) -> Result<ExerciseDesc, TmcError> {
+        let exercise_path = absolute(path)?;
+        self.call_and_parse(
+            path,
+            Request::ScanExercise {
+                exercise_path: &exercise_path,
+                exercise_name: &exercise_name,
+            },
+        )
+    }
+
+    fn run_tests_with_timeout(
+        &self,
+        path: &Path,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
+    ) -> Result<RunResult, TmcError> {
+        let exercise_path = absolute(path)?;
+        let request = Request::RunTests {
+            exercise_path: &exercise_path,
+            filter,
+        };
+        let config = Self::find_config(path)?;
+        match self.call(path, &config, request, timeout, resource_limits)? {
+            // in case the plugin doesn't support filtering
+            Some(stdout) => Ok(filter.apply(parse_response(path, "run_tests", &stdout)?)),
+            None => Ok(RunResult::timed_out()),
+        }
+    }
+
+    fn check_code_style(&self, path: &Path, locale: Language) -> Option<ValidationResult> {
+        let result = absolute(path).and_then(|exercise_path| {
+            self.call_and_parse(
+                path,
+                Request::CheckCodeStyle {
+                    exercise_path: &exercise_path,
+                    locale: locale.to_639_3(),
+                },
+            )
+        });
+        match result {
+            Ok(validation_result) => validation_result,
+            Err(e) => {
+                log::error!("failed to check code style: {}", e);
+                None
+            }
+        }
+    }
+
+    fn get_student_file_policy(project_path: &Path) -> Self::StudentFilePolicy {
+        ExternalStudentFilePolicy::new(project_path.to_path_buf())
+    }
+
+    /// Checks if an external plugin is configured for the directory.
+    fn is_exercise_type_correct(path: &Path) -> bool {
+        matches!(
+            manifest::find_config(path, manifest::manifest_dir().as_deref()),
+            Ok(Some(_))
+        )
+    }
+
+    fn clean(&self, path: &Path) -> Result<(), TmcError> {
+        let exercise_path = absolute(path)?;
+        self.call_with_config_timeout(
+            path,
+            Request::Clean {
+                exercise_path: &exercise_path,
+            },
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)] // the test plugin is a shell script
+mod test {
+    use super::*;
+    use std::fs;
+    use tmc_langs_framework::domain::{RunStatus, Strategy};
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    // copies the test exercise to a temp directory
+    fn copy_exercise() -> tempfile::TempDir {
+        let temp = tempfile::tempdir().unwrap();
+        for file in &[".tmcproject.yml", "plugin.sh", "src/main.txt"] {
+            let target = temp.path().join(file);
+            fs::create_dir_all(target.parent().unwrap()).unwrap();
+            fs::copy(Path::new("tests/data/exercise").join(file), target).unwrap();
+        }
+        temp
+    }
+
+    #[test]
+    fn detects_exercise() {
+        init();
+
+        assert!(ExternalPlugin::is_exercise_type_correct(Path::new(
+            "tests/data/exercise"
+        )));
+        assert!(!ExternalPlugin::is_exercise_type_correct(Path::new(
+            "tests/data"
+        )));
+    }
+
+    #[test]
+    fn scans_exercise() {
+        init();
+
+        let temp = copy_exercise();
+        let plugin = ExternalPlugin::new();
+        let desc = plugin
+            .scan_exercise(temp.path(), "exercise".to_string())
+            .unwrap();
+        assert_eq!(desc.name, "exercise");
+        assert_eq!(desc.tests.len(), 1);
+        assert_eq!(desc.tests[0].points, &["1.1"]);
+    }
+
+    #[test]
+    fn runs_tests() {
+        init();
+
+        let temp = copy_exercise();
+        let plugin = ExternalPlugin::new();
+        let run_result = plugin.run_tests(temp.path()).unwrap();
+        assert_eq!(run_result.status, RunStatus::Passed);
+        assert_eq!(run_result.test_results.len(), 1);
+        assert!(run_result.test_results[0].successful);
+    }
+
+    #[test]
+    fn run_tests_timeout() {
+        init();
+
+        let temp = copy_exercise();
+        fs::write(temp.path().join("slow"), "").unwrap();
+        let plugin = ExternalPlugin::new();
+        let run_result = plugin
+            .run_tests_with_timeout(
+                temp.path(),
+                Some(Duration::from_millis(500)),
+                ResourceLimits::default(),
+                &TestFilter::default(),
+            )
+            .unwrap();
+        assert_eq!(run_result.status, RunStatus::TestsFailed);
+        assert_eq!(run_result.test_results[0].name, "Timeout test");
+    }
+
+    #[test]
+    fn checks_code_style() {
+        init();
+
+        let temp = copy_exercise();
+        let plugin = ExternalPlugin::new();
+        let validation_result = plugin.check_code_style(temp.path(), Language::Eng).unwrap();
+        assert_eq!(validation_result.strategy, Strategy::Disabled);
+    }
+
+    #[test]
+    fn cleans() {
+        init();
+
+        let temp = copy_exercise();
+        fs::write(temp.path().join("build_output"), "").unwrap();
+        let plugin = ExternalPlugin::new();
+        plugin.clean(temp.path()).unwrap();
+        assert!(!temp.path().join("build_output").exists());
+    }
+
+    #[test]
+    fn errors_on_timeout() {
+        init();
+
+        let temp = copy_exercise();
+        fs::write(
+            temp.path().join(".tmcproject.yml"),
+            "external_plugin:\n  command: ./plugin.sh\n  timeout_secs: 1\n",
+        )
+        .unwrap();
+        fs::write(temp.path().join("slow"), "").unwrap();
+        let plugin = ExternalPlugin::new();
+        let err = plugin
+            .scan_exercise(temp.path(), "exercise".to_string())
+            .unwrap_err();
+        let is_timeout = match &err {
+            TmcError::Plugin(e) => matches!(
+                e.downcast_ref::<ExternalError>(),
+                Some(ExternalError::Timeout(..))
+            ),
+            _ => false,
+        };
+        assert!(is_timeout, "{:?}", err);
+    }
+
+    #[test]
+    fn errors_on_failure() {
+        init();
+
+        let temp = copy_exercise();
+        fs::write(
+            temp.path().join(".tmcproject.yml"),
+            "external_plugin:\n  command: false\n",
+        )
+        .unwrap();
+        let plugin = ExternalPlugin::new();
+        assert!(plugin.run_tests(temp.path()).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+// Contains the external plugin student file policy
+
+use crate::manifest;
+use std::path::{Path, PathBuf};
+use tmc_langs_framework::StudentFilePolicy;
+
+pub struct ExternalStudentFilePolicy {
+    config_file_parent_path: PathBuf,
+    student_file_paths: Vec<PathBuf>,
+}
+
+impl ExternalStudentFilePolicy {
+    /// Reads the student file paths from the external plugin configuration for the project.
+    /// Uses src if the project has no configuration.
+    pub fn new(config_file_parent_path: PathBuf) -> Self {
+        let student_file_paths = manifest::find_config(
+            &config_file_parent_path,
+            manifest::manifest_dir().as_deref(),
+        )
+        .ok()
+        .flatten()
+        .map(|c| c.student_file_paths)
+        .unwrap_or_else(|| vec![PathBuf::from("src")]);
+        Self {
+            config_file_parent_path,
+            student_file_paths,
+        }
+    }
+}
+
+impl StudentFilePolicy for ExternalStudentFilePolicy {
+    fn get_config_file_parent_path(&self) -> &Path {
+        &self.config_file_parent_path
+    }
+
+    fn is_student_source_file(&self, path: &Path) -> bool {
+        self.student_file_paths.iter().any(|p| path.starts_with(p))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uses_configured_student_file_paths() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join(".tmcproject.yml"),
+            r#"external_plugin:
+  command: plugin
+  student_file_paths:
+    - lib
+    - main.lisp
+"#,
+        )
+        .unwrap();
+
+        let policy = ExternalStudentFilePolicy::new(temp.path().to_path_buf());
+        assert!(policy.is_student_source_file(Path::new("lib/file")));
+        assert!(policy.is_student_source_file(Path::new("main.lisp")));
+        assert!(!policy.is_student_source_file(Path::new("src/file")));
+    }
+}
@@ -0,0 +1,69 @@
+//! Contains the requests sent to external plugins
+
+use serde::Serialize;
+use std::path::Path;
+use tmc_langs_framework::domain::TestFilter;
+
+/// The version of the protocol, sent with every request.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A request written as JSON to the stdin of the external plugin.
+#[derive(Debug, Serialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request<'a> {
+    /// Expects an `ExerciseDesc` in response.
+    ScanExercise {
+        exercise_path: &'a Path,
+        exercise_name: &'a str,
+    },
+    /// Expects a `RunResult` in response.
+    RunTests {
+        exercise_path: &'a Path,
+        filter: &'a TestFilter,
+    },
+    /// Expects a `ValidationResult` or `null` in response.
+    CheckCodeStyle {
+        exercise_path: &'a Path,
+        locale: &'a str,
+    },
+    /// The response is ignored.
+    Clean { exercise_path: &'a Path },
+}
+
+impl Request<'_> {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ScanExercise { .. } => "scan_exercise",
+            Self::RunTests { .. } => "run_tests",
+            Self::CheckCodeStyle { .. } => "check_code_style",
+            Self::Clean { .. } => "clean",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct VersionedRequest<'a> {
+    pub protocol_version: u32,
+    #[serde(flatten)]
+    pub request: Request<'a>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serializes_request() {
+        let request = VersionedRequest {
+            protocol_version: PROTOCOL_VERSION,
+            request: Request::ScanExercise {
+                exercise_path: Path::new("/exercise"),
+                exercise_name: "ex",
+            },
+        };
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"protocol_version":1,"command":"scan_exercise","exercise_path":"/exercise","exercise_name":"ex"}"#
+        );
+    }
+}
@@ -0,0 +1,28 @@
+#!/bin/sh
+# a minimal external plugin for testing
+
+request=$(cat)
+case "$request" in
+*'"command":"scan_exercise"'*)
+    if [ -e slow ]; then
+        sleep 10
+    fi
+    echo '{"name":"exercise","tests":[{"name":"MainTest.test","points":["1.1"]}]}'
+    ;;
+*'"command":"run_tests"'*)
+    if [ -e slow ]; then
+        sleep 10
+    fi
+    echo '{"status":"PASSED","testResults":[{"name":"MainTest.test","successful":true,"points":["1.1"],"message":""}],"logs":{}}'
+    ;;
+*'"command":"check_code_style"'*)
+    echo '{"strategy":"DISABLED","validationErrors":null}'
+    ;;
+*'"command":"clean"'*)
+    rm -f build_output
+    ;;
+*)
+    echo "unknown request $request" >&2
+    exit 1
+    ;;
+esac
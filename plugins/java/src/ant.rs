@@ -13,8 +13,8 @@ use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::time::Duration;
 use tmc_langs_framework::{
-    command::TmcCommand,
-    domain::{ExerciseDesc, RunResult, ValidationResult},
+    command::{OutputWithTimeout, ResourceLimits, TmcCommand},
+    domain::{ExerciseDesc, RunResult, TestFilter, ValidationResult},
     plugin::{Language, LanguagePlugin},
     TmcError,
 };
@@ -95,9 +95,11 @@ impl LanguagePlugin for AntPlugin {
     fn run_tests_with_timeout(
         &self,
         project_root_path: &Path,
-        _timeout: Option<Duration>,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
     ) -> Result<RunResult, TmcError> {
-        Ok(self.run_java_tests(project_root_path)?)
+        Ok(self.run_java_tests(project_root_path, timeout, resource_limits, filter)?)
     }
 
     /// Checks if the directory contains a src and a test directory.
@@ -232,6 +234,9 @@ impl JavaPlugin for AntPlugin {
     fn create_run_result_file(
         &self,
         path: &Path,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
         compile_result: CompileResult,
     ) -> Result<TestRun, JavaError> {
         log::info!("Running tests for project at {}", path.display());
@@ -244,6 +249,19 @@ impl JavaPlugin for AntPlugin {
 
         let test_dir = path.join("test");
         let result_file = path.join("results.txt");
+
+        // the runner only runs the test methods given as arguments
+        let selected_tests = filter.select(&exercise);
+        if selected_tests.is_empty() {
+            fs::write(&result_file, "[]")
+                .map_err(|e| JavaError::FileWrite(result_file.clone(), e))?;
+            return Ok(TestRun {
+                test_results: result_file,
+                stdout: vec![],
+                stderr: vec![],
+            });
+        }
+
         let class_path = self.get_project_class_path(path)?;
 
         let mut arguments = vec![];
@@ -268,7 +286,7 @@ impl JavaPlugin for AntPlugin {
         arguments.push("-cp".to_string());
         arguments.push(class_path);
         arguments.push("fi.helsinki.cs.tmc.testrunner.Main".to_string());
-        for desc in exercise.tests {
+        for desc in selected_tests {
             let mut s = String::new();
             s.push_str(&desc.name.replace(' ', "."));
             s.push('{');
@@ -280,7 +298,11 @@ impl JavaPlugin for AntPlugin {
         log::debug!("java args {} in {}", arguments.join(" "), path.display());
         let mut command = TmcCommand::new("java");
         command.current_dir(path).args(arguments);
-        let output = command.output()?;
+        command.resource_limits(resource_limits);
+        let output = match command.output_with_timeout(timeout)? {
+            OutputWithTimeout::Output(output) => output,
+            OutputWithTimeout::Timeout { .. } => return Err(JavaError::TestsTimedOut),
+        };
 
         Ok(TestRun {
             test_results: result_file,
@@ -296,7 +318,7 @@ mod test {
     use super::*;
     use tempfile::{tempdir, TempDir};
     use tmc_langs_framework::domain::Strategy;
-    use tmc_langs_framework::zip::ZipArchive;
+    use tmc_langs_framework::io::{archive::Archive, tmc_zip::UnzipLimits};
 
     fn init() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -396,7 +418,13 @@ mod test {
         let plugin = AntPlugin::new().unwrap();
         let compile_result = plugin.build(test_path).unwrap();
         let test_run = plugin
-            .create_run_result_file(test_path, compile_result)
+            .create_run_result_file(
+                test_path,
+                None,
+                ResourceLimits::default(),
+                &TestFilter::default(),
+                compile_result,
+            )
             .unwrap();
         log::trace!("stdout: {}", String::from_utf8_lossy(&test_run.stdout));
         log::debug!("stderr: {}", String::from_utf8_lossy(&test_run.stderr));
@@ -431,6 +459,30 @@ mod test {
         assert_eq!(stack_trace.method_name, "fail");
     }
 
+    #[test]
+    fn creates_filtered_run_result_file() {
+        init();
+
+        let temp_dir = copy_test_dir("tests/data/ant_project");
+        let test_path = temp_dir.path();
+        let plugin = AntPlugin::new().unwrap();
+        let compile_result = plugin.build(test_path).unwrap();
+        let filter = TestFilter::new(vec!["ArithTest testSub".to_string()], vec![]);
+        let test_run = plugin
+            .create_run_result_file(
+                test_path,
+                None,
+                ResourceLimits::default(),
+                &filter,
+                compile_result,
+            )
+            .unwrap();
+        let res = fs::read_to_string(test_run.test_results).unwrap();
+        let test_cases: Vec<super::super::TestCase> = serde_json::from_str(&res).unwrap();
+        assert_eq!(test_cases.len(), 1);
+        assert_eq!(test_cases[0].method_name, "testSub");
+    }
+
     #[test]
     fn scans_exercise() {
         init();
@@ -484,17 +536,23 @@ mod test {
 
     #[test]
     fn finds_project_dir_in_zip() {
-        let file = File::open("tests/data/AntProject.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = AntPlugin::find_project_dir_in_zip(&mut zip).unwrap();
+        let mut archive = Archive::open(
+            Path::new("tests/data/AntProject.zip"),
+            UnzipLimits::default(),
+        )
+        .unwrap();
+        let dir = AntPlugin::find_project_dir_in_archive(&mut archive).unwrap();
         assert_eq!(dir, Path::new("Outer/Inner/ant_project"));
     }
 
     #[test]
     fn doesnt_find_project_dir_in_zip() {
-        let file = File::open("tests/data/AntWithoutSrc.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = AntPlugin::find_project_dir_in_zip(&mut zip);
+        let mut archive = Archive::open(
+            Path::new("tests/data/AntWithoutSrc.zip"),
+            UnzipLimits::default(),
+        )
+        .unwrap();
+        let dir = AntPlugin::find_project_dir_in_archive(&mut archive);
         assert!(dir.is_err());
     }
 }
@@ -44,6 +44,8 @@ pub enum JavaError {
     FileCopy(PathBuf, PathBuf, #[source] std::io::Error),
     #[error("Failed to find cache directory")]
     CacheDir,
+    #[error("Tests timed out")]
+    TestsTimedOut,
     #[error("Failed to compile")]
     Compilation(Vec<u8>),
 
@@ -139,6 +139,12 @@ struct TestCase {
     status: TestCaseStatus,
     message: Option<String>,
     exception: Option<CaughtException>,
+    #[serde(default)]
+    duration_ms: Option<u64>,
+    #[serde(default)]
+    stdout: Option<String>,
+    #[serde(default)]
+    stderr: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
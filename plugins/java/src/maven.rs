@@ -15,8 +15,8 @@ use std::process::Stdio;
 use std::time::Duration;
 use tar::Archive;
 use tmc_langs_framework::{
-    command::TmcCommand,
-    domain::{ExerciseDesc, RunResult, ValidationResult},
+    command::{OutputWithTimeout, ResourceLimits, TmcCommand},
+    domain::{ExerciseDesc, RunResult, TestFilter, ValidationResult},
     plugin::{Language, LanguagePlugin},
     TmcError,
 };
@@ -92,9 +92,11 @@ impl LanguagePlugin for MavenPlugin {
     fn run_tests_with_timeout(
         &self,
         project_root_path: &Path,
-        _timeout: Option<Duration>,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
     ) -> Result<RunResult, TmcError> {
-        Ok(self.run_java_tests(project_root_path)?)
+        Ok(self.run_java_tests(project_root_path, timeout, resource_limits, filter)?)
     }
 
     /// Checks if the directory has a pom.xml file.
@@ -230,16 +232,41 @@ impl JavaPlugin for MavenPlugin {
     fn create_run_result_file(
         &self,
         path: &Path,
-        _compile_result: CompileResult,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
+        compile_result: CompileResult,
     ) -> Result<TestRun, JavaError> {
         log::info!("Running tests for maven project at {}", path.display());
 
+        let test_results = path.join("target/test_output.txt");
+
+        // tmc-maven-plugin always runs every test, so the results are filtered afterwards,
+        // but like with ant, nothing is ran if the filter doesn't select any tests
+        if !filter.is_empty() {
+            let exercise =
+                self.scan_exercise_with_compile_result(path, "".to_string(), compile_result)?;
+            if filter.select(&exercise).is_empty() {
+                fs::write(&test_results, "[]")
+                    .map_err(|e| JavaError::FileWrite(test_results.clone(), e))?;
+                return Ok(TestRun {
+                    test_results,
+                    stdout: vec![],
+                    stderr: vec![],
+                });
+            }
+        }
+
         let mvn_path = Self::get_mvn_command()?;
         let mut command = TmcCommand::named("maven", &mvn_path);
         command
             .current_dir(path)
             .arg("fi.helsinki.cs.tmc:tmc-maven-plugin:1.12:test");
-        let output = command.output()?;
+        command.resource_limits(resource_limits);
+        let output = match command.output_with_timeout(timeout)? {
+            OutputWithTimeout::Output(output) => output,
+            OutputWithTimeout::Timeout { .. } => return Err(JavaError::TestsTimedOut),
+        };
 
         if !output.status.success() {
             log::warn!("stdout: {}", String::from_utf8_lossy(&output.stdout));
@@ -256,7 +283,7 @@ impl JavaPlugin for MavenPlugin {
         log::debug!("stderr: {}", String::from_utf8_lossy(&output.stderr));
 
         Ok(TestRun {
-            test_results: path.join("target/test_output.txt"),
+            test_results,
             stdout: output.stdout,
             stderr: output.stderr,
         })
@@ -268,10 +295,9 @@ impl JavaPlugin for MavenPlugin {
 mod test {
     use super::super::{TestCase, TestCaseStatus};
     use super::*;
-    use std::fs::File;
     use tempfile::{tempdir, TempDir};
     use tmc_langs_framework::domain::Strategy;
-    use tmc_langs_framework::zip::ZipArchive;
+    use tmc_langs_framework::io::{archive::Archive, tmc_zip::UnzipLimits};
     use walkdir::WalkDir;
 
     #[cfg(windows)]
@@ -346,7 +372,13 @@ mod test {
         let plugin = MavenPlugin::new().unwrap();
         let compile_result = plugin.build(test_path).unwrap();
         let test_run = plugin
-            .create_run_result_file(test_path, compile_result)
+            .create_run_result_file(
+                test_path,
+                None,
+                ResourceLimits::default(),
+                &TestFilter::default(),
+                compile_result,
+            )
             .unwrap();
         let test_result: Vec<TestCase> =
             serde_json::from_str(&fs::read_to_string(test_run.test_results).unwrap()).unwrap();
@@ -368,6 +400,31 @@ mod test {
         assert_eq!(stack_trace.method_name, "assertEquals");
     }
 
+    #[test]
+    fn creates_empty_run_result_file_without_selected_tests() {
+        init();
+
+        let temp_dir = copy_test_dir("tests/data/maven_exercise");
+        let test_path = temp_dir.path();
+        let plugin = MavenPlugin::new().unwrap();
+        let compile_result = plugin.build(test_path).unwrap();
+        let filter = TestFilter::new(vec!["NoSuchTest".to_string()], vec![]);
+        let test_run = plugin
+            .create_run_result_file(
+                test_path,
+                None,
+                ResourceLimits::default(),
+                &filter,
+                compile_result,
+            )
+            .unwrap();
+        let test_result: Vec<TestCase> =
+            serde_json::from_str(&fs::read_to_string(test_run.test_results).unwrap()).unwrap();
+        assert!(test_result.is_empty());
+        // maven was not ran
+        assert!(test_run.stdout.is_empty());
+    }
+
     #[test]
     fn scans_exercise() {
         init();
@@ -428,17 +485,23 @@ mod test {
 
     #[test]
     fn finds_project_dir_in_zip() {
-        let file = File::open("tests/data/MavenProject.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = MavenPlugin::find_project_dir_in_zip(&mut zip).unwrap();
+        let mut archive = Archive::open(
+            Path::new("tests/data/MavenProject.zip"),
+            UnzipLimits::default(),
+        )
+        .unwrap();
+        let dir = MavenPlugin::find_project_dir_in_archive(&mut archive).unwrap();
         assert_eq!(dir, Path::new("Outer/Inner/maven_exercise"));
     }
 
     #[test]
     fn doesnt_find_project_dir_in_zip() {
-        let file = File::open("tests/data/MavenWithoutSrc.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = MavenPlugin::find_project_dir_in_zip(&mut zip);
+        let mut archive = Archive::open(
+            Path::new("tests/data/MavenWithoutSrc.zip"),
+            UnzipLimits::default(),
+        )
+        .unwrap();
+        let dir = MavenPlugin::find_project_dir_in_archive(&mut archive);
         assert!(dir.is_err());
     }
 }
@@ -7,9 +7,12 @@ use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tmc_langs_framework::{
-    command::TmcCommand,
-    domain::{ExerciseDesc, RunResult, RunStatus, TestDesc, TestResult, ValidationResult},
+    command::{ResourceLimits, TmcCommand},
+    domain::{
+        ExerciseDesc, RunResult, RunStatus, TestDesc, TestFilter, TestResult, ValidationResult,
+    },
     plugin::{Language, LanguagePlugin},
 };
 use walkdir::WalkDir;
@@ -26,8 +29,14 @@ pub(crate) trait JavaPlugin: LanguagePlugin {
     /// Builds the Java project.
     fn build(&self, project_root_path: &Path) -> Result<CompileResult, JavaError>;
 
-    /// Runs the tests for the given project.
-    fn run_java_tests(&self, project_root_path: &Path) -> Result<RunResult, JavaError> {
+    /// Runs the tests selected by the filter for the given project.
+    fn run_java_tests(
+        &self,
+        project_root_path: &Path,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
+    ) -> Result<RunResult, JavaError> {
         log::info!(
             "Running tests for project at {}",
             project_root_path.display()
@@ -38,11 +47,22 @@ pub(crate) trait JavaPlugin: LanguagePlugin {
             return Ok(self.run_result_from_failed_compilation(compile_result));
         }
 
-        let test_result = self.create_run_result_file(project_root_path, compile_result)?;
+        let test_result = match self.create_run_result_file(
+            project_root_path,
+            timeout,
+            resource_limits,
+            filter,
+            compile_result,
+        ) {
+            Ok(test_result) => test_result,
+            Err(JavaError::TestsTimedOut) => return Ok(RunResult::timed_out()),
+            Err(err) => return Err(err),
+        };
         let result = self.parse_test_result(&test_result);
         fs::remove_file(&test_result.test_results)
             .map_err(|e| JavaError::FileRemove(test_result.test_results, e))?;
-        Ok(result?)
+        // the runners filter the tests themselves, this only ensures that no extra results are included
+        Ok(filter.apply(result?))
     }
 
     /// Parses test results.
@@ -57,7 +77,7 @@ pub(crate) trait JavaPlugin: LanguagePlugin {
             if test_case.status == TestCaseStatus::Failed {
                 status = RunStatus::TestsFailed;
             }
-            test_results.push(self.convert_test_case_result(test_case));
+            test_results.push(Self::convert_test_case_result(test_case));
         }
 
         let mut logs = HashMap::new();
@@ -77,7 +97,7 @@ pub(crate) trait JavaPlugin: LanguagePlugin {
     }
 
     /// Converts a Java test case into a tmc-langs test result.
-    fn convert_test_case_result(&self, test_case: TestCase) -> TestResult {
+    fn convert_test_case_result(test_case: TestCase) -> TestResult {
         let mut exceptions = vec![];
         let mut points = vec![];
 
@@ -102,6 +122,9 @@ pub(crate) trait JavaPlugin: LanguagePlugin {
             points,
             message,
             exception: exceptions,
+            duration_ms: test_case.duration_ms,
+            stdout: test_case.stdout,
+            stderr: test_case.stderr,
         }
     }
 
@@ -141,10 +164,14 @@ pub(crate) trait JavaPlugin: LanguagePlugin {
         }
     }
 
-    /// Runs tests and writes the results into a file.
+    /// Runs the tests selected by the filter and writes the results into a file.
+    /// Returns JavaError::TestsTimedOut if the test run exceeds the timeout.
     fn create_run_result_file(
         &self,
         path: &Path,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
         compile_result: CompileResult,
     ) -> Result<TestRun, JavaError>;
 
@@ -308,4 +335,40 @@ openjdk version "1.8.0_252"
             parsed,
         );
     }
+
+    #[test]
+    fn converts_test_case_with_timing() {
+        let test_case = r#"{
+    "className": "ArithTest",
+    "methodName": "testAdd",
+    "pointNames": ["arith-funcs"],
+    "status": "PASSED",
+    "message": null,
+    "exception": null,
+    "durationMs": 12,
+    "stdout": "out",
+    "stderr": "err"
+}"#;
+        let test_case: TestCase = serde_json::from_str(test_case).unwrap();
+        let test_result = AntPlugin::convert_test_case_result(test_case);
+        assert_eq!(test_result.name, "ArithTest testAdd");
+        assert!(test_result.successful);
+        assert_eq!(test_result.duration_ms, Some(12));
+        assert_eq!(test_result.stdout.as_deref(), Some("out"));
+        assert_eq!(test_result.stderr.as_deref(), Some("err"));
+
+        // older runners don't report timing or output
+        let test_case = r#"{
+    "className": "ArithTest",
+    "methodName": "testAdd",
+    "pointNames": [],
+    "status": "FAILED",
+    "message": "fail",
+    "exception": null
+}"#;
+        let test_case: TestCase = serde_json::from_str(test_case).unwrap();
+        let test_result = AntPlugin::convert_test_case_result(test_case);
+        assert_eq!(test_result.duration_ms, None);
+        assert_eq!(test_result.stdout, None);
+    }
 }
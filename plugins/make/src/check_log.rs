@@ -28,12 +28,16 @@ impl CheckLog {
 
                 let points = point_map.remove(&test.id).unwrap_or_default();
                 let exceptions = vec![];
+                let duration_ms = parse_duration_ms(&test.duration);
                 test_results.push(TestResult {
                     name: test.description,
                     successful,
                     points,
                     message: test.message,
                     exception: exceptions,
+                    duration_ms,
+                    stdout: None,
+                    stderr: None,
                 });
             }
         }
@@ -45,6 +49,15 @@ impl CheckLog {
     }
 }
 
+/// Parses a Check duration in seconds into milliseconds.
+/// Check reports a negative duration when timing is not available.
+fn parse_duration_ms(duration: &str) -> Option<u64> {
+    match duration.trim().parse::<f64>() {
+        Ok(secs) if secs >= 0.0 => Some((secs * 1000.0).round() as u64),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TestSuite {
     title: String,
@@ -64,3 +77,49 @@ pub struct Test {
     description: String,
     message: String,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn parses_test_durations() {
+        init();
+
+        let xml = r#"<?xml version="1.0"?>
+<testsuites xmlns="http://check.sourceforge.net/ns">
+  <datetime>2020-05-26 09:47:38</datetime>
+  <suite>
+    <title>Test-Timing</title>
+    <test result="success">
+      <path>.</path>
+      <fn>test_source.c:11</fn>
+      <id>test_one</id>
+      <iteration>0</iteration>
+      <duration>0.012400</duration>
+      <description>test_one</description>
+      <message>Passed</message>
+    </test>
+    <test result="failure">
+      <path>.</path>
+      <fn>test_source.c:17</fn>
+      <id>test_two</id>
+      <iteration>0</iteration>
+      <duration>-1.000000</duration>
+      <description>test_two</description>
+      <message>Failed</message>
+    </test>
+  </suite>
+  <duration>0.000567</duration>
+</testsuites>"#;
+        let check_log: CheckLog = serde_xml_rs::from_str(xml).unwrap();
+        let run_result = check_log.into_run_result(HashMap::new());
+        assert_eq!(run_result.status, RunStatus::TestsFailed);
+        assert_eq!(run_result.test_results[0].duration_ms, Some(12));
+        assert_eq!(run_result.test_results[1].duration_ms, None);
+    }
+}
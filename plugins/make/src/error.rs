@@ -16,6 +16,8 @@ pub enum MakeError {
     RunningTests(ExitStatus, String),
     #[error("Failed to run tests with valgrind. Exit code: {0}, stderr: {1}")]
     RunningTestsWithValgrind(ExitStatus, String),
+    #[error("Tests timed out")]
+    TestsTimedOut,
     #[error("Failed to parse valgrind logs: could not find pids")]
     NoPidsInValgrindLogs,
 
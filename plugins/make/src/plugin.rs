@@ -13,8 +13,8 @@ use std::io::{self, BufRead, BufReader, Read};
 use std::path::Path;
 use std::time::Duration;
 use tmc_langs_framework::{
-    command::TmcCommand,
-    domain::{ExerciseDesc, RunResult, RunStatus, TestDesc, TmcProjectYml},
+    command::{OutputWithTimeout, ResourceLimits, TmcCommand},
+    domain::{ExerciseDesc, RunResult, RunStatus, TestDesc, TestFilter, TmcProjectYml},
     plugin::LanguagePlugin,
     TmcError,
 };
@@ -68,8 +68,16 @@ impl MakePlugin {
     }
 
     /// Runs tests with or without valgrind according to the argument.
-    /// Returns an error if the command finishes unsuccessfully.
-    fn run_tests_with_valgrind(&self, path: &Path, run_valgrind: bool) -> Result<(), MakeError> {
+    /// If a test case is given, Check only runs the test case with that name.
+    /// Returns an error if the command finishes unsuccessfully or exceeds the timeout.
+    fn run_tests_with_valgrind(
+        &self,
+        path: &Path,
+        run_valgrind: bool,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        test_case: Option<&str>,
+    ) -> Result<(), MakeError> {
         let arg = if run_valgrind {
             "run-test-with-valgrind"
         } else {
@@ -79,7 +87,14 @@ impl MakePlugin {
 
         let mut command = TmcCommand::new("make");
         command.current_dir(path).arg(arg);
-        let output = command.output()?;
+        if let Some(test_case) = test_case {
+            command.env("CK_RUN_CASE", test_case);
+        }
+        command.resource_limits(resource_limits);
+        let output = match command.output_with_timeout(timeout)? {
+            OutputWithTimeout::Output(output) => output,
+            OutputWithTimeout::Timeout { .. } => return Err(MakeError::TestsTimedOut),
+        };
 
         log::trace!("stdout: {}", String::from_utf8_lossy(&output.stdout));
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -99,6 +114,63 @@ impl MakePlugin {
         Ok(())
     }
 
+    /// Runs tests with valgrind, falling back to running them without it if valgrind fails.
+    /// Returns whether the tests were ran with valgrind.
+    fn run_tests_with_fallback(
+        &self,
+        path: &Path,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        test_case: Option<&str>,
+    ) -> Result<bool, MakeError> {
+        let mut ran_valgrind = true;
+        let valgrind_run =
+            self.run_tests_with_valgrind(path, true, timeout, resource_limits, test_case);
+        if let Err(error) = valgrind_run {
+            match error {
+                MakeError::MakeCommand(io_error)
+                    if io_error.kind() == io::ErrorKind::PermissionDenied =>
+                {
+                    // failed due to lacking permissions, try to clean and rerun
+                    let _output = self.clean(path)?;
+                    if let Err(err) = self.run_tests_with_valgrind(
+                        path,
+                        false,
+                        timeout,
+                        resource_limits,
+                        test_case,
+                    ) {
+                        log::error!(
+                            "Running with valgrind failed after trying to clean! {}",
+                            err
+                        );
+                        ran_valgrind = false;
+                        log::info!("Running without valgrind");
+                        self.run_tests_with_valgrind(
+                            path,
+                            false,
+                            timeout,
+                            resource_limits,
+                            test_case,
+                        )?;
+                    }
+                }
+                MakeError::MakeCommand(_) | MakeError::RunningTestsWithValgrind(..) => {
+                    ran_valgrind = false;
+                    log::info!("Running without valgrind");
+                    self.run_tests_with_valgrind(path, false, timeout, resource_limits, test_case)?;
+                }
+                // timing out is not a valgrind problem, so the tests are not ran again
+                MakeError::TestsTimedOut => return Err(MakeError::TestsTimedOut),
+                err => {
+                    log::warn!("unexpected error {:?}", err);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(ran_valgrind)
+    }
+
     /// Tries to build the project at the given directory, returns whether
     /// the process finished successfully or not.
     fn builds(&self, dir: &Path) -> Result<bool, MakeError> {
@@ -123,7 +195,7 @@ impl LanguagePlugin for MakePlugin {
             return MakeError::NoExerciseFound(path.to_path_buf()).into();
         }
 
-        self.run_tests_with_valgrind(path, false)?;
+        self.run_tests_with_valgrind(path, false, None, ResourceLimits::default(), None)?;
 
         let available_points_path = path.join("test/tmc_available_points.txt");
 
@@ -137,7 +209,9 @@ impl LanguagePlugin for MakePlugin {
     fn run_tests_with_timeout(
         &self,
         path: &Path,
-        _timeout: Option<Duration>,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
     ) -> Result<RunResult, TmcError> {
         if !self.builds(path)? {
             return Ok(RunResult {
@@ -147,37 +221,20 @@ impl LanguagePlugin for MakePlugin {
             });
         }
 
+        // each test is registered as its own test case, but Check can only be told to run a single one,
+        // so other filters are applied to the results
+        let test_case = match (filter.tests.as_slice(), filter.points.is_empty()) {
+            ([test], true) => Some(test.as_str()),
+            _ => None,
+        };
+
         // try to run valgrind
-        let mut ran_valgrind = true;
-        let valgrind_run = self.run_tests_with_valgrind(path, true);
-        if let Err(error) = valgrind_run {
-            match error {
-                MakeError::MakeCommand(io_error)
-                    if io_error.kind() == io::ErrorKind::PermissionDenied =>
-                {
-                    // failed due to lacking permissions, try to clean and rerun
-                    let _output = self.clean(path)?;
-                    if let Err(err) = self.run_tests_with_valgrind(path, false) {
-                        log::error!(
-                            "Running with valgrind failed after trying to clean! {}",
-                            err
-                        );
-                        ran_valgrind = false;
-                        log::info!("Running without valgrind");
-                        self.run_tests_with_valgrind(path, false)?;
-                    }
-                }
-                MakeError::MakeCommand(_) | MakeError::RunningTestsWithValgrind(..) => {
-                    ran_valgrind = false;
-                    log::info!("Running without valgrind");
-                    self.run_tests_with_valgrind(path, false)?;
-                }
-                err => {
-                    log::warn!("unexpected error {:?}", err);
-                    return Err(err.into());
-                }
-            }
-        }
+        let ran_valgrind =
+            match self.run_tests_with_fallback(path, timeout, resource_limits, test_case) {
+                Ok(ran_valgrind) => ran_valgrind,
+                Err(MakeError::TestsTimedOut) => return Ok(RunResult::timed_out()),
+                Err(err) => return Err(err.into()),
+            };
 
         let base_test_path = path.join("test");
 
@@ -241,7 +298,7 @@ impl LanguagePlugin for MakePlugin {
             }
         }
 
-        Ok(run_result)
+        Ok(filter.apply(run_result))
     }
 
     fn get_student_file_policy(project_path: &Path) -> Self::StudentFilePolicy {
@@ -275,7 +332,7 @@ mod test {
     use super::*;
     use std::path::PathBuf;
     use tempfile::{tempdir, TempDir};
-    use tmc_langs_framework::zip::ZipArchive;
+    use tmc_langs_framework::io::{archive::Archive, tmc_zip::UnzipLimits};
 
     fn init() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -344,6 +401,23 @@ mod test {
         assert_eq!(point, "1.1");
     }
 
+    #[test]
+    fn runs_tests_filtered() {
+        init();
+
+        let temp = copy_test("tests/data/passing");
+        let plugin = MakePlugin::new();
+        let filter = TestFilter::new(vec!["test_one".to_string()], vec![]);
+        let run_result = plugin.run_tests_filtered(temp.path(), &filter).unwrap();
+        assert_eq!(run_result.status, RunStatus::Passed);
+        assert_eq!(run_result.test_results.len(), 1);
+
+        let filter = TestFilter::new(vec![], vec!["2.1".to_string()]);
+        let run_result = plugin.run_tests_filtered(temp.path(), &filter).unwrap();
+        assert_eq!(run_result.status, RunStatus::TestsFailed);
+        assert_eq!(run_result.test_results[0].name, "Tests found test");
+    }
+
     #[test]
     fn runs_tests_failing() {
         init();
@@ -391,19 +465,44 @@ mod test {
         todo!("valgrind results in random order?")
     }
 
+    #[test]
+    fn runs_tests_timeout() {
+        init();
+
+        let temp = copy_test("tests/data/timeout");
+        let plugin = MakePlugin::new();
+        let run_result = plugin
+            .run_tests_with_timeout(
+                temp.path(),
+                Some(Duration::from_millis(100)),
+                ResourceLimits::default(),
+                &TestFilter::default(),
+            )
+            .unwrap();
+        assert_eq!(run_result.status, RunStatus::TestsFailed);
+        assert_eq!(run_result.test_results.len(), 1);
+        assert_eq!(run_result.test_results[0].name, "Timeout test");
+    }
+
     #[test]
     fn finds_project_dir_in_zip() {
-        let file = File::open("tests/data/MakeProject.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = MakePlugin::find_project_dir_in_zip(&mut zip).unwrap();
+        let mut archive = Archive::open(
+            Path::new("tests/data/MakeProject.zip"),
+            UnzipLimits::default(),
+        )
+        .unwrap();
+        let dir = MakePlugin::find_project_dir_in_archive(&mut archive).unwrap();
         assert_eq!(dir, Path::new("Outer/Inner/passing"));
     }
 
     #[test]
     fn doesnt_find_project_dir_in_zip() {
-        let file = File::open("tests/data/MakeWithoutSrc.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = MakePlugin::find_project_dir_in_zip(&mut zip);
+        let mut archive = Archive::open(
+            Path::new("tests/data/MakeWithoutSrc.zip"),
+            UnzipLimits::default(),
+        )
+        .unwrap();
+        let dir = MakePlugin::find_project_dir_in_archive(&mut archive);
         assert!(dir.is_err());
     }
 }
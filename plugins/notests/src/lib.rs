@@ -1,14 +1,14 @@
 //! Language plugin for no_tests exercises
 
 use tmc_langs_framework::{
-    domain::{ExerciseDesc, RunResult, RunStatus, TestDesc, TestResult},
+    command::ResourceLimits,
+    domain::{ExerciseDesc, RunResult, RunStatus, TestDesc, TestFilter, TestResult},
+    io::archive::Archive,
     policy::EverythingIsStudentFilePolicy,
-    zip::ZipArchive,
     LanguagePlugin, StudentFilePolicy, TmcError,
 };
 
 use std::collections::HashMap;
-use std::io::{Read, Seek};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
@@ -48,6 +48,8 @@ impl LanguagePlugin for NoTestsPlugin {
         &self,
         path: &Path,
         _timeout: Option<Duration>,
+        _resource_limits: ResourceLimits,
+        _filter: &TestFilter,
     ) -> Result<RunResult, TmcError> {
         Ok(RunResult {
             status: RunStatus::Passed,
@@ -57,6 +59,9 @@ impl LanguagePlugin for NoTestsPlugin {
                 points: self.get_points(path),
                 message: "".to_string(),
                 exception: vec![],
+                duration_ms: None,
+                stdout: None,
+                stderr: None,
             }],
             logs: HashMap::new(),
         })
@@ -73,9 +78,7 @@ impl LanguagePlugin for NoTestsPlugin {
             .unwrap_or(false)
     }
 
-    fn find_project_dir_in_zip<R: Read + Seek>(
-        _zip_archive: &mut ZipArchive<R>,
-    ) -> Result<PathBuf, TmcError> {
+    fn find_project_dir_in_archive(_archive: &mut Archive) -> Result<PathBuf, TmcError> {
         Ok(PathBuf::from(""))
     }
 
@@ -65,6 +65,9 @@ impl PythonTestResult {
             message: parse_test_message(self.message),
             points: self.points,
             exception: self.backtrace,
+            duration_ms: None,
+            stdout: None,
+            stderr: None,
         }
     }
 }
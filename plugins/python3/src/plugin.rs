@@ -2,7 +2,7 @@
 
 use crate::error::PythonError;
 use crate::policy::Python3StudentFilePolicy;
-use crate::{LocalPy, PythonTestResult, LOCAL_PY};
+use crate::{parse_test_name, LocalPy, PythonTestResult, LOCAL_PY};
 
 use std::collections::HashMap;
 use std::fs::{self, File};
@@ -10,8 +10,8 @@ use std::io::BufReader;
 use std::path::Path;
 use std::time::Duration;
 use tmc_langs_framework::{
-    command::{OutputWithTimeout, TmcCommand},
-    domain::{ExerciseDesc, RunResult, RunStatus, TestDesc, TestResult},
+    command::{OutputWithTimeout, ResourceLimits, TmcCommand},
+    domain::{ExerciseDesc, RunResult, RunStatus, TestDesc, TestFilter, TestResult},
     plugin::LanguagePlugin,
     TmcError,
 };
@@ -45,7 +45,12 @@ impl LanguagePlugin for Python3Plugin {
                 .map_err(|e| PythonError::FileRemove(available_points_json.clone(), e))?;
         }
 
-        let run_result = run_tmc_command(exercise_directory, &["available_points"], None);
+        let run_result = run_tmc_command(
+            exercise_directory,
+            &["available_points"],
+            None,
+            ResourceLimits::default(),
+        );
         if let Err(error) = run_result {
             log::error!("Failed to scan exercise. {}", error);
         }
@@ -63,7 +68,28 @@ impl LanguagePlugin for Python3Plugin {
         &self,
         exercise_directory: &Path,
         timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
     ) -> Result<RunResult, TmcError> {
+        // the test program runs the tests named in its arguments, or all of them if none are given
+        let mut test_names = vec![];
+        if !filter.is_empty() {
+            let exercise_desc = self.scan_exercise(exercise_directory, "".to_string())?;
+            // the names in the results are shortened, so both formats are accepted
+            test_names = exercise_desc
+                .tests
+                .into_iter()
+                .filter(|t| {
+                    filter.matches(&t.name, &t.points)
+                        || filter.tests.contains(&parse_test_name(t.name.clone()))
+                })
+                .map(|t| t.name)
+                .collect();
+            if test_names.is_empty() {
+                return Ok(RunResult::new(RunStatus::Passed, vec![], HashMap::new()));
+            }
+        }
+
         let test_results_json = exercise_directory.join(".tmc_test_results.json");
         // remove any existing results json
         if test_results_json.exists() {
@@ -71,21 +97,10 @@ impl LanguagePlugin for Python3Plugin {
                 .map_err(|e| PythonError::FileRemove(test_results_json.clone(), e))?;
         }
 
-        let output = run_tmc_command(exercise_directory, &[], timeout)?;
+        let test_names: Vec<&str> = test_names.iter().map(String::as_str).collect();
+        let output = run_tmc_command(exercise_directory, &test_names, timeout, resource_limits)?;
         if let OutputWithTimeout::Timeout { .. } = output {
-            return Ok(RunResult {
-                status: RunStatus::TestsFailed,
-                test_results: vec![TestResult {
-                    name: "Timeout test".to_string(),
-                    successful: false,
-                    points: vec![],
-                    message:
-                        "Tests timed out.\nMake sure you don't have an infinite loop in your code."
-                            .to_string(),
-                    exception: vec![],
-                }],
-                logs: HashMap::new(),
-            });
+            return Ok(RunResult::timed_out());
         }
 
         let parse_res = parse_test_result(&test_results_json);
@@ -133,6 +148,7 @@ fn run_tmc_command(
     path: &Path,
     extra_args: &[&str],
     timeout: Option<Duration>,
+    resource_limits: ResourceLimits,
 ) -> Result<OutputWithTimeout, PythonError> {
     let path = path
         .canonicalize()
@@ -156,11 +172,8 @@ fn run_tmc_command(
         .args(&common_args)
         .args(extra_args)
         .current_dir(path);
-    let output = if let Some(timeout) = timeout {
-        command.wait_with_timeout(timeout)?
-    } else {
-        OutputWithTimeout::Output(command.output()?)
-    };
+    command.resource_limits(resource_limits);
+    let output = command.output_with_timeout(timeout)?;
 
     log::trace!("stdout: {}", String::from_utf8_lossy(output.stdout()));
     log::debug!("stderr: {}", String::from_utf8_lossy(output.stderr()));
@@ -206,8 +219,10 @@ mod test {
     use super::*;
     use std::path::{Path, PathBuf};
     use tempfile::{tempdir, TempDir};
-    use tmc_langs_framework::zip::ZipArchive;
-    use tmc_langs_framework::{domain::RunStatus, plugin::LanguagePlugin};
+    use tmc_langs_framework::io::archive::Archive;
+    use tmc_langs_framework::{
+        domain::RunStatus, io::tmc_zip::UnzipLimits, plugin::LanguagePlugin,
+    };
 
     fn init() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -361,6 +376,25 @@ mod test {
         assert!(temp_path.join("leave").exists());
     }
 
+    #[test]
+    fn runs_tests_filtered() {
+        init();
+        let plugin = Python3Plugin::new();
+
+        let temp = copy_test("tests/data/complex");
+        let filter = TestFilter::new(vec!["GreeterTest: test_greets".to_string()], vec![]);
+        let run_result = plugin.run_tests_filtered(temp.path(), &filter).unwrap();
+        assert_eq!(run_result.test_results.len(), 1);
+        assert_eq!(run_result.test_results[0].name, "GreeterTest: test_greets");
+
+        let filter = TestFilter::new(vec![], vec!["1.1".to_string()]);
+        let run_result = plugin.run_tests_filtered(temp.path(), &filter).unwrap();
+        assert!(!run_result.test_results.is_empty());
+        for test_result in run_result.test_results {
+            assert!(test_result.name.starts_with("TavaraTest: "));
+        }
+    }
+
     #[test]
     fn timeout() {
         init();
@@ -368,7 +402,12 @@ mod test {
 
         let temp = copy_test("tests/data/timeout");
         let timeout = plugin
-            .run_tests_with_timeout(temp.path(), Some(std::time::Duration::from_millis(1)))
+            .run_tests_with_timeout(
+                temp.path(),
+                Some(std::time::Duration::from_millis(1)),
+                ResourceLimits::default(),
+                &TestFilter::default(),
+            )
             .unwrap();
         assert_eq!(timeout.test_results[0].name, "Timeout test");
     }
@@ -376,18 +415,24 @@ mod test {
     #[test]
     fn finds_project_dir_in_zip() {
         init();
-        let file = File::open("tests/data/PythonProject.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = Python3Plugin::find_project_dir_in_zip(&mut zip).unwrap();
+        let mut archive = Archive::open(
+            Path::new("tests/data/PythonProject.zip"),
+            UnzipLimits::default(),
+        )
+        .unwrap();
+        let dir = Python3Plugin::find_project_dir_in_archive(&mut archive).unwrap();
         assert_eq!(dir, Path::new("Outer/Inner/project"));
     }
 
     #[test]
     fn doesnt_find_project_dir_in_zip() {
         init();
-        let file = File::open("tests/data/PythonWithoutSrc.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = Python3Plugin::find_project_dir_in_zip(&mut zip);
+        let mut archive = Archive::open(
+            Path::new("tests/data/PythonWithoutSrc.zip"),
+            UnzipLimits::default(),
+        )
+        .unwrap();
+        let dir = Python3Plugin::find_project_dir_in_archive(&mut archive);
         assert!(dir.is_err());
     }
 
@@ -398,7 +443,9 @@ mod test {
         let archive = Path::new("tests/data/student_exercise.zip");
         let temp = tempfile::tempdir().unwrap();
         assert!(!temp.path().join("src/source.py").exists());
-        plugin.extract_project(archive, temp.path(), false).unwrap();
+        plugin
+            .extract_project(archive, temp.path(), false, UnzipLimits::default())
+            .unwrap();
         assert!(temp.path().join("src/source.py").exists());
         assert!(temp.path().join("test/test.py").exists());
         assert!(temp.path().join("tmc/tmc").exists());
@@ -422,7 +469,9 @@ mod test {
             fs::read_to_string(temp.path().join("tmc/tmc")).unwrap(),
             "NEW"
         );
-        plugin.extract_project(archive, temp.path(), false).unwrap();
+        plugin
+            .extract_project(archive, temp.path(), false, UnzipLimits::default())
+            .unwrap();
         assert_eq!(
             fs::read_to_string(temp.path().join("src/source.py")).unwrap(),
             "NEW"
@@ -458,7 +507,9 @@ mod test {
             fs::read_to_string(temp.path().join("tmc/tmc")).unwrap(),
             "NEW"
         );
-        plugin.extract_project(archive, temp.path(), true).unwrap();
+        plugin
+            .extract_project(archive, temp.path(), true, UnzipLimits::default())
+            .unwrap();
         assert_eq!(
             fs::read_to_string(temp.path().join("src/source.py")).unwrap(),
             "NEW"
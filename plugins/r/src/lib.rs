@@ -85,6 +85,9 @@ impl From<RTestResult> for TestResult {
             points: r_test_result.points,
             message: r_test_result.message,
             exception: r_test_result.backtrace,
+            duration_ms: None,
+            stdout: None,
+            stderr: None,
         }
     }
 }
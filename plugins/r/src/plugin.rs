@@ -6,15 +6,14 @@ use crate::RStudentFilePolicy;
 use super::RRunResult;
 
 use tmc_langs_framework::{
-    command::TmcCommand,
-    domain::{ExerciseDesc, RunResult, TestDesc},
-    zip::ZipArchive,
+    command::{OutputWithTimeout, ResourceLimits, TmcCommand},
+    domain::{ExerciseDesc, RunResult, TestDesc, TestFilter},
+    io::archive::Archive,
     LanguagePlugin, TmcError,
 };
 
 use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::{Read, Seek};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
@@ -73,7 +72,9 @@ impl LanguagePlugin for RPlugin {
     fn run_tests_with_timeout(
         &self,
         path: &Path,
-        _timeout: Option<Duration>,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
     ) -> Result<RunResult, TmcError> {
         // delete results json
         let results_path = path.join(".results.json");
@@ -90,7 +91,11 @@ impl LanguagePlugin for RPlugin {
         };
         let mut command = TmcCommand::new("Rscript");
         command.current_dir(path).args(args);
-        let out = command.output()?;
+        command.resource_limits(resource_limits);
+        let out = match command.output_with_timeout(timeout)? {
+            OutputWithTimeout::Output(output) => output,
+            OutputWithTimeout::Timeout { .. } => return Ok(RunResult::timed_out()),
+        };
 
         log::trace!("stdout: {}", String::from_utf8_lossy(&out.stdout));
         log::debug!("stderr: {}", String::from_utf8_lossy(&out.stderr));
@@ -114,7 +119,8 @@ impl LanguagePlugin for RPlugin {
             RError::JsonDeserialize(results_path, e)
         })?;
 
-        Ok(run_result.into())
+        // the test runner has no option for selecting tests, so the results are filtered instead
+        Ok(filter.apply(run_result.into()))
     }
 
     fn get_student_file_policy(project_path: &Path) -> Self::StudentFilePolicy {
@@ -126,14 +132,10 @@ impl LanguagePlugin for RPlugin {
         path.join("R").exists() || path.join("tests/testthat").exists()
     }
 
-    fn find_project_dir_in_zip<R: Read + Seek>(
-        zip_archive: &mut ZipArchive<R>,
-    ) -> Result<PathBuf, TmcError> {
-        for i in 0..zip_archive.len() {
-            // zips don't necessarily contain entries for intermediate directories,
-            // so we need to check every path for R
-            let file = zip_archive.by_index(i)?;
-            let file_path = file.sanitized_name();
+    fn find_project_dir_in_archive(archive: &mut Archive) -> Result<PathBuf, TmcError> {
+        // archives don't necessarily contain entries for intermediate directories,
+        // so we need to check every path for R
+        for (file_path, _kind) in archive.list_entries()? {
             // todo: do in one pass somehow
             if file_path.components().any(|c| c.as_os_str() == "R") {
                 let path: PathBuf = file_path
@@ -158,6 +160,7 @@ mod test {
     use super::*;
     use std::path::PathBuf;
     use tmc_langs_framework::domain::RunStatus;
+    use tmc_langs_framework::io::tmc_zip::UnzipLimits;
 
     fn init() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -279,19 +282,41 @@ mod test {
         assert!(logs.contains("unexpected 'in'"));
     }
 
+    #[test]
+    fn run_tests_timeout() {
+        init();
+        let plugin = RPlugin {};
+        let temp = copy_test("tests/data/timeout");
+
+        let run = plugin
+            .run_tests_with_timeout(
+                temp.path(),
+                Some(Duration::from_millis(100)),
+                ResourceLimits::default(),
+                &TestFilter::default(),
+            )
+            .unwrap();
+        assert_eq!(run.status, RunStatus::TestsFailed);
+        assert_eq!(run.test_results.len(), 1);
+        assert_eq!(run.test_results[0].name, "Timeout test");
+    }
+
     #[test]
     fn finds_project_dir_in_zip() {
-        let file = File::open("tests/data/RProject.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = RPlugin::find_project_dir_in_zip(&mut zip).unwrap();
+        let mut archive =
+            Archive::open(Path::new("tests/data/RProject.zip"), UnzipLimits::default()).unwrap();
+        let dir = RPlugin::find_project_dir_in_archive(&mut archive).unwrap();
         assert_eq!(dir, Path::new("Outer/Inner/simple_all_tests_pass"));
     }
 
     #[test]
     fn doesnt_find_project_dir_in_zip() {
-        let file = File::open("tests/data/RWithoutR.zip").unwrap();
-        let mut zip = ZipArchive::new(file).unwrap();
-        let dir = RPlugin::find_project_dir_in_zip(&mut zip);
+        let mut archive = Archive::open(
+            Path::new("tests/data/RWithoutR.zip"),
+            UnzipLimits::default(),
+        )
+        .unwrap();
+        let dir = RPlugin::find_project_dir_in_archive(&mut archive);
         assert!(dir.is_err());
     }
 }
@@ -14,6 +14,45 @@ pub fn create_app() -> App<'static, 'static> {
         .about(env!("CARGO_PKG_DESCRIPTION"))
         .setting(AppSettings::SubcommandRequiredElseHelp)
 
+        .subcommand(SubCommand::with_name("audit-course")
+            .about("Finds all exercise root directories inside the exercise-path, scans them and reports points awarded by more than one exercise, tests and exercises without points, no-tests points that clash with the points of tests, unusual point names and exercises recognised by more than one language plugin.")
+            .long_about(schema_leaked::<Vec<CourseAuditIssue>>())
+            .arg(Arg::with_name("exercise-path")
+                .help("Path to the directory where the projects reside.")
+                .long("exercise-path")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("output-path")
+                .help("If given, the issues will be written to this path. Overwritten if it already exists.")
+                .long("output-path")
+                .takes_value(true)))
+
+        .subcommand(SubCommand::with_name("calculate-checksum")
+            .about("Calculates an MD5 checksum of the exercise's files, excluding hidden files, in the same way as the checksum of the exercise on the TMC server.")
+            .long_about(schema_leaked::<String>())
+            .arg(Arg::with_name("exercise-path")
+                .help("Path to the directory where the exercise resides.")
+                .long("exercise-path")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("output-path")
+                .help("If given, the checksum will be written to this path. Overwritten if it already exists.")
+                .long("output-path")
+                .takes_value(true)))
+
+        .subcommand(SubCommand::with_name("calculate-points")
+            .about("Runs the tests for the exercise and calculates which points the student would be awarded. A point is only awarded if every test that may award it passes.")
+            .long_about(schema_leaked::<ExercisePoints>())
+            .arg(Arg::with_name("exercise-path")
+                .help("Path to the directory where the exercise resides.")
+                .long("exercise-path")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("output-path")
+                .help("If given, the points will be written to this path. Overwritten if it already exists.")
+                .long("output-path")
+                .takes_value(true)))
+
         .subcommand(SubCommand::with_name("checkstyle")
             .about("Checks the code style for the given exercise.")
             .long_about(schema_leaked::<Option<ValidationResult>>())
@@ -49,6 +88,9 @@ pub fn create_app() -> App<'static, 'static> {
                 .long("exercise-path")
                 .required(true)
                 .takes_value(true))
+            .arg(Arg::with_name("non-deterministic")
+                .help("Stores the files in filesystem order with the current time as their timestamp. By default, the files are sorted and their timestamps and permissions are normalised so that the same files always produce the same archive.")
+                .long("non-deterministic"))
             .arg(Arg::with_name("output-path")
                 .help("Path to the output ZIP archive. Overwritten if it already exists.")
                 .long("output-path")
@@ -57,14 +99,43 @@ pub fn create_app() -> App<'static, 'static> {
 
         .subcommand(create_core_app()) // "core"
 
+        .subcommand(SubCommand::with_name("detect-plugins")
+            .about("Lists every language plugin that recognises the exercise and the plugin that is used for it. The plugin can be set explicitly with the plugin key in .tmcproject.yml.")
+            .long_about(schema_leaked::<PluginDetection>())
+            .arg(Arg::with_name("exercise-path")
+                .help("Path to the directory where the exercise resides.")
+                .long("exercise-path")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("output-path")
+                .help("If given, the results will be written to this path. Overwritten if it already exists.")
+                .long("output-path")
+                .takes_value(true)))
+
         .subcommand(SubCommand::with_name("extract-project")
-            .about("Extracts an exercise from a ZIP archive. If the output-path is a project root, the plugin's student file policy will be used to avoid overwriting student files.")
+            .about("Extracts an exercise from a zip, tar, tar.gz or tar.zst archive. The format is detected from the contents of the archive. If the output-path is a project root, the plugin's student file policy will be used to avoid overwriting student files.")
             .long_about(SCHEMA_NULL)
             .arg(Arg::with_name("archive-path")
-                .help("Path to the ZIP archive.")
+                .help("Path to the archive.")
                 .long("archive-path")
                 .required(true)
                 .takes_value(true))
+            .arg(Arg::with_name("max-unzip-compression-ratio")
+                .help("The maximum ratio of the uncompressed size of a file in the archive to its compressed size. Defaults to 200.")
+                .long("max-unzip-compression-ratio")
+                .takes_value(true))
+            .arg(Arg::with_name("max-unzip-entries")
+                .help("The maximum number of entries in the archive. Defaults to 50000.")
+                .long("max-unzip-entries")
+                .takes_value(true))
+            .arg(Arg::with_name("max-unzip-file-size")
+                .help("The maximum uncompressed size of a single file in the archive in bytes. Defaults to 100 MiB.")
+                .long("max-unzip-file-size")
+                .takes_value(true))
+            .arg(Arg::with_name("max-unzip-total-size")
+                .help("The maximum uncompressed size of the contents of the archive in bytes. Defaults to 500 MiB.")
+                .long("max-unzip-total-size")
+                .takes_value(true))
             .arg(Arg::with_name("output-path")
                 .help("Path to the directory where the archive will be extracted.")
                 .long("output-path")
@@ -97,6 +168,19 @@ pub fn create_app() -> App<'static, 'static> {
                 .long("output-path")
                 .takes_value(true)))
 
+        .subcommand(SubCommand::with_name("lint-exercise")
+            .about("Checks the solution and stub markers in the exercise's source files for problems that would produce a broken stub or solution, such as unterminated blocks.")
+            .long_about(schema_leaked::<Vec<meta_syntax::MetaSyntaxLint>>())
+            .arg(Arg::with_name("exercise-path")
+                .help("Path to the directory where the exercise resides.")
+                .long("exercise-path")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("output-path")
+                .help("If given, the problems will be written to this path. Overwritten if it already exists.")
+                .long("output-path")
+                .takes_value(true)))
+
         .subcommand(SubCommand::with_name("prepare-solutions")
             .about("Processes the exercise files in exercise-path, removing all code marked as stubs.")
             .long_about(SCHEMA_NULL)
@@ -126,27 +210,52 @@ pub fn create_app() -> App<'static, 'static> {
                 .takes_value(true)))
 
         .subcommand(SubCommand::with_name("prepare-submission")
-            .about("Takes a submission ZIP archive and turns it into an archive with reset test files, and tmc-params, ready for further processing.")
+            .about("Takes a submission archive and turns it into an archive with reset test files, and tmc-params, ready for further processing.")
             .long_about(SCHEMA_NULL)
+            .arg(Arg::with_name("output-format")
+                .help("The format of the resulting archive. Defaults to tar.")
+                .long("output-format")
+                .possible_values(&["zip", "tar", "tar.gz", "tar.zst"])
+                .conflicts_with("output-zip")
+                .takes_value(true))
             .arg(Arg::with_name("output-zip")
-                .help("Packages the submission into a zip archive instead of a tar.")
+                .help("Packages the submission into a zip archive instead of a tar. Same as --output-format zip.")
                 .long("output-zip"))
             .arg(Arg::with_name("clone-path")
                 .help("Path to exercise's clone path, where the unmodified test files will be copied from.")
                 .long("clone-path")
                 .takes_value(true)
                 .required(true))
+            .arg(Arg::with_name("max-unzip-compression-ratio")
+                .help("The maximum ratio of the uncompressed size of a file in the submission or stub archive to its compressed size. Defaults to 200.")
+                .long("max-unzip-compression-ratio")
+                .takes_value(true))
+            .arg(Arg::with_name("max-unzip-entries")
+                .help("The maximum number of entries in the submission or stub archive. Defaults to 50000.")
+                .long("max-unzip-entries")
+                .takes_value(true))
+            .arg(Arg::with_name("max-unzip-file-size")
+                .help("The maximum uncompressed size of a single file in the submission or stub archive in bytes. Defaults to 100 MiB.")
+                .long("max-unzip-file-size")
+                .takes_value(true))
+            .arg(Arg::with_name("max-unzip-total-size")
+                .help("The maximum uncompressed size of the contents of the submission or stub archive in bytes. Defaults to 500 MiB.")
+                .long("max-unzip-total-size")
+                .takes_value(true))
+            .arg(Arg::with_name("non-deterministic")
+                .help("Stores the files in filesystem order with their current metadata. By default, the files are sorted and their timestamps, permissions and owners are normalised so that the same submission always produces the same archive.")
+                .long("non-deterministic"))
             .arg(Arg::with_name("output-path")
                 .help("Path to the resulting archive. Overwritten if it already exists.")
                 .long("output-path")
                 .required(true)
                 .takes_value(true))
             .arg(Arg::with_name("stub-zip-path")
-                .help("If given, the tests will be copied from this stub ZIP instead, effectively ignoring hidden tests.")
+                .help("If given, the tests will be copied from this stub archive instead, effectively ignoring hidden tests. The format is detected from the contents of the archive.")
                 .long("stub-zip-path")
                 .takes_value(true))
             .arg(Arg::with_name("submission-path")
-                .help("Path to the submission ZIP archive.")
+                .help("Path to the submission archive. The format is detected from the contents of the archive.")
                 .long("submission-path")
                 .required(true)
                 .takes_value(true))
@@ -160,6 +269,44 @@ pub fn create_app() -> App<'static, 'static> {
                 .long("top-level-dir-name")
                 .takes_value(true)))
 
+        .subcommand(SubCommand::with_name("preview-exercise")
+            .about("Shows what prepare-stubs and prepare-solutions would do to each file in the exercise as unified diffs of the original file compared to the stub and solution versions.")
+            .long_about(schema_leaked::<Vec<FilePreview>>())
+            .arg(Arg::with_name("exercise-path")
+                .help("Path to the directory where the exercise resides.")
+                .long("exercise-path")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("output-path")
+                .help("If given, the diffs will be written to this path. Overwritten if it already exists.")
+                .long("output-path")
+                .takes_value(true)))
+
+        .subcommand(SubCommand::with_name("run-sandboxed")
+            .about("Runs a submission archive created with prepare-submission locally like the grading sandbox does. The archive is unpacked into a temporary directory, .tmcparams is sourced and the tests are ran in a separate process with the given limits. On Linux, the process is isolated with namespaces when they are available.")
+            .long_about(schema_leaked::<SandboxResult>())
+            .arg(Arg::with_name("archive-path")
+                .help("Path to the tar archive created with prepare-submission.")
+                .long("archive-path")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("max-processes")
+                .help("The maximum number of processes the test run may have.")
+                .long("max-processes")
+                .takes_value(true))
+            .arg(Arg::with_name("memory-limit")
+                .help("The maximum size of the virtual memory of each process of the test run in bytes.")
+                .long("memory-limit")
+                .takes_value(true))
+            .arg(Arg::with_name("output-path")
+                .help("If given, the results will be written to this path. Overwritten if it already exists.")
+                .long("output-path")
+                .takes_value(true))
+            .arg(Arg::with_name("timeout")
+                .help("The maximum time the test run may take in seconds.")
+                .long("timeout")
+                .takes_value(true)))
+
         .subcommand(SubCommand::with_name("run-tests")
             .about("Run the tests for the exercise using the appropriate language plugin.")
             .long_about(schema_leaked::<RunResult>())
@@ -173,6 +320,12 @@ pub fn create_app() -> App<'static, 'static> {
                 .long("exercise-path")
                 .required(true)
                 .takes_value(true))
+            .arg(Arg::with_name("format")
+                .help("The format of the test results written to output-path. Defaults to json.")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["json", "junit", "tap"])
+                .requires("output-path"))
             .arg(Arg::with_name("locale")
                 .help("Language as a three letter ISO 639-3 code, e.g. 'eng' or 'fin'. Required if checkstyle-output-path is given.")
                 .long("locale")
@@ -180,7 +333,22 @@ pub fn create_app() -> App<'static, 'static> {
             .arg(Arg::with_name("output-path")
                 .help("If defined, the test results will be written to this path. Overwritten if it already exists.")
                 .long("output-path")
-                .takes_value(true)))
+                .takes_value(true))
+            .arg(Arg::with_name("point")
+                .help("Only runs the tests that may award the given point. Can be given multiple times, and combined with test.")
+                .long("point")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("test")
+                .help("Only runs the test with the given name, as given by scan-exercise or in the test results. Can be given multiple times, and combined with point. The C# runner can't select tests, so it runs every test and the other results are discarded.")
+                .long("test")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("watch")
+                .help("Keeps running and re-runs the tests every time a student source file changes. The results of each run are printed as a separate line with the in-progress status.")
+                .long("watch")))
 
         .subcommand(SubCommand::with_name("scan-exercise")
             .about("Produces a description of an exercise using the appropriate language plugin.")
@@ -194,6 +362,19 @@ pub fn create_app() -> App<'static, 'static> {
                 .help("If given, the scan results will be written to this path. Overwritten if it already exists.")
                 .long("output-path")
                 .takes_value(true)))
+
+        .subcommand(SubCommand::with_name("verify-exercises")
+            .about("Finds all exercise root directories inside the exercise-path and checks that the stub of each exercise compiles and fails its tests, that the solution passes all of them and that the stub and solution have the same points as the exercise.")
+            .long_about(schema_leaked::<Vec<ExerciseVerification>>())
+            .arg(Arg::with_name("exercise-path")
+                .help("Path to the directory where the projects reside.")
+                .long("exercise-path")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("output-path")
+                .help("If given, the verification results will be written to this path. Overwritten if it already exists.")
+                .long("output-path")
+                .takes_value(true)))
 }
 
 /// Constructs the core sub-command.
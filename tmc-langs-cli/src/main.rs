@@ -13,18 +13,31 @@ use std::env;
 use std::fmt::Debug;
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tempfile::NamedTempFile;
 use tmc_langs_core::oauth2::{
     basic::BasicTokenType, AccessToken, EmptyExtraTokenFields, Scope, StandardTokenResponse,
 };
 use tmc_langs_core::{CoreError, FeedbackAnswer, TmcCore, Token};
-use tmc_langs_framework::{domain::ValidationResult, error::CommandNotFound};
+use tmc_langs_framework::{
+    command::ResourceLimits,
+    domain::{RunResult, TestFilter, ValidationResult},
+    error::CommandNotFound,
+    io::{
+        archive::ArchiveFormat,
+        tmc_zip::{Reproducibility, UnzipLimits},
+    },
+    report,
+};
 use tmc_langs_util::{
-    task_executor::{self, TmcParams},
+    task_executor::{self, StudentFileWatcher, TmcParams},
     Language,
 };
 use url::Url;
 
+/// How often the student files are checked for changes in watch mode.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 #[quit::main]
 fn main() {
     env_logger::init();
@@ -94,6 +107,105 @@ fn run() -> Result<()> {
 
     // enforces that each branch must return a PrintToken as proof of having printed the output
     let _printed: PrintToken = match matches.subcommand() {
+        ("audit-course", Some(matches)) => {
+            let exercise_path = matches.value_of("exercise-path").unwrap();
+            let exercise_path = Path::new(exercise_path);
+
+            let output_path = matches.value_of("output-path");
+            let output_path = output_path.map(Path::new);
+
+            let issues = task_executor::audit_course(exercise_path);
+
+            if let Some(output_path) = output_path {
+                write_result_to_file_as_json(&issues, output_path)?;
+            }
+
+            let output = Output {
+                status: Status::Finished,
+                message: Some(format!(
+                    "found {} issues in the exercises at {}",
+                    issues.len(),
+                    exercise_path.display(),
+                )),
+                result: OutputResult::ExecutedCommand,
+                percent_done: 1.0,
+                data: Some(issues),
+            };
+            print_output(&output)?
+        }
+        ("calculate-checksum", Some(matches)) => {
+            let exercise_path = matches.value_of("exercise-path").unwrap();
+            let exercise_path = Path::new(exercise_path);
+
+            let output_path = matches.value_of("output-path");
+            let output_path = output_path.map(Path::new);
+
+            let checksum =
+                task_executor::calculate_exercise_checksum(exercise_path).with_context(|| {
+                    format!(
+                        "Failed to calculate checksum for exercise at {}",
+                        exercise_path.display()
+                    )
+                })?;
+
+            if let Some(output_path) = output_path {
+                write_result_to_file_as_json(&checksum, output_path)?;
+            }
+
+            let output = Output {
+                status: Status::Finished,
+                message: Some(format!(
+                    "calculated checksum for {}",
+                    exercise_path.display(),
+                )),
+                result: OutputResult::ExecutedCommand,
+                percent_done: 1.0,
+                data: Some(checksum),
+            };
+            print_output(&output)?
+        }
+        ("calculate-points", Some(matches)) => {
+            let exercise_path = matches.value_of("exercise-path").unwrap();
+            let exercise_path = Path::new(exercise_path);
+
+            let output_path = matches.value_of("output-path");
+            let output_path = output_path.map(Path::new);
+
+            let exercise_name = exercise_path.file_name().with_context(|| {
+                format!(
+                    "No file name found in exercise path {}",
+                    exercise_path.display()
+                )
+            })?;
+
+            let exercise_name = exercise_name.to_str().with_context(|| {
+                format!(
+                    "Exercise path's file name '{:?}' was not valid UTF8",
+                    exercise_name
+                )
+            })?;
+
+            let points = task_executor::calculate_points(exercise_path, exercise_name.to_string())
+                .with_context(|| {
+                    format!(
+                        "Failed to calculate points for exercise at {}",
+                        exercise_path.display()
+                    )
+                })?;
+
+            if let Some(output_path) = output_path {
+                write_result_to_file_as_json(&points, output_path)?;
+            }
+
+            let output = Output {
+                status: Status::Finished,
+                message: Some(format!("calculated points for {}", exercise_path.display(),)),
+                result: OutputResult::ExecutedCommand,
+                percent_done: 1.0,
+                data: Some(points),
+            };
+            print_output(&output)?
+        }
         ("checkstyle", Some(matches)) => {
             let exercise_path = matches.value_of("exercise-path").unwrap();
             let exercise_path = Path::new(exercise_path);
@@ -139,9 +251,12 @@ fn run() -> Result<()> {
             let output_path = matches.value_of("output-path").unwrap();
             let output_path = Path::new(output_path);
 
-            let data = task_executor::compress_project(exercise_path).with_context(|| {
-                format!("Failed to compress project at {}", exercise_path.display())
-            })?;
+            let reproducibility = into_reproducibility(matches);
+
+            let data = task_executor::compress_project(exercise_path, reproducibility)
+                .with_context(|| {
+                    format!("Failed to compress project at {}", exercise_path.display())
+                })?;
 
             if let Some(parent) = output_path.parent() {
                 fs::create_dir_all(parent)
@@ -168,6 +283,33 @@ fn run() -> Result<()> {
             print_output(&output)?
         }
         ("core", Some(matches)) => run_core(matches)?,
+        ("detect-plugins", Some(matches)) => {
+            let exercise_path = matches.value_of("exercise-path").unwrap();
+            let exercise_path = Path::new(exercise_path);
+
+            let output_path = matches.value_of("output-path");
+            let output_path = output_path.map(Path::new);
+
+            let detection = task_executor::detect_plugins(exercise_path).with_context(|| {
+                format!(
+                    "Failed to detect plugins for exercise at {}",
+                    exercise_path.display()
+                )
+            })?;
+
+            if let Some(output_path) = output_path {
+                write_result_to_file_as_json(&detection, output_path)?;
+            }
+
+            let output = Output {
+                status: Status::Finished,
+                message: Some(format!("detected plugins for {}", exercise_path.display(),)),
+                result: OutputResult::ExecutedCommand,
+                percent_done: 1.0,
+                data: Some(detection),
+            };
+            print_output(&output)?
+        }
         ("extract-project", Some(matches)) => {
             let archive_path = matches.value_of("archive-path").unwrap();
             let archive_path = Path::new(archive_path);
@@ -175,9 +317,11 @@ fn run() -> Result<()> {
             let output_path = matches.value_of("output-path").unwrap();
             let output_path = Path::new(output_path);
 
-            task_executor::extract_project(archive_path, output_path, true).with_context(|| {
-                format!("Failed to extract project at {}", output_path.display())
-            })?;
+            let unzip_limits = into_unzip_limits(matches)?;
+            task_executor::extract_project(archive_path, output_path, true, unzip_limits)
+                .with_context(|| {
+                    format!("Failed to extract project at {}", output_path.display())
+                })?;
 
             let output = Output::<()> {
                 status: Status::Finished,
@@ -245,6 +389,34 @@ fn run() -> Result<()> {
             };
             print_output(&output)?
         }
+        ("lint-exercise", Some(matches)) => {
+            let exercise_path = matches.value_of("exercise-path").unwrap();
+            let exercise_path = Path::new(exercise_path);
+
+            let output_path = matches.value_of("output-path");
+            let output_path = output_path.map(Path::new);
+
+            let lints = task_executor::lint_exercise(exercise_path).with_context(|| {
+                format!("Failed to lint exercise at {}", exercise_path.display())
+            })?;
+
+            if let Some(output_path) = output_path {
+                write_result_to_file_as_json(&lints, output_path)?;
+            }
+
+            let output = Output {
+                status: Status::Finished,
+                message: Some(format!(
+                    "found {} problems in exercise at {}",
+                    lints.len(),
+                    exercise_path.display(),
+                )),
+                result: OutputResult::ExecutedCommand,
+                percent_done: 1.0,
+                data: Some(lints),
+            };
+            print_output(&output)?
+        }
         ("prepare-solutions", Some(matches)) => {
             let exercise_path = matches.value_of("exercise-path").unwrap();
             let exercise_path = Path::new(exercise_path);
@@ -305,7 +477,15 @@ fn run() -> Result<()> {
             print_output(&output)?
         }
         ("prepare-submission", Some(matches)) => {
-            let output_zip = matches.is_present("output-zip");
+            let output_format = if matches.is_present("output-zip") {
+                ArchiveFormat::Zip
+            } else {
+                matches
+                    .value_of("output-format")
+                    .map(str::parse)
+                    .transpose()?
+                    .unwrap_or(ArchiveFormat::Tar)
+            };
 
             let clone_path = matches.value_of("clone-path").unwrap();
             let clone_path = Path::new(clone_path);
@@ -353,6 +533,10 @@ fn run() -> Result<()> {
             let top_level_dir_name = matches.value_of("top-level-dir-name");
             let top_level_dir_name = top_level_dir_name.map(str::to_string);
 
+            let reproducibility = into_reproducibility(matches);
+
+            let unzip_limits = into_unzip_limits(matches)?;
+
             task_executor::prepare_submission(
                 submission_path,
                 output_path,
@@ -360,7 +544,9 @@ fn run() -> Result<()> {
                 tmc_params,
                 clone_path,
                 stub_zip_path,
-                output_zip,
+                output_format,
+                unzip_limits,
+                reproducibility,
             )?;
 
             let output = Output::<()> {
@@ -376,45 +562,95 @@ fn run() -> Result<()> {
             };
             print_output(&output)?
         }
-        ("run-tests", Some(matches)) => {
-            let checkstyle_output_path = matches.value_of("checkstyle-output-path");
-            let checkstyle_output_path: Option<&Path> = checkstyle_output_path.map(Path::new);
-
+        ("preview-exercise", Some(matches)) => {
             let exercise_path = matches.value_of("exercise-path").unwrap();
             let exercise_path = Path::new(exercise_path);
 
-            let locale = matches.value_of("locale");
-
             let output_path = matches.value_of("output-path");
             let output_path = output_path.map(Path::new);
 
-            let test_result = task_executor::run_tests(exercise_path).with_context(|| {
-                format!(
-                    "Failed to run tests for exercise at {}",
-                    exercise_path.display()
-                )
+            let previews = task_executor::preview_exercise(exercise_path).with_context(|| {
+                format!("Failed to preview exercise at {}", exercise_path.display())
             })?;
 
             if let Some(output_path) = output_path {
-                write_result_to_file_as_json(&test_result, output_path)?;
+                write_result_to_file_as_json(&previews, output_path)?;
             }
 
-            // todo: checkstyle results in stdout?
-            if let Some(checkstyle_output_path) = checkstyle_output_path {
-                let locale = into_locale(locale.unwrap())?;
+            let output = Output {
+                status: Status::Finished,
+                message: Some(format!(
+                    "previewed {} changed files in exercise at {}",
+                    previews.len(),
+                    exercise_path.display(),
+                )),
+                result: OutputResult::ExecutedCommand,
+                percent_done: 1.0,
+                data: Some(previews),
+            };
+            print_output(&output)?
+        }
+        ("run-sandboxed", Some(matches)) => {
+            let archive_path = matches.value_of("archive-path").unwrap();
+            let archive_path = Path::new(archive_path);
+
+            let output_path = matches.value_of("output-path");
+            let output_path = output_path.map(Path::new);
 
-                run_checkstyle_write_results(exercise_path, Some(checkstyle_output_path), locale)?;
+            let timeout = matches.value_of("timeout").map(into_u64).transpose()?;
+            let timeout = timeout.map(Duration::from_secs);
+
+            let resource_limits = ResourceLimits {
+                address_space_bytes: matches.value_of("memory-limit").map(into_u64).transpose()?,
+                max_processes: matches
+                    .value_of("max-processes")
+                    .map(into_u64)
+                    .transpose()?,
+                ..ResourceLimits::default()
+            };
+
+            let tmc_langs_cli =
+                env::current_exe().context("Failed to get the path of the current executable")?;
+            let sandbox_result = task_executor::run_sandboxed(
+                archive_path,
+                &tmc_langs_cli,
+                timeout,
+                resource_limits,
+            )
+            .with_context(|| format!("Failed to run {} in sandbox", archive_path.display()))?;
+
+            if let Some(output_path) = output_path {
+                write_result_to_file_as_json(&sandbox_result, output_path)?;
             }
 
             let output = Output {
                 status: Status::Finished,
-                message: Some(format!("ran tests for {}", exercise_path.display(),)),
+                message: Some(format!("ran {} in sandbox", archive_path.display())),
                 result: OutputResult::ExecutedCommand,
                 percent_done: 1.0,
-                data: Some(test_result),
+                data: Some(sandbox_result),
             };
             print_output(&output)?
         }
+        ("run-tests", Some(matches)) => {
+            let exercise_path = matches.value_of("exercise-path").unwrap();
+            let exercise_path = Path::new(exercise_path);
+
+            if matches.is_present("watch") {
+                watch_tests(matches, exercise_path)?
+            } else {
+                let test_result = run_tests_and_write_results(matches, exercise_path)?;
+
+                let output = Output {
+                    status: Status::Finished,
+                    message: Some(format!("ran tests for {}", exercise_path.display(),)),
+                    result: OutputResult::ExecutedCommand,
+                    percent_done: 1.0,
+                    data: Some(test_result),
+                };
+                print_output(&output)?
+            }
+        }
         ("scan-exercise", Some(matches)) => {
             let exercise_path = matches.value_of("exercise-path").unwrap();
             let exercise_path = Path::new(exercise_path);
@@ -455,6 +691,40 @@ fn run() -> Result<()> {
             };
             print_output(&output)?
         }
+        ("verify-exercises", Some(matches)) => {
+            let exercise_path = matches.value_of("exercise-path").unwrap();
+            let exercise_path = Path::new(exercise_path);
+
+            let output_path = matches.value_of("output-path");
+            let output_path = output_path.map(Path::new);
+
+            let verifications =
+                task_executor::verify_exercises(exercise_path).with_context(|| {
+                    format!("Failed to verify exercises at {}", exercise_path.display())
+                })?;
+
+            if let Some(output_path) = output_path {
+                write_result_to_file_as_json(&verifications, output_path)?;
+            }
+
+            let failed = verifications
+                .iter()
+                .filter(|v| !v.problems.is_empty())
+                .count();
+            let output = Output {
+                status: Status::Finished,
+                message: Some(format!(
+                    "verified {} exercises at {}, {} with problems",
+                    verifications.len(),
+                    exercise_path.display(),
+                    failed,
+                )),
+                result: OutputResult::ExecutedCommand,
+                percent_done: 1.0,
+                data: Some(verifications),
+            };
+            print_output(&output)?
+        }
         _ => unreachable!("missing subcommand arm"),
     };
     Ok(())
@@ -568,7 +838,11 @@ fn run_core(matches: &ArgMatches) -> Result<PrintToken> {
             log::debug!("downloaded old submission to {}", temp_zip.path().display());
 
             // extract submission
-            task_executor::extract_student_files(temp_zip.path(), output_path)?;
+            task_executor::extract_student_files(
+                temp_zip.path(),
+                output_path,
+                UnzipLimits::default(),
+            )?;
             log::debug!("extracted project");
 
             let output = Output::<()> {
@@ -1146,6 +1420,119 @@ fn run_core(matches: &ArgMatches) -> Result<PrintToken> {
     Ok(printed)
 }
 
+/// Runs the tests for the run-tests command and writes the results and checkstyle results into the given files, if any.
+fn run_tests_and_write_results(matches: &ArgMatches, exercise_path: &Path) -> Result<RunResult> {
+    let checkstyle_output_path = matches.value_of("checkstyle-output-path");
+    let checkstyle_output_path: Option<&Path> = checkstyle_output_path.map(Path::new);
+
+    let locale = matches.value_of("locale");
+
+    let output_path = matches.value_of("output-path");
+    let output_path = output_path.map(Path::new);
+
+    let points = matches.values_of("point").unwrap_or_default();
+    let points = points.map(str::to_string).collect();
+
+    let tests = matches.values_of("test").unwrap_or_default();
+    let tests = tests.map(str::to_string).collect();
+
+    let filter = TestFilter::new(tests, points);
+    let test_result =
+        task_executor::run_tests_filtered(exercise_path, &filter).with_context(|| {
+            format!(
+                "Failed to run tests for exercise at {}",
+                exercise_path.display()
+            )
+        })?;
+
+    if let Some(output_path) = output_path {
+        match matches.value_of("format") {
+            Some("junit") => {
+                let suite_name = exercise_path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let report = report::to_junit_xml(&test_result, &suite_name);
+                write_report_to_file(&report, output_path)?;
+            }
+            Some("tap") => {
+                let report = report::to_tap(&test_result);
+                write_report_to_file(&report, output_path)?;
+            }
+            _ => write_result_to_file_as_json(&test_result, output_path)?,
+        }
+    }
+
+    // todo: checkstyle results in stdout?
+    if let Some(checkstyle_output_path) = checkstyle_output_path {
+        let locale = into_locale(locale.unwrap())?;
+
+        run_checkstyle_write_results(exercise_path, Some(checkstyle_output_path), locale)?;
+    }
+
+    Ok(test_result)
+}
+
+/// Runs the tests every time a student source file of the exercise changes, printing the results of each run.
+/// Errors from individual runs are printed without stopping the watch, so this only returns if printing fails.
+fn watch_tests(matches: &ArgMatches, exercise_path: &Path) -> Result<PrintToken> {
+    let mut watcher = StudentFileWatcher::new(exercise_path).with_context(|| {
+        format!(
+            "Failed to watch the student files of the exercise at {}",
+            exercise_path.display()
+        )
+    })?;
+    log::debug!("watching {:?}", watcher.student_source_files());
+
+    let mut message = format!("ran tests for {}", exercise_path.display());
+    loop {
+        match run_tests_and_write_results(matches, exercise_path) {
+            Ok(test_result) => {
+                let output = Output {
+                    status: Status::InProgress,
+                    message: Some(message),
+                    result: OutputResult::ExecutedCommand,
+                    percent_done: 0.0,
+                    data: Some(test_result),
+                };
+                print_output(&output)?;
+            }
+            Err(e) => {
+                let causes: Vec<String> = e.chain().map(|e| format!("Caused by: {}", e)).collect();
+                let output = Output {
+                    status: Status::InProgress,
+                    message: Some(error_message_special_casing(&e)),
+                    result: OutputResult::Error,
+                    percent_done: 0.0,
+                    data: Some(ErrorData {
+                        kind: solve_error_kind(&e),
+                        trace: causes,
+                    }),
+                };
+                print_output(&output)?;
+            }
+        }
+
+        // the watcher's state is from before the test run,
+        // so files saved while the tests were running are reported right away
+        let changed = watcher.wait_for_change(WATCH_POLL_INTERVAL);
+        let changed: Vec<_> = changed
+            .iter()
+            .map(|p| {
+                p.strip_prefix(exercise_path)
+                    .unwrap_or(p)
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        message = format!(
+            "ran tests for {} after changes to {}",
+            exercise_path.display(),
+            changed.join(", ")
+        );
+    }
+}
+
 fn print_output<T: Serialize + Debug>(output: &Output<T>) -> Result<PrintToken> {
     let result = serde_json::to_string(&output)
         .with_context(|| format!("Failed to convert {:?} to JSON", output))?;
@@ -1171,6 +1558,12 @@ fn write_result_to_file_as_json<T: Serialize>(result: &T, output_path: &Path) ->
     Ok(())
 }
 
+fn write_report_to_file(report: &str, output_path: &Path) -> Result<()> {
+    fs::write(output_path, report)
+        .with_context(|| format!("Failed to write test report to {}", output_path.display()))?;
+    Ok(())
+}
+
 fn into_usize(arg: &str) -> Result<usize> {
     usize::from_str_radix(arg, 10).with_context(|| {
         format!(
@@ -1180,6 +1573,50 @@ fn into_usize(arg: &str) -> Result<usize> {
     })
 }
 
+fn into_u64(arg: &str) -> Result<u64> {
+    arg.parse::<u64>().with_context(|| {
+        format!(
+            "Failed to convert argument to a non-negative integer: {}",
+            arg,
+        )
+    })
+}
+
+// reads the max-unzip-* arguments, using the defaults for the ones that are not given
+fn into_unzip_limits(matches: &ArgMatches) -> Result<UnzipLimits> {
+    let defaults = UnzipLimits::default();
+    Ok(UnzipLimits {
+        max_entries: matches
+            .value_of("max-unzip-entries")
+            .map(into_u64)
+            .transpose()?
+            .unwrap_or(defaults.max_entries),
+        max_file_size: matches
+            .value_of("max-unzip-file-size")
+            .map(into_u64)
+            .transpose()?
+            .unwrap_or(defaults.max_file_size),
+        max_total_size: matches
+            .value_of("max-unzip-total-size")
+            .map(into_u64)
+            .transpose()?
+            .unwrap_or(defaults.max_total_size),
+        max_compression_ratio: matches
+            .value_of("max-unzip-compression-ratio")
+            .map(into_u64)
+            .transpose()?
+            .unwrap_or(defaults.max_compression_ratio),
+    })
+}
+
+fn into_reproducibility(matches: &ArgMatches) -> Reproducibility {
+    if matches.is_present("non-deterministic") {
+        Reproducibility::NonDeterministic
+    } else {
+        Reproducibility::Deterministic
+    }
+}
+
 fn into_locale(arg: &str) -> Result<Language> {
     Language::from_locale(arg)
         .or_else(|| Language::from_639_1(arg))
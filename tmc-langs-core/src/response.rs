@@ -12,7 +12,7 @@ use serde::{
 use std::fmt;
 use std::str::FromStr;
 use thiserror::Error;
-use tmc_langs_util::ValidationResult;
+use tmc_langs_util::{ResourceLimits, ValidationResult};
 
 /// Models the responses from tmc-server, which can either
 /// be some successful response, a single error or a list of errors
@@ -177,6 +177,19 @@ pub struct Exercise {
     pub solution_zip_url: Option<String>,
 }
 
+impl Exercise {
+    /// Returns the resource limits for running the exercise's tests.
+    /// The server gives the memory limit in megabytes, and it is used as the address space limit.
+    pub fn resource_limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            address_space_bytes: self
+                .memory_limit
+                .map(|megabytes| megabytes as u64 * 1024 * 1024),
+            ..ResourceLimits::default()
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct CourseExercise {
     pub id: usize,
@@ -495,6 +508,49 @@ mod test {
         assert!(serde_json::from_value::<CourseDetails>(details).is_ok());
     }
 
+    #[test]
+    fn exercise_memory_limit_to_resource_limits() {
+        init();
+
+        let exercise = serde_json::json!({
+            "id": 1,
+            "name": "n",
+            "locked": false,
+            "deadline_description": null,
+            "deadline": null,
+            "soft_deadline": null,
+            "soft_deadline_description": null,
+            "checksum": "c",
+            "return_url": "r",
+            "zip_url": "z",
+            "returnable": true,
+            "requires_review": false,
+            "attempted": false,
+            "completed": false,
+            "reviewed": false,
+            "all_review_points_given": true,
+            "memory_limit": 200,
+            "runtime_params": [],
+            "valgrind_strategy": "fail",
+            "code_review_requests_enabled": false,
+            "run_tests_locally_action_enabled": true,
+            "latest_submission_url": null,
+            "latest_submission_id": null,
+            "solution_zip_url": null
+        });
+        let mut exercise: Exercise = serde_json::from_value(exercise).unwrap();
+        assert_eq!(
+            exercise.resource_limits(),
+            ResourceLimits {
+                address_space_bytes: Some(200 * 1024 * 1024),
+                ..ResourceLimits::default()
+            }
+        );
+
+        exercise.memory_limit = None;
+        assert!(exercise.resource_limits().is_empty());
+    }
+
     #[test]
     fn feedback_kind_de() {
         init();
@@ -23,7 +23,7 @@ use std::result::Result as StdResult;
 use std::thread;
 use std::time::Duration;
 use tempfile::NamedTempFile;
-use tmc_langs_util::task_executor;
+use tmc_langs_util::{task_executor, Reproducibility, TestFilter, UnzipLimits};
 use walkdir::WalkDir;
 
 pub type Token =
@@ -290,7 +290,7 @@ impl TmcCore {
             progress += step;
 
             self.report_progress("Extracting exercise...", StatusType::Extracting, progress);
-            task_executor::extract_project(zip_file.path(), target, true)?;
+            task_executor::extract_project(zip_file.path(), target, true, UnzipLimits::default())?;
             progress += step;
         }
         self.report_complete("Finished downloading and extracting exercises.");
@@ -386,7 +386,8 @@ impl TmcCore {
         locale: Option<Language>,
     ) -> Result<NewSubmission> {
         // compress
-        let compressed = task_executor::compress_project(submission_path)?;
+        let compressed =
+            task_executor::compress_project(submission_path, Reproducibility::Deterministic)?;
         let mut file = NamedTempFile::new().map_err(CoreError::TempFile)?;
         file.write_all(&compressed)
             .map_err(|e| CoreError::FileWrite(file.path().to_path_buf(), e))?;
@@ -434,6 +435,20 @@ impl TmcCore {
         Ok(task_executor::run_tests(path)?)
     }
 
+    /// Runs tests for the project with the resource limits in the exercise's metadata from the server.
+    /// The limits configured in the project's .tmcproject.yml take precedence.
+    ///
+    /// # Errors
+    /// Returns an error if no matching language plugin for the project is found,
+    /// or if the plugin returns an error while trying to run the tests.
+    pub fn run_tests_for_exercise(&self, path: &Path, exercise: &Exercise) -> Result<RunResult> {
+        Ok(task_executor::run_tests_with_limits(
+            path,
+            exercise.resource_limits(),
+            &TestFilter::default(),
+        )?)
+    }
+
     /// Sends feedback.
     ///
     /// # Errors
@@ -468,7 +483,8 @@ impl TmcCore {
             StatusType::Compressing,
             0.0,
         );
-        let compressed = task_executor::compress_project(submission_path)?;
+        let compressed =
+            task_executor::compress_project(submission_path, Reproducibility::Deterministic)?;
         self.report_progress(
             "Compressed submission. Creating temporary file...",
             StatusType::Processing,
@@ -685,7 +701,8 @@ impl TmcCore {
         locale: Option<Language>,
     ) -> Result<NewSubmission> {
         // compress
-        let compressed = task_executor::compress_project(submission_path)?;
+        let compressed =
+            task_executor::compress_project(submission_path, Reproducibility::Deterministic)?;
         let mut file = NamedTempFile::new().map_err(CoreError::TempFile)?;
         file.write_all(&compressed)
             .map_err(|e| CoreError::FileWrite(file.path().to_path_buf(), e))?;
@@ -701,7 +718,7 @@ impl TmcCore {
     pub fn download_model_solution(&self, solution_download_url: Url, target: &Path) -> Result<()> {
         let zip_file = NamedTempFile::new().map_err(CoreError::TempFile)?;
         self.download_from(solution_download_url, zip_file.path())?;
-        task_executor::extract_project(zip_file.path(), target, false)?;
+        task_executor::extract_project(zip_file.path(), target, false, UnzipLimits::default())?;
         Ok(())
     }
 
@@ -1,18 +1,118 @@
 //! Custom wrapper for Command that supports timeouts and contains custom error handling.
 
 use crate::{Result, TmcError};
-use std::io::Read;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
 use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
-use std::process::{Command, ExitStatus, Output, Stdio};
-use std::thread;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::{
+    mpsc::{self, RecvTimeoutError, Sender},
+    Arc,
+};
+use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
+/// How long the processes of a timed out command are given to exit after SIGTERM before they are killed.
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// The default maximum number of bytes captured from each of a command's output streams.
+pub const DEFAULT_OUTPUT_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Limits on the resources a command and the processes it spawns may use.
+/// On Unix, the limits are applied with setrlimit before the command is executed. Ignored on other platforms.
+///
+/// Note that the address space limit also counts memory that is only reserved,
+/// which runtimes like the JVM do liberally, and that the process limit counts
+/// all processes of the user running the command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct ResourceLimits {
+    /// The maximum size of a process's virtual memory in bytes.
+    #[serde(default)]
+    pub address_space_bytes: Option<u64>,
+    /// The maximum amount of CPU time a process may use in seconds.
+    #[serde(default)]
+    pub cpu_time_secs: Option<u64>,
+    /// The maximum number of processes.
+    #[serde(default)]
+    pub max_processes: Option<u64>,
+    /// The maximum size of a file created by a process in bytes.
+    #[serde(default)]
+    pub file_size_bytes: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Checks whether any of the limits are set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns these limits with the ones that are not set taken from the other limits.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            address_space_bytes: self.address_space_bytes.or(other.address_space_bytes),
+            cpu_time_secs: self.cpu_time_secs.or(other.cpu_time_secs),
+            max_processes: self.max_processes.or(other.max_processes),
+            file_size_bytes: self.file_size_bytes.or(other.file_size_bytes),
+        }
+    }
+
+    /// Sets the limits for the current process.
+    #[cfg(unix)]
+    fn apply(&self) -> std::io::Result<()> {
+        // the type of the resource parameter differs between platforms
+        macro_rules! set_limit {
+            ($resource:expr, $limit:expr) => {
+                if let Some(limit) = $limit {
+                    let mut rlimit = libc::rlimit {
+                        rlim_cur: 0,
+                        rlim_max: 0,
+                    };
+                    if libc::getrlimit($resource, &mut rlimit) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    // the hard limit can't be raised without privileges
+                    let limit = (limit as libc::rlim_t).min(rlimit.rlim_max);
+                    rlimit.rlim_cur = limit;
+                    rlimit.rlim_max = limit;
+                    if libc::setrlimit($resource, &rlimit) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+            };
+        }
+
+        // safe: getrlimit and setrlimit only read and write the given struct
+        unsafe {
+            set_limit!(libc::RLIMIT_AS, self.address_space_bytes);
+            set_limit!(libc::RLIMIT_CPU, self.cpu_time_secs);
+            set_limit!(libc::RLIMIT_NPROC, self.max_processes);
+            set_limit!(libc::RLIMIT_FSIZE, self.file_size_bytes);
+        }
+        Ok(())
+    }
+}
+
+/// One of the output streams of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+type LineCallback = Arc<dyn Fn(OutputStream, &str) + Send + Sync>;
+
 // todo: collect args?
 pub struct TmcCommand {
     name: &'static str,
     path: PathBuf,
     command: Command,
+    output_limit: usize,
+    line_callback: Option<LineCallback>,
+    input: Option<Vec<u8>>,
+    stdin_set: bool,
 }
 
 impl TmcCommand {
@@ -22,6 +122,10 @@ impl TmcCommand {
             command: Command::new(&path),
             name,
             path,
+            output_limit: DEFAULT_OUTPUT_LIMIT,
+            line_callback: None,
+            input: None,
+            stdin_set: false,
         }
     }
 
@@ -31,90 +135,359 @@ impl TmcCommand {
             command: Command::new(&path),
             name,
             path,
+            output_limit: DEFAULT_OUTPUT_LIMIT,
+            line_callback: None,
+            input: None,
+            stdin_set: false,
+        }
+    }
+
+    /// Applies the resource limits to the command's process and any processes it spawns.
+    pub fn resource_limits(&mut self, limits: ResourceLimits) -> &mut Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+
+            if !limits.is_empty() {
+                // safe: the hook only calls getrlimit and setrlimit which are async-signal-safe
+                unsafe {
+                    self.pre_exec(move || limits.apply());
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            if !limits.is_empty() {
+                log::warn!("resource limits are not supported on this platform");
+            }
         }
+        self
+    }
+
+    /// Sets the maximum number of bytes captured from stdout and stderr each.
+    /// When a stream outputs more, only the latest output is kept and
+    /// a marker with the number of bytes dropped is inserted at the start.
+    pub fn output_limit(&mut self, limit: usize) -> &mut Self {
+        self.output_limit = limit;
+        self
+    }
+
+    /// Sets a callback that is called with each line of output as soon as it is read.
+    /// Lines longer than the output limit are passed on in parts.
+    pub fn on_line<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(OutputStream, &str) + Send + Sync + 'static,
+    {
+        self.line_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets data that is written to the command's stdin when it is ran with one of the output methods.
+    /// The stdin is closed after the data has been written.
+    pub fn input(&mut self, data: Vec<u8>) -> &mut Self {
+        self.input = Some(data);
+        self
+    }
+
+    // shadows command's stdin so that the output methods know not to replace it
+    pub fn stdin<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.stdin_set = true;
+        self.command.stdin(cfg);
+        self
     }
 
     // shadows command's status
     pub fn status(&mut self) -> Result<ExitStatus> {
-        self.deref_mut().status().map_err(|e| {
-            if let std::io::ErrorKind::NotFound = e.kind() {
-                TmcError::CommandNotFound(crate::error::CommandNotFound {
-                    name: self.name,
-                    path: self.path.clone(),
-                    source: e,
-                })
-            } else {
-                TmcError::CommandFailed(self.name, e)
-            }
-        })
+        self.deref_mut().status().map_err(|e| self.command_error(e))
     }
 
     // shadows command's output
     pub fn output(&mut self) -> Result<Output> {
-        self.deref_mut().output().map_err(|e| {
-            if let std::io::ErrorKind::NotFound = e.kind() {
-                TmcError::CommandNotFound(crate::error::CommandNotFound {
-                    name: self.name,
-                    path: self.path.clone(),
-                    source: e,
-                })
-            } else {
-                TmcError::CommandFailed(self.name, e)
-            }
-        })
+        // like Command::output, does not inherit stdin unless the caller has configured it
+        if self.input.is_none() && !self.stdin_set {
+            self.command.stdin(Stdio::null());
+        }
+        match self.capture(None)? {
+            OutputWithTimeout::Output(output) => Ok(output),
+            OutputWithTimeout::Timeout { .. } => unreachable!("no timeout was set"),
+        }
+    }
+
+    /// Runs the command to completion, enforcing the timeout if one is given.
+    pub fn output_with_timeout(&mut self, timeout: Option<Duration>) -> Result<OutputWithTimeout> {
+        if let Some(timeout) = timeout {
+            self.wait_with_timeout(timeout)
+        } else {
+            self.output().map(OutputWithTimeout::Output)
+        }
     }
 
     /// Waits with the given timeout. Sets stdout and stderr in order to capture them after erroring.
+    /// On Unix, the command is ran in its own session so that any processes it spawns
+    /// are terminated along with it when the timeout is reached.
     pub fn wait_with_timeout(&mut self, timeout: Duration) -> Result<OutputWithTimeout> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+
+            // safe: setsid is async-signal-safe and does not touch the parent's memory
+            unsafe {
+                self.pre_exec(|| {
+                    if libc::setsid() == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        self.capture(Some(timeout))
+    }
+
+    /// Runs the command, reading its output as it is produced into bounded buffers.
+    fn capture(&mut self, timeout: Option<Duration>) -> Result<OutputWithTimeout> {
+        let input = self.input.take();
+        if input.is_some() {
+            self.command.stdin(Stdio::piped());
+        }
+
         // spawn process and init timer
-        let mut child = self
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|e| TmcError::CommandSpawn(self.name, e))?;
+        let mut child = match self.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+            Ok(child) => child,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(self.command_error(e)),
+            Err(e) => return Err(TmcError::CommandSpawn(self.name, e)),
+        };
         let timer = Instant::now();
 
-        loop {
-            match child.try_wait().map_err(TmcError::Process)? {
-                Some(_exit_status) => {
-                    // done, get output
-                    return child
-                        .wait_with_output()
-                        .map(OutputWithTimeout::Output)
-                        .map_err(|e| {
-                            if let std::io::ErrorKind::NotFound = e.kind() {
-                                TmcError::CommandNotFound(crate::error::CommandNotFound {
-                                    name: self.name,
-                                    path: self.path.clone(),
-                                    source: e,
-                                })
-                            } else {
-                                TmcError::CommandFailed(self.name, e)
-                            }
-                        });
+        if let Some(input) = input {
+            // written in a separate thread so that a child that doesn't read its stdin can't block us
+            let mut stdin = child.stdin.take().expect("stdin was piped");
+            let name = self.name;
+            thread::spawn(move || {
+                if let Err(e) = stdin.write_all(&input) {
+                    log::warn!("failed to write input to command {}: {}", name, e);
+                }
+            });
+        }
+
+        // the output is read in separate threads so that the child never blocks on a full pipe
+        let (done_sender, done_receiver) = mpsc::channel();
+        let stdout_reader = spawn_reader(
+            child.stdout.take().expect("stdout was piped"),
+            OutputStream::Stdout,
+            self.output_limit,
+            self.line_callback.clone(),
+            done_sender.clone(),
+        );
+        let stderr_reader = spawn_reader(
+            child.stderr.take().expect("stderr was piped"),
+            OutputStream::Stderr,
+            self.output_limit,
+            self.line_callback.clone(),
+            done_sender,
+        );
+
+        let mut timed_out = false;
+        if let Some(timeout) = timeout {
+            loop {
+                if child.try_wait().map_err(TmcError::Process)?.is_some() {
+                    break;
+                }
+                // still running, check timeout
+                if timer.elapsed() > timeout {
+                    log::warn!("command {} timed out", self.name);
+                    terminate(&mut child)?;
+                    timed_out = true;
+                    break;
                 }
-                None => {
-                    // still running, check timeout
-                    if timer.elapsed() > timeout {
-                        log::warn!("command {} timed out", self.name);
-                        // todo: cleaner method for killing
-                        child.kill().map_err(TmcError::Process)?;
-
-                        let mut stdout = vec![];
-                        let mut stderr = vec![];
-                        let stdout_handle = child.stdout.as_mut().unwrap();
-                        let stderr_handle = child.stderr.as_mut().unwrap();
-                        stdout_handle.read_to_end(&mut stdout).unwrap();
-                        stderr_handle.read_to_end(&mut stderr).unwrap();
-                        return Ok(OutputWithTimeout::Timeout { stdout, stderr });
+
+                // TODO: gradually increase sleep duration?
+                thread::sleep(Duration::from_millis(100));
+            }
+
+            if !timed_out {
+                // processes spawned by the child may keep the output streams open after it exits,
+                // in which case they are killed but the child's exit status is still reported
+                for _ in 0..2 {
+                    let remaining = timeout
+                        .checked_sub(timer.elapsed())
+                        .unwrap_or_else(|| Duration::from_secs(0));
+                    if let Err(RecvTimeoutError::Timeout) = done_receiver.recv_timeout(remaining) {
+                        log::warn!(
+                            "processes spawned by command {} kept running past the timeout",
+                            self.name
+                        );
+                        kill_remaining(&child);
+                        break;
                     }
+                }
+            }
+        }
+
+        let status = child.wait().map_err(TmcError::Process)?;
+        let stdout = join_reader(stdout_reader);
+        let stderr = join_reader(stderr_reader);
+        if timed_out {
+            Ok(OutputWithTimeout::Timeout { stdout, stderr })
+        } else {
+            Ok(OutputWithTimeout::Output(Output {
+                status,
+                stdout,
+                stderr,
+            }))
+        }
+    }
+
+    fn command_error(&self, error: io::Error) -> TmcError {
+        if let io::ErrorKind::NotFound = error.kind() {
+            TmcError::CommandNotFound(crate::error::CommandNotFound {
+                name: self.name,
+                path: self.path.clone(),
+                source: error,
+            })
+        } else {
+            TmcError::CommandFailed(self.name, error)
+        }
+    }
+}
+
+/// Keeps the latest bytes written to it up to a limit.
+struct OutputBuffer {
+    buffer: VecDeque<u8>,
+    limit: usize,
+    truncated: usize,
+}
 
-                    // TODO: gradually increase sleep duration?
-                    thread::sleep(Duration::from_millis(100));
+impl OutputBuffer {
+    fn new(limit: usize) -> Self {
+        Self {
+            buffer: VecDeque::new(),
+            limit,
+            truncated: 0,
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        // only the last limit bytes of the data can fit
+        let skip = data.len().saturating_sub(self.limit);
+        let data = &data[skip..];
+        let overflow = (self.buffer.len() + data.len()).saturating_sub(self.limit);
+        self.buffer.drain(..overflow);
+        self.buffer.extend(data);
+        self.truncated += skip + overflow;
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        if self.truncated == 0 {
+            return self.buffer.into();
+        }
+        let mut bytes = format!("[{} bytes of output truncated]\n", self.truncated).into_bytes();
+        bytes.extend(self.buffer);
+        bytes
+    }
+}
+
+/// Reads the stream into a bounded buffer in a new thread,
+/// passing each line to the callback and notifying the sender when the stream is closed.
+fn spawn_reader<R: Read + Send + 'static>(
+    mut reader: R,
+    stream: OutputStream,
+    limit: usize,
+    line_callback: Option<LineCallback>,
+    done_sender: Sender<()>,
+) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = OutputBuffer::new(limit);
+        let mut line = vec![];
+        let mut chunk = [0; 8 * 1024];
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    log::warn!("failed to read command output: {}", e);
+                    break;
+                }
+            };
+            let data = &chunk[..read];
+            buffer.push(data);
+
+            if let Some(callback) = &line_callback {
+                let mut rest = data;
+                while let Some(newline) = rest.iter().position(|b| *b == b'\n') {
+                    line.extend_from_slice(&rest[..newline]);
+                    callback(stream, &String::from_utf8_lossy(&line));
+                    line.clear();
+                    rest = &rest[newline + 1..];
                 }
+                line.extend_from_slice(rest);
+                if line.len() >= limit {
+                    callback(stream, &String::from_utf8_lossy(&line));
+                    line.clear();
+                }
+            }
+        }
+        if let Some(callback) = &line_callback {
+            if !line.is_empty() {
+                callback(stream, &String::from_utf8_lossy(&line));
             }
         }
+        // the receiver is gone if the command did not have a timeout
+        let _ = done_sender.send(());
+        buffer.into_bytes()
+    })
+}
+
+fn join_reader(reader: JoinHandle<Vec<u8>>) -> Vec<u8> {
+    reader.join().unwrap_or_else(|_| {
+        log::error!("output reader thread panicked");
+        vec![]
+    })
+}
+
+/// Terminates the child along with any processes in its process group.
+/// The processes are first sent SIGTERM and killed if they are still running after a grace period.
+#[cfg(unix)]
+fn terminate(child: &mut Child) -> Result<()> {
+    // the child is the leader of its own session and process group
+    let process_group = child.id() as libc::pid_t;
+
+    // safe: killpg only sends a signal
+    unsafe { libc::killpg(process_group, libc::SIGTERM) };
+    let timer = Instant::now();
+    while timer.elapsed() < KILL_GRACE_PERIOD {
+        // reap the child if it has exited so it does not linger in the group as a zombie
+        child.try_wait().map_err(TmcError::Process)?;
+        // signal 0 checks whether any process in the group still exists
+        if unsafe { libc::killpg(process_group, 0) } == -1 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
     }
+    unsafe { libc::killpg(process_group, libc::SIGKILL) };
+    child.wait().map_err(TmcError::Process)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn terminate(child: &mut Child) -> Result<()> {
+    child.kill().map_err(TmcError::Process)?;
+    child.wait().map_err(TmcError::Process)?;
+    Ok(())
+}
+
+/// Kills any processes left in the group of a child that has already exited.
+#[cfg(unix)]
+fn kill_remaining(child: &Child) {
+    // the process group outlives the child as long as it has members
+    // safe: killpg only sends a signal
+    unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) };
+}
+
+#[cfg(not(unix))]
+fn kill_remaining(_child: &Child) {
+    log::warn!("cannot kill processes spawned by the command on this platform");
 }
 
 impl Deref for TmcCommand {
@@ -165,4 +538,167 @@ mod test {
             panic!("unexpected result");
         }
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn applies_resource_limits() {
+        let mut cmd = TmcCommand::new("sh");
+        cmd.resource_limits(ResourceLimits {
+            cpu_time_secs: Some(5),
+            ..ResourceLimits::default()
+        });
+        cmd.arg("-c").arg("ulimit -t");
+        let output = cmd.output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "5");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn file_size_limit_stops_writes() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("file");
+
+        let mut cmd = TmcCommand::new("sh");
+        cmd.resource_limits(ResourceLimits {
+            file_size_bytes: Some(1024),
+            ..ResourceLimits::default()
+        });
+        cmd.arg("-c")
+            .arg(format!("head -c 4096 /dev/zero > {}", file.display()));
+        let output = cmd.output().unwrap();
+        assert!(!output.status.success());
+        assert!(std::fs::metadata(&file).unwrap().len() <= 1024);
+    }
+
+    #[test]
+    fn writes_input_to_stdin() {
+        let mut cmd = TmcCommand::new("cat");
+        cmd.input(b"some input".to_vec());
+        let output = cmd.output().unwrap();
+        assert_eq!(output.stdout, b"some input");
+    }
+
+    #[test]
+    fn truncates_output() {
+        let mut cmd = TmcCommand::new("sh");
+        cmd.output_limit(100)
+            .arg("-c")
+            .arg("head -c 10000 /dev/zero | tr '\\0' a; echo end");
+        let output = cmd.output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.starts_with("[9904 bytes of output truncated]\n"));
+        assert!(stdout.ends_with(&format!("{}end\n", "a".repeat(96))));
+    }
+
+    #[test]
+    fn truncates_output_on_timeout() {
+        let mut cmd = TmcCommand::new("sh");
+        cmd.output_limit(1000)
+            .arg("-c")
+            .arg("while true; do echo output; done");
+        let res = cmd.wait_with_timeout(Duration::from_millis(500)).unwrap();
+        if let OutputWithTimeout::Timeout { stdout, .. } = res {
+            let stdout = String::from_utf8(stdout).unwrap();
+            assert!(stdout.starts_with('['));
+            assert!(stdout.contains("bytes of output truncated]"));
+            assert!(stdout.len() < 1100);
+        } else {
+            panic!("unexpected result");
+        }
+    }
+
+    #[test]
+    fn streams_lines_to_callback() {
+        let lines = Arc::new(std::sync::Mutex::new(vec![]));
+        let callback_lines = lines.clone();
+
+        let mut cmd = TmcCommand::new("sh");
+        cmd.on_line(move |stream, line| {
+            callback_lines
+                .lock()
+                .unwrap()
+                .push((stream, line.to_string()))
+        })
+        .arg("-c")
+        .arg("echo one; echo two >&2; printf three");
+        let output = cmd.output().unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "one\nthree");
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.contains(&(OutputStream::Stdout, "one".to_string())));
+        assert!(lines.contains(&(OutputStream::Stderr, "two".to_string())));
+        assert!(lines.contains(&(OutputStream::Stdout, "three".to_string())));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn output_held_open_after_exit() {
+        let mut cmd = TmcCommand::new("sh");
+        cmd.arg("-c").arg("sleep 30 & echo exited; exit 3");
+        let timer = Instant::now();
+        let res = cmd.wait_with_timeout(Duration::from_millis(500)).unwrap();
+        assert!(timer.elapsed() < Duration::from_secs(10));
+        if let OutputWithTimeout::Output(output) = res {
+            assert_eq!(output.status.code(), Some(3));
+            assert_eq!(output.stdout, b"exited\n");
+        } else {
+            panic!("unexpected result");
+        }
+    }
+
+    #[test]
+    fn keeps_configured_stdin() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "from file").unwrap();
+        let mut cmd = TmcCommand::new("cat");
+        cmd.stdin(std::fs::File::open(file.path()).unwrap());
+        let output = cmd.output().unwrap();
+        assert_eq!(output.stdout, b"from file");
+    }
+
+    // returns the state of the process from /proc, or None if it does not exist
+    #[cfg(target_os = "linux")]
+    fn process_state(pid: &str) -> Option<char> {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        // the state follows the parenthesised executable name
+        stat.rsplit(')').next()?.trim().chars().next()
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn timeout_kills_process_group() {
+        let temp = tempfile::tempdir().unwrap();
+        let pid_file = temp.path().join("pid");
+
+        let mut cmd = TmcCommand::new("sh");
+        cmd.arg("tests/data/command/forking.sh").arg(&pid_file);
+        let timer = Instant::now();
+        let res = cmd.wait_with_timeout(Duration::from_millis(500)).unwrap();
+        // the grandchild holds stdout open for 30 seconds if it is not killed
+        assert!(timer.elapsed() < Duration::from_secs(10));
+        if let OutputWithTimeout::Output(_) = res {
+            panic!("unexpected result");
+        }
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let state = process_state(pid.trim());
+        // the killed grandchild may remain as a zombie until it is reaped by init
+        assert!(state.is_none() || state == Some('Z'), "{:?}", state);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn timeout_kills_processes_ignoring_sigterm() {
+        let mut cmd = TmcCommand::new("sh");
+        cmd.arg("tests/data/command/ignores_sigterm.sh");
+        let timer = Instant::now();
+        let res = cmd.wait_with_timeout(Duration::from_millis(100)).unwrap();
+        assert!(timer.elapsed() >= KILL_GRACE_PERIOD);
+        assert!(timer.elapsed() < Duration::from_secs(10));
+        if let OutputWithTimeout::Output(_) = res {
+            panic!("unexpected result");
+        }
+    }
 }
@@ -2,11 +2,12 @@
 
 pub mod meta_syntax;
 
-use super::{Result, TmcError};
+use super::{command::ResourceLimits, io::path_util, Result, TmcError};
 use log::debug;
+use meta_syntax::CommentSyntax;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs::File;
 use std::path::{Path, PathBuf};
 
@@ -39,6 +40,15 @@ pub struct TestResult {
     pub message: String,
     #[serde(default)]
     pub exception: Vec<String>,
+    /// How long the test took to run in milliseconds, if reported by the test runner.
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    /// The standard output captured during the test, if reported by the test runner.
+    #[serde(default)]
+    pub stdout: Option<String>,
+    /// The standard error captured during the test, if reported by the test runner.
+    #[serde(default)]
+    pub stderr: Option<String>,
 }
 
 /// A description of an exercise.
@@ -82,6 +92,97 @@ impl RunResult {
             logs,
         }
     }
+
+    /// Creates the result for a test run that was stopped for exceeding its timeout.
+    pub fn timed_out() -> Self {
+        Self {
+            status: RunStatus::TestsFailed,
+            test_results: vec![TestResult {
+                name: "Timeout test".to_string(),
+                successful: false,
+                points: vec![],
+                message:
+                    "Tests timed out.\nMake sure you don't have an infinite loop in your code."
+                        .to_string(),
+                exception: vec![],
+                duration_ms: None,
+                stdout: None,
+                stderr: None,
+            }],
+            logs: HashMap::new(),
+        }
+    }
+}
+
+/// Selects a subset of an exercise's tests to run.
+///
+/// A test is selected if its name is one of the test names or if it may award one of the points.
+/// An empty filter selects every test.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct TestFilter {
+    /// Test names in the format used in `TestDesc::name`.
+    #[serde(default)]
+    pub tests: Vec<String>,
+    /// Point names.
+    #[serde(default)]
+    pub points: Vec<String>,
+}
+
+impl TestFilter {
+    pub fn new(tests: Vec<String>, points: Vec<String>) -> Self {
+        Self { tests, points }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tests.is_empty() && self.points.is_empty()
+    }
+
+    /// Checks whether the test with the given name and points is selected.
+    pub fn matches(&self, name: &str, points: &[String]) -> bool {
+        self.is_empty()
+            || self.tests.iter().any(|t| t == name)
+            || points.iter().any(|p| self.points.contains(p))
+    }
+
+    /// Returns the tests of the exercise that are selected.
+    pub fn select<'a>(&self, exercise_desc: &'a ExerciseDesc) -> Vec<&'a TestDesc> {
+        exercise_desc
+            .tests
+            .iter()
+            .filter(|t| self.matches(&t.name, &t.points))
+            .collect()
+    }
+
+    /// Removes the results of the tests that were not selected, updating the status accordingly.
+    /// Used when the test runner cannot filter the tests itself, or can only do it partially.
+    pub fn apply(&self, run_result: RunResult) -> RunResult {
+        if self.is_empty() {
+            return run_result;
+        }
+
+        let RunResult {
+            mut status,
+            test_results,
+            logs,
+        } = run_result;
+        let test_results: Vec<TestResult> = test_results
+            .into_iter()
+            .filter(|t| self.matches(&t.name, &t.points))
+            .collect();
+        // other statuses are not affected by the results of individual tests
+        if status == RunStatus::Passed || status == RunStatus::TestsFailed {
+            status = if test_results.iter().all(|t| t.successful) {
+                RunStatus::Passed
+            } else {
+                RunStatus::TestsFailed
+            };
+        }
+        RunResult {
+            status,
+            test_results,
+            logs,
+        }
+    }
 }
 
 /// The overall status of a test run.
@@ -103,6 +204,81 @@ pub enum RunStatus {
     GenericError,
 }
 
+/// The points that a test run would award for an exercise.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExercisePoints {
+    /// Points for which every test that may award the point passed.
+    pub awarded_points: Vec<String>,
+    /// Points with at least one failed or missing test.
+    pub missing_points: Vec<String>,
+}
+
+impl ExercisePoints {
+    /// Calculates the awarded and missing points of an exercise from its description and a test run.
+    ///
+    /// A point is only awarded if every test that may award it passed. Because the test names in the results
+    /// do not always match the names in the description, a point is considered missing if any test result
+    /// awarding it failed, or if fewer successful results award it than there are tests awarding it in the description.
+    /// The points of a `no-tests` configuration are always awarded. Both lists are sorted and deduplicated.
+    pub fn new(
+        exercise_desc: &ExerciseDesc,
+        run_result: &RunResult,
+        no_tests: Option<&NoTests>,
+    ) -> Self {
+        let mut expected: HashMap<&str, usize> = HashMap::new();
+        for test in &exercise_desc.tests {
+            for point in &test.points {
+                *expected.entry(point).or_default() += 1;
+            }
+        }
+
+        let mut passed: HashMap<&str, usize> = HashMap::new();
+        let mut failed: HashSet<&str> = HashSet::new();
+        for test_result in &run_result.test_results {
+            for point in &test_result.points {
+                if test_result.successful {
+                    *passed.entry(point).or_default() += 1;
+                } else {
+                    failed.insert(point);
+                }
+            }
+        }
+
+        let no_tests_points: HashSet<&str> = no_tests
+            .filter(|n| n.flag)
+            .map(|n| n.points.iter().map(String::as_str).collect())
+            .unwrap_or_default();
+
+        let all_points: BTreeSet<&str> = expected
+            .keys()
+            .chain(passed.keys())
+            .chain(failed.iter())
+            .chain(no_tests_points.iter())
+            .copied()
+            .collect();
+
+        let mut awarded_points = vec![];
+        let mut missing_points = vec![];
+        for point in all_points {
+            let passed_count = passed.get(point).copied().unwrap_or_default();
+            let expected_count = expected.get(point).copied().unwrap_or_default();
+            let awarded = no_tests_points.contains(point)
+                || (!failed.contains(point) && passed_count > 0 && passed_count >= expected_count);
+            if awarded {
+                awarded_points.push(point.to_string());
+            } else {
+                missing_points.push(point.to_string());
+            }
+        }
+
+        Self {
+            awarded_points,
+            missing_points,
+        }
+    }
+}
+
 /// Represents configuration based on which submission may be packaged.
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct ExercisePackagingConfiguration {
@@ -124,6 +300,124 @@ impl ExercisePackagingConfiguration {
     }
 }
 
+/// Describes which language plugins match an exercise directory.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct PluginDetection {
+    /// The names of all plugins that recognise the directory, in the order they are checked in.
+    pub matching_plugins: Vec<String>,
+    /// The plugin set with the `plugin` key in the exercise's `.tmcproject.yml`, if any.
+    pub configured_plugin: Option<String>,
+    /// The plugin that is used for the exercise, if any.
+    pub selected_plugin: Option<String>,
+}
+
+/// The result of running a prepared submission in the local sandbox, in the form reported by the grading sandbox.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SandboxResult {
+    /// Whether the test run finished, failed or timed out.
+    pub status: SandboxStatus,
+    /// The exit code of the test run. Missing if the run timed out or was killed by a signal.
+    pub exit_code: Option<i32>,
+    /// The results written by the test run. Missing if the run did not produce valid results.
+    pub test_output: Option<RunResult>,
+    /// The standard output of the test run.
+    pub stdout: String,
+    /// The standard error of the test run.
+    pub stderr: String,
+}
+
+/// The overall status of a sandboxed test run.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SandboxStatus {
+    /// The test run exited successfully. The tests themselves may have failed.
+    Finished,
+    /// The test run exited with an error.
+    Failed,
+    /// The test run was stopped for exceeding the timeout.
+    Timeout,
+}
+
+/// What preparing the stub and solution would do to a file of an exercise.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FilePreview {
+    /// The path of the file, relative to the exercise root.
+    pub path: PathBuf,
+    /// A unified diff of the original file compared to the stub version, or none if the stub is unchanged.
+    pub stub_diff: Option<String>,
+    /// A unified diff of the original file compared to the solution version, or none if the solution is unchanged.
+    pub solution_diff: Option<String>,
+}
+
+/// The result of verifying that an exercise's stub and solution behave as expected.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExerciseVerification {
+    pub exercise_path: PathBuf,
+    /// The status of the test run on the stub, if the tests could be ran.
+    pub stub_status: Option<RunStatus>,
+    /// The status of the test run on the solution, if the tests could be ran.
+    pub solution_status: Option<RunStatus>,
+    /// Empty if the exercise passed the verification.
+    pub problems: Vec<VerificationProblem>,
+}
+
+/// A problem found when verifying an exercise.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationProblem {
+    pub kind: VerificationProblemKind,
+    pub message: String,
+}
+
+/// The kind of a problem found when verifying an exercise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VerificationProblemKind {
+    /// The stub did not compile.
+    StubCompileFailed,
+    /// All of the tests passed for the stub.
+    StubPassed,
+    /// Some of the tests failed for the solution.
+    SolutionFailed,
+    /// The points found by scanning the stub or solution differ from the original exercise.
+    PointsDiffer,
+    /// Preparing, scanning or testing the exercise failed.
+    Error,
+}
+
+/// An issue found by auditing the exercises of a course.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CourseAuditIssue {
+    pub kind: CourseAuditIssueKind,
+    /// The exercises that the issue concerns.
+    pub exercise_paths: Vec<PathBuf>,
+    pub message: String,
+}
+
+/// The kind of an issue found by auditing the exercises of a course.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CourseAuditIssueKind {
+    /// The same point is awarded by more than one exercise.
+    DuplicatePoint,
+    /// A test does not award any points.
+    TestWithoutPoints,
+    /// The exercise does not award any points, neither from its tests nor from `no-tests`.
+    NoPoints,
+    /// A `no-tests` point in `.tmcproject.yml` is also awarded by a test of the exercise.
+    NoTestsPointClash,
+    /// A point name is empty or contains whitespace or unusual characters.
+    UnusualPointName,
+    /// More than one language plugin recognises the exercise and none is configured in `.tmcproject.yml`.
+    /// The `no-tests` plugin is not counted, as it is used together with a language plugin.
+    AmbiguousPlugin,
+    /// Scanning the exercise failed.
+    ScanFailed,
+}
+
 /// Extra data from a `.tmcproject.yml` file.
 #[derive(Debug, Deserialize, Default)]
 pub struct TmcProjectYml {
@@ -145,6 +439,20 @@ pub struct TmcProjectYml {
 
     #[serde(default)]
     pub fail_on_valgrind_error: Option<bool>,
+
+    #[serde(default)]
+    pub resource_limits: Option<ResourceLimits>,
+
+    #[serde(default)]
+    pub plugin: Option<String>,
+
+    #[serde(default)]
+    pub external_plugin: Option<ExternalPluginConfig>,
+
+    /// Additional comment syntaxes for meta syntax parsing, keyed by file extension.
+    /// Replaces the default syntaxes for the extension.
+    #[serde(default)]
+    pub comment_syntaxes: HashMap<String, Vec<CommentSyntax>>,
 }
 
 impl TmcProjectYml {
@@ -158,10 +466,72 @@ impl TmcProjectYml {
         }
         debug!("reading .tmcproject.yml from {}", config_path.display());
         let file = File::open(&config_path).map_err(|e| TmcError::OpenFile(config_path, e))?;
-        Ok(serde_yaml::from_reader(file)?)
+        let mut tmc_project_yml: Self = serde_yaml::from_reader(file)?;
+        tmc_project_yml.sanitize_paths()?;
+        Ok(tmc_project_yml)
+    }
+
+    /// Reads only the `plugin` key from the project directory's .tmcproject.yml,
+    /// so that plugin selection does not depend on the rest of the file being valid.
+    pub fn read_plugin(project_dir: &Path) -> Result<Option<String>> {
+        let config_path = project_dir.join(".tmcproject.yml");
+        if !config_path.exists() {
+            return Ok(None);
+        }
+        let file =
+            File::open(&config_path).map_err(|e| TmcError::OpenFile(config_path.clone(), e))?;
+        let yaml: serde_yaml::Value = serde_yaml::from_reader(file)
+            .map_err(|e| TmcError::InvalidPluginKey(config_path.clone(), e.to_string()))?;
+        match yaml.get("plugin") {
+            None | Some(serde_yaml::Value::Null) => Ok(None),
+            Some(serde_yaml::Value::String(plugin)) => Ok(Some(plugin.clone())),
+            Some(_) => Err(TmcError::InvalidPluginKey(
+                config_path,
+                "the value is not a string".to_string(),
+            )),
+        }
+    }
+
+    // the paths are relative to the project directory and must stay within it
+    fn sanitize_paths(&mut self) -> Result<()> {
+        let external_plugin_paths = self
+            .external_plugin
+            .iter_mut()
+            .flat_map(|c| c.student_file_paths.iter_mut());
+        for path in self
+            .extra_student_files
+            .iter_mut()
+            .chain(self.extra_exercise_files.iter_mut())
+            .chain(self.force_update.iter_mut())
+            .chain(external_plugin_paths)
+        {
+            *path = path_util::sanitize_relative_path(path)?;
+        }
+        Ok(())
     }
 }
 
+/// An executable that implements a language plugin with the external plugin protocol.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+pub struct ExternalPluginConfig {
+    /// The executable. Relative paths are resolved from the directory the configuration is in,
+    /// and names without a directory are looked up from PATH.
+    pub command: PathBuf,
+    /// Arguments passed to the executable before the request is written to its stdin.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Directories or files that contain student files, relative to the exercise root.
+    #[serde(default = "default_student_file_paths")]
+    pub student_file_paths: Vec<PathBuf>,
+    /// The timeout in seconds for the requests other than running tests, which use the timeout given for the test run.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+fn default_student_file_paths() -> Vec<PathBuf> {
+    vec![PathBuf::from("src")]
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(from = "NoTestsWrapper")]
 pub struct NoTests {
@@ -238,6 +608,27 @@ pub struct ValidationResult {
 mod test {
     use super::*;
 
+    #[test]
+    fn reads_plugin_key_only() {
+        let temp = tempfile::tempdir().unwrap();
+        assert_eq!(TmcProjectYml::read_plugin(temp.path()).unwrap(), None);
+
+        let config_path = temp.path().join(".tmcproject.yml");
+        std::fs::write(&config_path, "plugin: apache-ant\nno-tests: [[\n").unwrap();
+        assert!(TmcProjectYml::read_plugin(temp.path()).is_err());
+        std::fs::write(&config_path, "plugin: apache-ant\nextra_student_files: 1\n").unwrap();
+        assert!(TmcProjectYml::from(temp.path()).is_err());
+        assert_eq!(
+            TmcProjectYml::read_plugin(temp.path()).unwrap().as_deref(),
+            Some("apache-ant")
+        );
+        std::fs::write(&config_path, "plugin: [apache-ant]\n").unwrap();
+        assert!(matches!(
+            TmcProjectYml::read_plugin(temp.path()),
+            Err(TmcError::InvalidPluginKey(_, _))
+        ));
+    }
+
     #[test]
     fn deserialize_no_tests() {
         let no_tests_yml = r#"no-tests:
@@ -251,4 +642,99 @@ mod test {
         assert!(no_tests.flag);
         assert!(!no_tests.points.is_empty());
     }
+
+    fn test_result(name: &str, successful: bool, points: &[&str]) -> TestResult {
+        TestResult {
+            name: name.to_string(),
+            successful,
+            points: points.iter().map(|p| p.to_string()).collect(),
+            message: String::new(),
+            exception: vec![],
+            duration_ms: None,
+            stdout: None,
+            stderr: None,
+        }
+    }
+
+    #[test]
+    fn filters_tests() {
+        let filter = TestFilter::new(vec!["TestOne".to_string()], vec!["1.3".to_string()]);
+        let run_result = RunResult::new(
+            RunStatus::TestsFailed,
+            vec![
+                test_result("TestOne", true, &["1.1"]),
+                test_result("TestTwo", false, &["1.2"]),
+                test_result("TestThree", true, &["1.2", "1.3"]),
+            ],
+            HashMap::new(),
+        );
+        let run_result = filter.apply(run_result);
+        assert_eq!(run_result.status, RunStatus::Passed);
+        let names: Vec<_> = run_result.test_results.iter().map(|t| &t.name).collect();
+        assert_eq!(names, &["TestOne", "TestThree"]);
+
+        let run_result = RunResult::new(
+            RunStatus::Passed,
+            vec![test_result("TestTwo", true, &["1.2"])],
+            HashMap::new(),
+        );
+        let run_result = TestFilter::default().apply(run_result);
+        assert_eq!(run_result.test_results.len(), 1);
+    }
+
+    #[test]
+    fn calculates_points() {
+        let exercise_desc = ExerciseDesc::new(
+            "ex".to_string(),
+            vec![
+                TestDesc::new("TestOne".to_string(), vec!["1.1".to_string()]),
+                TestDesc::new(
+                    "TestTwo".to_string(),
+                    vec!["1.2".to_string(), "1.3".to_string()],
+                ),
+                TestDesc::new(
+                    "TestThree".to_string(),
+                    vec!["1.3".to_string(), "1.4".to_string()],
+                ),
+                TestDesc::new("TestFour".to_string(), vec!["1.5".to_string()]),
+            ],
+        );
+        // TestFour has no result
+        let run_result = RunResult::new(
+            RunStatus::TestsFailed,
+            vec![
+                test_result("TestOne", true, &["1.1"]),
+                test_result("TestTwo", true, &["1.2", "1.3"]),
+                test_result("TestThree", false, &["1.3", "1.4"]),
+            ],
+            HashMap::new(),
+        );
+
+        let points = ExercisePoints::new(&exercise_desc, &run_result, None);
+        assert_eq!(points.awarded_points, &["1.1", "1.2"]);
+        assert_eq!(points.missing_points, &["1.3", "1.4", "1.5"]);
+
+        let no_tests = NoTests {
+            flag: true,
+            points: vec!["1.5".to_string(), "extra".to_string()],
+        };
+        let points = ExercisePoints::new(&exercise_desc, &run_result, Some(&no_tests));
+        assert_eq!(points.awarded_points, &["1.1", "1.2", "1.5", "extra"]);
+        assert_eq!(points.missing_points, &["1.3", "1.4"]);
+    }
+
+    #[test]
+    fn deserialize_external_plugin() {
+        let external_plugin_yml = r#"external_plugin:
+  command: ./plugin
+  args:
+    - --verbose
+"#;
+
+        let cfg: TmcProjectYml = serde_yaml::from_str(external_plugin_yml).unwrap();
+        let external_plugin = cfg.external_plugin.unwrap();
+        assert_eq!(external_plugin.command, Path::new("./plugin"));
+        assert_eq!(external_plugin.args, &["--verbose"]);
+        assert_eq!(external_plugin.student_file_paths, &[Path::new("src")]);
+    }
 }
@@ -5,16 +5,222 @@ use crate::{Result, TmcError};
 use lazy_static::lazy_static;
 use log::debug;
 use regex::{Captures, Regex};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-// Meta syntaxes for each comment syntax
 lazy_static! {
-    static ref META_SYNTAXES_C: [MetaSyntax; 2] = [
-        MetaSyntax::new("//", None),
-        MetaSyntax::new("/\\*", Some("\\*/"))
-    ];
-    static ref META_SYNTAXES_HTML: [MetaSyntax; 1] = [MetaSyntax::new("<!--", Some("-->"))];
-    static ref META_SYNTAXES_PY: [MetaSyntax; 1] = [MetaSyntax::new("#", None)];
+    static ref DEFAULT_COMMENT_SYNTAXES: CommentSyntaxes = CommentSyntaxes::default();
+}
+
+// A comment start and an optional comment end
+type CommentSyntaxDef = (&'static str, Option<&'static str>);
+
+// Comment syntaxes for each group of languages
+const C_LIKE: &[CommentSyntaxDef] = &[("//", None), ("/*", Some("*/"))];
+const HTML: &[CommentSyntaxDef] = &[("<!--", Some("-->"))];
+const HASH: &[CommentSyntaxDef] = &[("#", None)];
+const HASKELL: &[CommentSyntaxDef] = &[("--", None), ("{-", Some("-}"))];
+const SQL: &[CommentSyntaxDef] = &[("--", None), ("/*", Some("*/"))];
+const LISP: &[CommentSyntaxDef] = &[(";", None)];
+
+// The file extensions that use each comment syntax by default
+const DEFAULT_EXTENSIONS: &[(&[&str], &[CommentSyntaxDef])] = &[
+    (
+        &[
+            "java", "c", "cpp", "h", "hpp", "js", "css", "rs", "qml", "ts", "tsx", "kt", "kts",
+            "scala", "go", "swift",
+        ],
+        C_LIKE,
+    ),
+    (&["xml", "http", "html", "qrc"], HTML),
+    (
+        &[
+            "properties",
+            "py",
+            "R",
+            "pro",
+            "sh",
+            "bash",
+            "yml",
+            "yaml",
+            "toml",
+        ],
+        HASH,
+    ),
+    (&["hs"], HASKELL),
+    (&["sql"], SQL),
+    (&["lisp", "cl", "el", "scm", "rkt", "clj"], LISP),
+];
+
+/// A comment syntax used to mark stubs and solutions, e.g. `//` or `/*` and `*/`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CommentSyntax {
+    /// The string that starts a comment.
+    pub start: String,
+    /// The string that ends a comment, or none for comments that continue until the end of the line.
+    #[serde(default)]
+    pub end: Option<String>,
+}
+
+impl CommentSyntax {
+    pub fn new<S: Into<String>>(start: S, end: Option<S>) -> Self {
+        Self {
+            start: start.into(),
+            end: end.map(Into::into),
+        }
+    }
+}
+
+/// Maps file extensions to the comment syntaxes that are used to mark stubs and solutions in them.
+/// The default mapping covers a number of common languages, and can be extended, for example
+/// with the `comment_syntaxes` in `.tmcproject.yml`.
+#[derive(Debug, Clone)]
+pub struct CommentSyntaxes {
+    by_extension: HashMap<String, Arc<[MetaSyntax]>>,
+}
+
+impl Default for CommentSyntaxes {
+    fn default() -> Self {
+        let mut by_extension = HashMap::new();
+        for (extensions, comment_syntaxes) in DEFAULT_EXTENSIONS {
+            let meta_syntaxes: Arc<[MetaSyntax]> = comment_syntaxes
+                .iter()
+                .map(|(start, end)| MetaSyntax::new(start, *end))
+                .collect();
+            for extension in extensions.iter() {
+                by_extension.insert(extension.to_string(), meta_syntaxes.clone());
+            }
+        }
+        Self { by_extension }
+    }
+}
+
+impl CommentSyntaxes {
+    /// Sets the comment syntaxes for the extension, replacing any previous ones.
+    /// The extension is given without the leading period, e.g. "java".
+    pub fn insert<S: Into<String>>(&mut self, extension: S, comment_syntaxes: &[CommentSyntax]) {
+        let meta_syntaxes = comment_syntaxes
+            .iter()
+            .map(|c| MetaSyntax::new(&c.start, c.end.as_deref()))
+            .collect();
+        self.by_extension.insert(extension.into(), meta_syntaxes);
+    }
+
+    /// Checks the meta syntax of a file with the given extension by parsing it with `MetaSyntaxParser`,
+    /// returning any problems found. `file` is only used to label the problems.
+    pub fn lint<R: Read>(
+        &self,
+        file: &Path,
+        target: R,
+        target_extension: &str,
+    ) -> Result<Vec<MetaSyntaxLint>> {
+        if !self.by_extension.contains_key(target_extension) {
+            return Ok(vec![]);
+        }
+
+        let mut parser = self.parser(target, target_extension);
+        parser.lints = Some(vec![]);
+        for line in &mut parser {
+            line?;
+        }
+        Ok(parser
+            .finish_lints()
+            .into_iter()
+            .map(|(line, kind)| MetaSyntaxLint::new(file, line, kind))
+            .collect())
+    }
+
+    /// Creates a parser for a file with the given extension.
+    /// Files with unknown extensions are parsed without any stub or solution processing.
+    pub fn parser<R: Read>(
+        &self,
+        target: R,
+        target_extension: &str,
+    ) -> MetaSyntaxParser<BufReader<R>> {
+        let meta_syntaxes = self
+            .by_extension
+            .get(target_extension)
+            .cloned()
+            .unwrap_or_else(|| Arc::new([]));
+        MetaSyntaxParser {
+            meta_syntaxes,
+            reader: BufReader::new(target),
+            line_number: 0,
+            in_stub: None,
+            in_solution: None,
+            in_hidden: None,
+            solution_file_seen: false,
+            lints: None,
+        }
+    }
+}
+
+/// The kind of a problem in the meta syntax of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MetaSyntaxLintKind {
+    /// A `BEGIN SOLUTION` without a matching `END SOLUTION`.
+    UnterminatedSolution,
+    /// A `STUB:` block that is never terminated.
+    UnterminatedStub,
+    /// A `BEGIN SOLUTION` inside a solution block.
+    NestedSolution,
+    /// An `END SOLUTION` outside of a solution block.
+    UnmatchedSolutionEnd,
+    /// A `STUB:` marker inside a solution block.
+    StubInSolution,
+    /// A `SOLUTION FILE` marker inside a solution or stub block, or a repeated one.
+    MisplacedSolutionFile,
+    /// A `BEGIN HIDDEN` without a matching `END HIDDEN`.
+    UnterminatedHidden,
+    /// An `END HIDDEN` outside of a hidden block.
+    UnmatchedHiddenEnd,
+    /// A `STUB:` line comment with nothing after it, which makes the next line a stub.
+    EmptyLineStub,
+}
+
+/// A problem in the meta syntax of a file that would produce a broken stub or solution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaSyntaxLint {
+    /// The path of the file, relative to the exercise root.
+    pub file: PathBuf,
+    /// The line of the problem, starting from 1.
+    pub line: usize,
+    pub kind: MetaSyntaxLintKind,
+    pub message: String,
+}
+
+impl MetaSyntaxLint {
+    fn new(file: &Path, line: usize, kind: MetaSyntaxLintKind) -> Self {
+        let message = match kind {
+            MetaSyntaxLintKind::UnterminatedSolution => "solution block is never terminated",
+            MetaSyntaxLintKind::UnterminatedStub => "stub block is never terminated",
+            MetaSyntaxLintKind::NestedSolution => "solution block inside another solution block",
+            MetaSyntaxLintKind::UnmatchedSolutionEnd => {
+                "end of a solution block without a beginning"
+            }
+            MetaSyntaxLintKind::StubInSolution => "stub marker inside a solution block",
+            MetaSyntaxLintKind::MisplacedSolutionFile => {
+                "solution file marker inside a block or repeated"
+            }
+            MetaSyntaxLintKind::UnterminatedHidden => "hidden block is never terminated",
+            MetaSyntaxLintKind::UnmatchedHiddenEnd => "end of a hidden block without a beginning",
+            MetaSyntaxLintKind::EmptyLineStub => {
+                "stub line comment without content turns the next line into a stub"
+            }
+        };
+        Self {
+            file: file.to_path_buf(),
+            line,
+            kind,
+            message: message.to_string(),
+        }
+    }
 }
 
 // Used to classify lines of code based on the annotations in the file
@@ -23,6 +229,8 @@ pub enum MetaString {
     String(String),
     Stub(String),
     Solution(String),
+    // hidden from the student, for example extra checks in test files
+    Hidden(String),
 }
 
 impl MetaString {
@@ -32,6 +240,7 @@ impl MetaString {
             Self::String(s) => &s,
             Self::Stub(s) => &s,
             Self::Solution(s) => &s,
+            Self::Hidden(s) => &s,
         }
     }
 }
@@ -39,18 +248,26 @@ impl MetaString {
 // Contains the needed regexes for a given comment syntax
 #[derive(Debug)]
 struct MetaSyntax {
+    line_comment: bool,
     solution_file: Regex,
     solution_begin: Regex,
     solution_end: Regex,
     stub_begin: Regex,
     stub_end: Regex,
+    hidden_begin: Regex,
+    hidden_end: Regex,
 }
 
 impl MetaSyntax {
-    fn new(comment_start: &'static str, comment_end: Option<&'static str>) -> Self {
-        let comment_start_pattern = format!("^(\\s*){}\\s*", comment_start);
+    fn new(comment_start: &str, comment_end: Option<&str>) -> Self {
+        // single character line comments are often repeated, e.g. `;; BEGIN SOLUTION` in Lisp
+        let comment_start_pattern = if comment_start.chars().count() == 1 && comment_end.is_none() {
+            format!("^(\\s*)(?:{})+\\s*", regex::escape(comment_start))
+        } else {
+            format!("^(\\s*){}\\s*", regex::escape(comment_start))
+        };
         let comment_end_pattern = match comment_end {
-            Some(s) => format!("(.*){}\\s*", s),
+            Some(s) => format!("(.*){}\\s*", regex::escape(s)),
             None => "(.*)".to_string(),
         };
         let solution_file = Regex::new(&format!(
@@ -70,13 +287,26 @@ impl MetaSyntax {
         .unwrap();
         let stub_begin = Regex::new(&format!("{}STUB:\\s*", comment_start_pattern)).unwrap();
         let stub_end = Regex::new(&comment_end_pattern).unwrap();
+        let hidden_begin = Regex::new(&format!(
+            "{}BEGIN\\s+HIDDEN{}",
+            comment_start_pattern, comment_end_pattern
+        ))
+        .unwrap();
+        let hidden_end = Regex::new(&format!(
+            "{}END\\s+HIDDEN{}",
+            comment_start_pattern, comment_end_pattern
+        ))
+        .unwrap();
 
         Self {
+            line_comment: comment_end.is_none(),
             solution_file,
             solution_begin,
             solution_end,
             stub_begin,
             stub_end,
+            hidden_begin,
+            hidden_end,
         }
     }
 }
@@ -84,30 +314,48 @@ impl MetaSyntax {
 /// Parses a given text file into an iterator of `MetaString`s
 #[derive(Debug)]
 pub struct MetaSyntaxParser<B: BufRead> {
-    meta_syntaxes: &'static [MetaSyntax],
+    meta_syntaxes: Arc<[MetaSyntax]>,
     reader: B,
-    // contains the syntax that started the current stub block
+    // the number of the last line read, starting from 1
+    line_number: usize,
+    // contains the index of the syntax and the line that started the current stub block
     // used to make sure only the appropriate terminator ends the block
-    in_stub: Option<&'static MetaSyntax>,
-    in_solution: bool,
+    in_stub: Option<(usize, usize)>,
+    // the lines that started the current solution and hidden blocks
+    in_solution: Option<usize>,
+    in_hidden: Option<usize>,
+    solution_file_seen: bool,
+    // the problems found while parsing, only collected when linting
+    lints: Option<Vec<(usize, MetaSyntaxLintKind)>>,
+}
+
+impl<B: BufRead> MetaSyntaxParser<B> {
+    fn lint(&mut self, kind: MetaSyntaxLintKind) {
+        if let Some(lints) = &mut self.lints {
+            lints.push((self.line_number, kind));
+        }
+    }
+
+    // returns the collected problems along with the blocks left open at the end of the file
+    fn finish_lints(self) -> Vec<(usize, MetaSyntaxLintKind)> {
+        let mut lints = self.lints.unwrap_or_default();
+        if let Some(line_number) = self.in_solution {
+            lints.push((line_number, MetaSyntaxLintKind::UnterminatedSolution));
+        }
+        if let Some(line_number) = self.in_hidden {
+            lints.push((line_number, MetaSyntaxLintKind::UnterminatedHidden));
+        }
+        if let Some((_, line_number)) = self.in_stub {
+            lints.push((line_number, MetaSyntaxLintKind::UnterminatedStub));
+        }
+        lints
+    }
 }
 
 impl<R: Read> MetaSyntaxParser<BufReader<R>> {
+    /// Creates a parser that uses the default comment syntax for the file extension.
     pub fn new(target: R, target_extension: &str) -> Self {
-        let reader = BufReader::new(target);
-        // Assigns each supported file extension with the proper comment syntax
-        let meta_syntaxes: &[MetaSyntax] = match target_extension {
-            "java" | "c" | "cpp" | "h" | "hpp" | "js" | "css" | "rs" | "qml" => &*META_SYNTAXES_C,
-            "xml" | "http" | "html" | "qrc" => &*META_SYNTAXES_HTML,
-            "properties" | "py" | "R" | "pro" => &*META_SYNTAXES_PY,
-            _ => &[],
-        };
-        Self {
-            meta_syntaxes,
-            reader,
-            in_stub: None,
-            in_solution: false,
-        }
+        DEFAULT_COMMENT_SYNTAXES.parser(target, target_extension)
     }
 }
 
@@ -121,13 +369,18 @@ impl<B: BufRead> Iterator for MetaSyntaxParser<B> {
             // read 0 bytes = reader empty = iterator empty
             Ok(0) => None,
             Ok(_) => {
+                self.line_number += 1;
                 // check line with each meta syntax
-                for meta_syntax in self.meta_syntaxes {
+                let meta_syntaxes = self.meta_syntaxes.clone();
+                for (i, meta_syntax) in meta_syntaxes.iter().enumerate() {
                     // check for stub
                     if self.in_stub.is_none() && meta_syntax.stub_begin.is_match(&s) {
                         debug!("stub start: '{}'", s);
+                        if self.in_solution.is_some() {
+                            self.lint(MetaSyntaxLintKind::StubInSolution);
+                        }
                         // save the syntax that started the current stub
-                        self.in_stub = Some(meta_syntax);
+                        self.in_stub = Some((i, self.line_number));
                         // remove stub start
                         s = meta_syntax
                             .stub_begin
@@ -135,15 +388,16 @@ impl<B: BufRead> Iterator for MetaSyntaxParser<B> {
                             .to_string();
                         debug!("parsed: '{}'", s);
                         if s.trim().is_empty() {
+                            if meta_syntax.line_comment {
+                                self.lint(MetaSyntaxLintKind::EmptyLineStub);
+                            }
                             // only metadata, skip
                             return self.next();
                         }
                     }
                     // if the line matches stub_end and the saved syntax matches
                     // the start of the current meta syntax
-                    if meta_syntax.stub_end.is_match(&s)
-                        && self.in_stub.map(|r| r.stub_begin.as_str())
-                            == Some(meta_syntax.stub_begin.as_str())
+                    if meta_syntax.stub_end.is_match(&s) && self.in_stub.map(|(j, _)| j) == Some(i)
                     {
                         debug!("stub end: '{}'", s);
                         self.in_stub = None;
@@ -161,20 +415,50 @@ impl<B: BufRead> Iterator for MetaSyntaxParser<B> {
 
                     // check for solution, skip all markers
                     if meta_syntax.solution_file.is_match(&s) {
+                        if self.solution_file_seen
+                            || self.in_solution.is_some()
+                            || self.in_stub.is_some()
+                        {
+                            self.lint(MetaSyntaxLintKind::MisplacedSolutionFile);
+                        }
+                        self.solution_file_seen = true;
                         return self.next();
                     } else if meta_syntax.solution_begin.is_match(&s) {
-                        self.in_solution = true;
+                        if self.in_solution.is_some() {
+                            self.lint(MetaSyntaxLintKind::NestedSolution);
+                        }
+                        self.in_solution = Some(self.line_number);
                         return self.next();
-                    } else if meta_syntax.solution_end.is_match(&s) && self.in_solution {
-                        self.in_solution = false;
+                    } else if meta_syntax.solution_end.is_match(&s) {
+                        if self.in_solution.is_some() {
+                            self.in_solution = None;
+                            return self.next();
+                        }
+                        // kept as a regular line
+                        self.lint(MetaSyntaxLintKind::UnmatchedSolutionEnd);
+                    }
+
+                    // check for hidden blocks, skip all markers
+                    if meta_syntax.hidden_begin.is_match(&s) {
+                        self.in_hidden = Some(self.line_number);
                         return self.next();
+                    } else if meta_syntax.hidden_end.is_match(&s) {
+                        if self.in_hidden.is_some() {
+                            self.in_hidden = None;
+                            return self.next();
+                        }
+                        // kept as a regular line
+                        self.lint(MetaSyntaxLintKind::UnmatchedHiddenEnd);
                     }
                 }
                 // after processing the line with each meta syntax,
                 // parse the current line accordingly
-                if self.in_solution {
+                if self.in_solution.is_some() {
                     debug!("solution: '{}'", s);
                     Some(Ok(MetaString::Solution(s)))
+                } else if self.in_hidden.is_some() {
+                    debug!("hidden: '{}'", s);
+                    Some(Ok(MetaString::Hidden(s)))
                 } else if self.in_stub.is_some() {
                     debug!("stub: '{}'", s);
                     Some(Ok(MetaString::Stub(s)))
@@ -208,6 +492,10 @@ mod test {
         fn stub(s: &str) -> Self {
             Self::Stub(s.to_string())
         }
+
+        fn hidden(s: &str) -> Self {
+            Self::Hidden(s.to_string())
+        }
     }
 
     #[test]
@@ -299,4 +587,204 @@ public class JavaTestCase {
         let actual = filter.map(|l| l.unwrap()).collect::<Vec<MetaString>>();
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn parse_default_syntaxes() {
+        init();
+
+        const SQL_FILE: &str = r#"SELECT name
+-- BEGIN SOLUTION
+FROM students;
+-- END SOLUTION
+/* STUB: FROM ...; */
+"#;
+        let expected: Vec<MetaString> = vec![
+            MetaString::str("SELECT name\n"),
+            MetaString::solution("FROM students;\n"),
+            MetaString::stub("FROM ...; "),
+        ];
+
+        let source = SQL_FILE.as_bytes();
+        let filter = MetaSyntaxParser::new(source, "sql");
+        let actual = filter.map(|l| l.unwrap()).collect::<Vec<MetaString>>();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn parse_repeated_line_comments() {
+        init();
+
+        const LISP_FILE: &str = r#";; BEGIN SOLUTION
+(x)
+;; END SOLUTION
+;; STUB: (y)
+"#;
+        let expected: Vec<MetaString> =
+            vec![MetaString::solution("(x)\n"), MetaString::stub("(y)\n")];
+
+        let source = LISP_FILE.as_bytes();
+        let filter = MetaSyntaxParser::new(source, "lisp");
+        let actual = filter.map(|l| l.unwrap()).collect::<Vec<MetaString>>();
+        assert_eq!(expected, actual);
+
+        const MATLAB_FILE: &str = r#"%% BEGIN SOLUTION
+x = 1;
+%% END SOLUTION
+"#;
+        // .m is not mapped by default as it is also used by Objective-C
+        let source = MATLAB_FILE.as_bytes();
+        let filter = MetaSyntaxParser::new(source, "m");
+        let actual = filter.map(|l| l.unwrap()).collect::<Vec<MetaString>>();
+        assert_eq!(actual.len(), 3);
+
+        let mut comment_syntaxes = CommentSyntaxes::default();
+        comment_syntaxes.insert("m", &[CommentSyntax::new("%", None)]);
+        let source = MATLAB_FILE.as_bytes();
+        let filter = comment_syntaxes.parser(source, "m");
+        let actual = filter.map(|l| l.unwrap()).collect::<Vec<MetaString>>();
+        assert_eq!(actual, &[MetaString::solution("x = 1;\n")]);
+    }
+
+    #[test]
+    fn parse_configured_syntaxes() {
+        init();
+
+        const VB_FILE: &str = r#"Module Hello
+    ' BEGIN SOLUTION
+    Sub Main()
+    End Sub
+    ' END SOLUTION
+    ' STUB: Sub Main()
+End Module
+"#;
+        let expected: Vec<MetaString> = vec![
+            MetaString::str("Module Hello\n"),
+            MetaString::solution("    Sub Main()\n"),
+            MetaString::solution("    End Sub\n"),
+            MetaString::stub("    Sub Main()\n"),
+            MetaString::str("End Module\n"),
+        ];
+
+        let mut comment_syntaxes = CommentSyntaxes::default();
+        comment_syntaxes.insert("vb", &[CommentSyntax::new("'", None)]);
+        let source = VB_FILE.as_bytes();
+        let filter = comment_syntaxes.parser(source, "vb");
+        let actual = filter.map(|l| l.unwrap()).collect::<Vec<MetaString>>();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn lints_meta_syntax() {
+        init();
+
+        const JAVA_FILE: &str = r#"public class JavaTestCase {
+    // BEGIN SOLUTION
+    // BEGIN SOLUTION
+    // STUB: return 0;
+    // SOLUTION FILE
+    // END SOLUTION
+    // END SOLUTION
+    /* STUB:
+    // BEGIN SOLUTION
+}
+"#;
+        let file = Path::new("JavaTestCase.java");
+        let lints = CommentSyntaxes::default()
+            .lint(file, JAVA_FILE.as_bytes(), "java")
+            .unwrap();
+        let lints: Vec<_> = lints.into_iter().map(|l| (l.line, l.kind)).collect();
+        assert_eq!(
+            lints,
+            &[
+                (3, MetaSyntaxLintKind::NestedSolution),
+                (4, MetaSyntaxLintKind::StubInSolution),
+                (5, MetaSyntaxLintKind::MisplacedSolutionFile),
+                (7, MetaSyntaxLintKind::UnmatchedSolutionEnd),
+                (9, MetaSyntaxLintKind::UnterminatedSolution),
+                (8, MetaSyntaxLintKind::UnterminatedStub),
+            ]
+        );
+
+        const VALID_FILE: &str = r#"/* SOLUTION FILE */
+public class JavaTestCase {
+    // BEGIN SOLUTION
+    public int foo() {
+        return 3;
+    }
+    // END SOLUTION
+    /* STUB:
+    public int foo() {
+        return 0;
+    }
+    */
+}
+"#;
+        let lints = CommentSyntaxes::default()
+            .lint(file, VALID_FILE.as_bytes(), "java")
+            .unwrap();
+        assert!(lints.is_empty(), "{:?}", lints);
+    }
+
+    #[test]
+    fn lints_like_parser() {
+        init();
+
+        // the empty stub line comment makes the next line a stub, dropping it from the solution
+        const JAVA_FILE: &str = "a();\n// STUB:\nb();\nc();\n";
+        let expected: Vec<MetaString> = vec![
+            MetaString::str("a();\n"),
+            MetaString::stub("b();\n"),
+            MetaString::str("c();\n"),
+        ];
+        let source = JAVA_FILE.as_bytes();
+        let filter = MetaSyntaxParser::new(source, "java");
+        let actual = filter.map(|l| l.unwrap()).collect::<Vec<MetaString>>();
+        assert_eq!(expected, actual);
+
+        let lints = CommentSyntaxes::default()
+            .lint(Path::new("Main.java"), JAVA_FILE.as_bytes(), "java")
+            .unwrap();
+        let lints: Vec<_> = lints.into_iter().map(|l| (l.line, l.kind)).collect();
+        assert_eq!(lints, &[(2, MetaSyntaxLintKind::EmptyLineStub)]);
+    }
+
+    #[test]
+    fn parse_hidden() {
+        init();
+
+        const JAVA_TEST_FILE: &str = r#"public class JavaTest {
+    // BEGIN HIDDEN
+    @Test
+    public void hidden() {}
+    // END HIDDEN
+}
+"#;
+        let expected: Vec<MetaString> = vec![
+            MetaString::str("public class JavaTest {\n"),
+            MetaString::hidden("    @Test\n"),
+            MetaString::hidden("    public void hidden() {}\n"),
+            MetaString::str("}\n"),
+        ];
+
+        let source = JAVA_TEST_FILE.as_bytes();
+        let filter = MetaSyntaxParser::new(source, "java");
+        let actual = filter.map(|l| l.unwrap()).collect::<Vec<MetaString>>();
+        assert_eq!(expected, actual);
+
+        let lints = CommentSyntaxes::default()
+            .lint(
+                Path::new("test.py"),
+                "# END HIDDEN\n# BEGIN HIDDEN\n".as_bytes(),
+                "py",
+            )
+            .unwrap();
+        let lints: Vec<_> = lints.into_iter().map(|l| (l.line, l.kind)).collect();
+        assert_eq!(
+            lints,
+            &[
+                (1, MetaSyntaxLintKind::UnmatchedHiddenEnd),
+                (2, MetaSyntaxLintKind::UnterminatedHidden),
+            ]
+        );
+    }
 }
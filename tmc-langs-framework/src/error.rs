@@ -29,6 +29,8 @@ pub enum TmcError {
     TarAppend(#[source] std::io::Error),
     #[error("Error finishing tar")]
     TarFinish(#[source] std::io::Error),
+    #[error("Failed to unpack tar archive at {0}")]
+    TarUnpack(PathBuf, #[source] std::io::Error),
     #[error("Failed to read line")]
     ReadLine(#[source] std::io::Error),
     #[error("Failed to copy file from {0} to {1}")]
@@ -49,6 +51,12 @@ pub enum TmcError {
     FileNotInProject(PathBuf, PathBuf),
     #[error("Path {0} is not absolute")]
     PathNotAbsolute(PathBuf),
+    #[error("Path {0} is not relative")]
+    PathNotRelative(PathBuf),
+    #[error("Path {0} points outside of the directory it is relative to")]
+    PathEscapesRoot(PathBuf),
+    #[error("Symlink {0} does not resolve to a path within {1}")]
+    SymlinkEscapesRoot(PathBuf, PathBuf),
 
     #[error("Path {0} contained invalid UTF8")]
     UTF8(PathBuf),
@@ -57,8 +65,26 @@ pub enum TmcError {
 
     #[error("No matching plugin found for {0}")]
     PluginNotFound(PathBuf),
+    #[error("Failed to read the plugin key from {0}: {1}")]
+    InvalidPluginKey(PathBuf, String),
+    #[error("The plugin {0} set in .tmcproject.yml is not registered")]
+    UnknownPlugin(String),
     #[error("No project directory found in archive during unzip")]
     NoProjectDirInZip,
+    #[error(
+        "The format of the archive at {0} is not supported, expected zip, tar, tar.gz or tar.zst"
+    )]
+    UnknownArchiveFormat(PathBuf),
+    #[error("The archive at {0} has more entries than the limit of {1}")]
+    ArchiveTooManyEntries(PathBuf, u64),
+    #[error("The file {0} in the archive is larger than the limit of {1} bytes")]
+    ArchiveFileTooLarge(PathBuf, u64),
+    #[error("The contents of the archive at {0} are larger than the limit of {1} bytes")]
+    ArchiveTotalSizeTooLarge(PathBuf, u64),
+    #[error(
+        "The compression ratio of the file {0} in the archive is higher than the limit of {1}"
+    )]
+    ArchiveCompressionRatioTooHigh(PathBuf, u64),
     #[error("Running command '{0}' failed")]
     CommandFailed(&'static str, #[source] std::io::Error),
 
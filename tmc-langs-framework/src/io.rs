@@ -1,5 +1,7 @@
 //! Contains functions for handling project directories and archives.
 
+pub mod archive;
 pub mod file_util;
+pub mod path_util;
 pub mod submission_processing;
 pub mod tmc_zip;
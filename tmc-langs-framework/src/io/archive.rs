@@ -0,0 +1,574 @@
+//! Contains functions for creating and reading archives in the supported formats.
+
+use crate::io::{
+    file_util, path_util,
+    tmc_zip::{self, Reproducibility, UnzipLimiter, UnzipLimits, COMPRESSION_RATIO_MIN_SIZE},
+};
+use crate::{Result, TmcError};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tar::{EntryType, HeaderMode};
+use walkdir::WalkDir;
+use zip::{read::ZipFile, write::FileOptions, ZipArchive, ZipWriter};
+
+/// The formats archives can be created and extracted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarZstd,
+}
+
+impl ArchiveFormat {
+    /// Detects the format of the archive at the path from its magic bytes.
+    /// Compressed archives are assumed to contain a tar archive.
+    pub fn detect(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|e| TmcError::OpenFile(path.to_path_buf(), e))?;
+        let mut header = vec![];
+        file.take(512)
+            .read_to_end(&mut header)
+            .map_err(|e| TmcError::FileRead(path.to_path_buf(), e))?;
+
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Ok(Self::Zip)
+        } else if header.starts_with(&[0x1f, 0x8b]) {
+            Ok(Self::TarGz)
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Ok(Self::TarZstd)
+        } else if header.get(257..262) == Some(b"ustar") {
+            Ok(Self::Tar)
+        } else {
+            Err(TmcError::UnknownArchiveFormat(path.to_path_buf()))
+        }
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = TmcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "zip" => Ok(Self::Zip),
+            "tar" => Ok(Self::Tar),
+            "tar.gz" => Ok(Self::TarGz),
+            "tar.zst" => Ok(Self::TarZstd),
+            _ => Err(TmcError::InvalidParam(format!(
+                "unknown archive format {}, expected one of zip, tar, tar.gz or tar.zst",
+                s
+            ))),
+        }
+    }
+}
+
+/// Archives the contents of the source directory into a new file at the target path, nested inside the prefix directory.
+/// The Unix permissions of the files are stored in every format.
+/// With `Reproducibility::Deterministic`, the entries are sorted and their metadata is normalised so that the same files always produce the same archive.
+pub fn create_archive(
+    source: &Path,
+    prefix: &Path,
+    target: &Path,
+    format: ArchiveFormat,
+    reproducibility: Reproducibility,
+) -> Result<()> {
+    log::debug!(
+        "archiving {} to {} as {:?}",
+        source.display(),
+        target.display(),
+        format
+    );
+
+    let file = file_util::create_file(target)?;
+    match format {
+        ArchiveFormat::Zip => write_zip(source, prefix, file, reproducibility)?,
+        ArchiveFormat::Tar => {
+            write_tar(source, prefix, file, reproducibility)?;
+        }
+        ArchiveFormat::TarGz => {
+            let encoder = GzEncoder::new(file, Compression::default());
+            write_tar(source, prefix, encoder, reproducibility)?
+                .finish()
+                .map_err(|e| TmcError::Write(target.to_path_buf(), e))?;
+        }
+        ArchiveFormat::TarZstd => {
+            let encoder = zstd::Encoder::new(file, 0)
+                .map_err(|e| TmcError::Write(target.to_path_buf(), e))?;
+            write_tar(source, prefix, encoder, reproducibility)?
+                .finish()
+                .map_err(|e| TmcError::Write(target.to_path_buf(), e))?;
+        }
+    }
+    Ok(())
+}
+
+fn walk(source: &Path, reproducibility: Reproducibility) -> WalkDir {
+    let walker = WalkDir::new(source);
+    if reproducibility == Reproducibility::Deterministic {
+        walker.sort_by(|a, b| a.file_name().cmp(b.file_name()))
+    } else {
+        walker
+    }
+}
+
+fn write_zip(
+    source: &Path,
+    prefix: &Path,
+    file: File,
+    reproducibility: Reproducibility,
+) -> Result<()> {
+    let mut archive = ZipWriter::new(file);
+    for entry in walk(source, reproducibility).into_iter().skip(1) {
+        let entry = entry?;
+        let entry_path = entry.path();
+        let stripped = prefix.join(entry_path.strip_prefix(source).unwrap());
+        log::debug!(
+            "adding {} to zip at {}",
+            entry_path.display(),
+            stripped.display()
+        );
+        let options = zip_file_options(entry_path, reproducibility)?;
+        let name = zip_entry_name(&stripped)?;
+        if entry_path.is_dir() {
+            archive.add_directory(name, options)?;
+        } else {
+            archive.start_file(name, options)?;
+            let mut file = file_util::open_file(entry_path)?;
+            std::io::copy(&mut file, &mut archive)
+                .map_err(|e| TmcError::FileRead(entry_path.to_path_buf(), e))?;
+        }
+    }
+    archive.finish()?;
+    Ok(())
+}
+
+// unlike the zips created by `tmc_zip::zip`, the archives keep the permissions of the files like tar does
+fn zip_file_options(path: &Path, reproducibility: Reproducibility) -> Result<FileOptions> {
+    let options = tmc_zip::file_options(path, reproducibility)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if reproducibility == Reproducibility::NonDeterministic {
+            let metadata =
+                std::fs::metadata(path).map_err(|e| TmcError::FileRead(path.to_path_buf(), e))?;
+            return Ok(options.unix_permissions(metadata.permissions().mode()));
+        }
+    }
+    Ok(options)
+}
+
+// zip entry names use forward slashes on every platform
+pub(crate) fn zip_entry_name(path: &Path) -> Result<String> {
+    let components = path
+        .components()
+        .map(|c| {
+            c.as_os_str()
+                .to_str()
+                .ok_or_else(|| TmcError::UTF8(path.to_path_buf()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(components.join("/"))
+}
+
+fn write_tar<W: Write>(
+    source: &Path,
+    prefix: &Path,
+    writer: W,
+    reproducibility: Reproducibility,
+) -> Result<W> {
+    let mut archive = tar::Builder::new(writer);
+    if reproducibility == Reproducibility::Deterministic {
+        archive.mode(HeaderMode::Deterministic);
+        for entry in walk(source, reproducibility) {
+            let entry = entry?;
+            let entry_path = entry.path();
+            let stripped = prefix.join(entry_path.strip_prefix(source).unwrap());
+            if stripped == Path::new("") {
+                // the root directory without a prefix has no entry
+                continue;
+            }
+            log::debug!(
+                "adding {} to tar at {}",
+                entry_path.display(),
+                stripped.display()
+            );
+            archive
+                .append_path_with_name(entry_path, &stripped)
+                .map_err(TmcError::TarAppend)?;
+        }
+    } else {
+        log::debug!(
+            "appending \"{}\" at \"{}\"",
+            source.display(),
+            prefix.display()
+        );
+        archive
+            .append_dir_all(prefix, source)
+            .map_err(TmcError::TarAppend)?;
+    }
+    archive.into_inner().map_err(TmcError::TarFinish)
+}
+
+/// The kind of an entry in an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    /// Symlinks and other special entries, which are never extracted.
+    Other,
+}
+
+/// An entry of an archive, given by `Archive::for_each_entry`. Reading it streams the entry's contents from the archive.
+// only one entry exists at a time, so the size of the variants doesn't matter
+#[allow(clippy::large_enum_variant)]
+pub enum ArchiveEntry<'a> {
+    Zip(ZipFile<'a>),
+    Tar(tar::Entry<'a, Box<dyn Read>>),
+}
+
+impl ArchiveEntry<'_> {
+    /// The path of the entry as it is stored in the archive.
+    pub fn name(&self) -> Cow<'_, str> {
+        match self {
+            Self::Zip(file) => Cow::Borrowed(file.name()),
+            Self::Tar(entry) => match entry.path_bytes() {
+                Cow::Borrowed(bytes) => String::from_utf8_lossy(bytes),
+                Cow::Owned(bytes) => Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()),
+            },
+        }
+    }
+
+    /// The path of the entry, sanitized with `path_util::sanitize_relative_path`.
+    pub fn path(&self) -> Result<PathBuf> {
+        path_util::sanitize_relative_path(Path::new(self.name().as_ref()))
+    }
+
+    pub fn kind(&self) -> EntryKind {
+        match self {
+            Self::Zip(file) => {
+                let is_symlink = file
+                    .unix_mode()
+                    .map(|mode| mode & 0o170_000 == 0o120_000)
+                    .unwrap_or_default();
+                if is_symlink {
+                    EntryKind::Other
+                } else if file.is_dir() {
+                    EntryKind::Directory
+                } else {
+                    EntryKind::File
+                }
+            }
+            Self::Tar(entry) => match entry.header().entry_type() {
+                EntryType::Directory => EntryKind::Directory,
+                EntryType::Regular | EntryType::Continuous => EntryKind::File,
+                _ => EntryKind::Other,
+            },
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind() == EntryKind::Directory
+    }
+
+    /// The Unix permissions stored for the entry, if any.
+    pub fn unix_mode(&self) -> Option<u32> {
+        match self {
+            Self::Zip(file) => file.unix_mode(),
+            Self::Tar(entry) => entry.header().mode().ok(),
+        }
+    }
+
+    /// The uncompressed size of the entry declared in the archive.
+    pub fn size(&self) -> u64 {
+        match self {
+            Self::Zip(file) => file.size(),
+            Self::Tar(entry) => entry.size(),
+        }
+    }
+
+    /// The compressed size of the entry, for formats that compress each entry separately.
+    pub fn compressed_size(&self) -> Option<u64> {
+        match self {
+            Self::Zip(file) => Some(file.compressed_size()),
+            Self::Tar(_) => None,
+        }
+    }
+}
+
+impl Read for ArchiveEntry<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Zip(file) => file.read(buf),
+            Self::Tar(entry) => entry.read(buf),
+        }
+    }
+}
+
+/// An archive opened for extraction in any of the supported formats.
+pub struct Archive {
+    path: PathBuf,
+    limits: UnzipLimits,
+    kind: ArchiveKind,
+}
+
+enum ArchiveKind {
+    Zip(ZipArchive<File>),
+    // tar archives can only be read in order, so they are opened again for every pass over the entries
+    Tar(ArchiveFormat),
+}
+
+impl Archive {
+    /// Opens the archive at the path, detecting its format with `ArchiveFormat::detect`.
+    pub fn open(path: &Path, limits: UnzipLimits) -> Result<Self> {
+        let format = ArchiveFormat::detect(path)?;
+        log::debug!("opening {} as {:?}", path.display(), format);
+
+        let kind = match format {
+            ArchiveFormat::Zip => {
+                let file =
+                    File::open(path).map_err(|e| TmcError::OpenFile(path.to_path_buf(), e))?;
+                let zip_archive = ZipArchive::new(file)?;
+                if zip_archive.len() as u64 > limits.max_entries {
+                    return Err(TmcError::ArchiveTooManyEntries(
+                        path.to_path_buf(),
+                        limits.max_entries,
+                    ));
+                }
+                ArchiveKind::Zip(zip_archive)
+            }
+            format => ArchiveKind::Tar(format),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            limits,
+            kind,
+        })
+    }
+
+    /// Creates a limiter for extracting the entries of this archive within its limits.
+    pub fn limiter(&self) -> UnzipLimiter {
+        UnzipLimiter::new(&self.path, self.limits)
+    }
+
+    /// Returns the path and kind of each entry, for example to find the project directory before extracting anything.
+    pub fn list_entries(&mut self) -> Result<Vec<(PathBuf, EntryKind)>> {
+        let mut entries = vec![];
+        self.for_each_entry(|entry| {
+            entries.push((entry.path()?, entry.kind()));
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+
+    /// Calls the function with each entry of the archive in order.
+    /// The number of entries is checked against the limits. The sizes in tar headers are exact and every entry
+    /// of a tar archive is decompressed even if it is not extracted, so for tar archives the declared sizes
+    /// and the compression ratio of the archive as a whole are checked as well.
+    pub fn for_each_entry<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&mut ArchiveEntry) -> Result<()>,
+    {
+        let path = &self.path;
+        let limits = self.limits;
+        match &mut self.kind {
+            ArchiveKind::Zip(zip_archive) => {
+                for i in 0..zip_archive.len() {
+                    let file = zip_archive.by_index(i)?;
+                    f(&mut ArchiveEntry::Zip(file))?;
+                }
+            }
+            ArchiveKind::Tar(format) => {
+                let unpack_error = |e| TmcError::TarUnpack(path.to_path_buf(), e);
+                let file =
+                    File::open(path).map_err(|e| TmcError::OpenFile(path.to_path_buf(), e))?;
+                let compressed_size = file
+                    .metadata()
+                    .map_err(|e| TmcError::FileRead(path.to_path_buf(), e))?
+                    .len();
+                let (reader, compressed_size): (Box<dyn Read>, _) = match format {
+                    ArchiveFormat::Tar => (Box::new(file), None),
+                    ArchiveFormat::TarGz => (Box::new(GzDecoder::new(file)), Some(compressed_size)),
+                    ArchiveFormat::TarZstd => (
+                        Box::new(zstd::Decoder::new(file).map_err(unpack_error)?),
+                        Some(compressed_size),
+                    ),
+                    ArchiveFormat::Zip => unreachable!("zip archives are not read as tar archives"),
+                };
+
+                let mut archive = tar::Archive::new(reader);
+                let mut entry_count = 0;
+                let mut total_size = 0;
+                for entry in archive.entries().map_err(unpack_error)? {
+                    let entry = entry.map_err(unpack_error)?;
+                    entry_count += 1;
+                    if entry_count > limits.max_entries {
+                        return Err(TmcError::ArchiveTooManyEntries(
+                            path.to_path_buf(),
+                            limits.max_entries,
+                        ));
+                    }
+                    let mut entry = ArchiveEntry::Tar(entry);
+                    let size = entry.size();
+                    if size > limits.max_file_size {
+                        return Err(TmcError::ArchiveFileTooLarge(
+                            PathBuf::from(entry.name().as_ref()),
+                            limits.max_file_size,
+                        ));
+                    }
+                    total_size += size;
+                    if total_size > limits.max_total_size {
+                        return Err(TmcError::ArchiveTotalSizeTooLarge(
+                            path.to_path_buf(),
+                            limits.max_total_size,
+                        ));
+                    }
+                    if let Some(compressed_size) = compressed_size {
+                        if total_size > COMPRESSION_RATIO_MIN_SIZE
+                            && total_size / compressed_size.max(1) > limits.max_compression_ratio
+                        {
+                            return Err(TmcError::ArchiveCompressionRatioTooHigh(
+                                path.to_path_buf(),
+                                limits.max_compression_ratio,
+                            ));
+                        }
+                    }
+                    f(&mut entry)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn parses_format() {
+        init();
+
+        assert_eq!(
+            "tar.zst".parse::<ArchiveFormat>().unwrap(),
+            ArchiveFormat::TarZstd
+        );
+        assert!("rar".parse::<ArchiveFormat>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn round_trips_every_format() {
+        use std::os::unix::fs::PermissionsExt;
+
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("source");
+        fs::create_dir_all(source.join("src")).unwrap();
+        fs::write(source.join("src/main.c"), "int main() {}\n").unwrap();
+        fs::write(source.join("run.sh"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(source.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        for (i, format) in [
+            ArchiveFormat::Zip,
+            ArchiveFormat::Tar,
+            ArchiveFormat::TarGz,
+            ArchiveFormat::TarZstd,
+        ]
+        .iter()
+        .enumerate()
+        {
+            for (j, reproducibility) in [
+                Reproducibility::Deterministic,
+                Reproducibility::NonDeterministic,
+            ]
+            .iter()
+            .enumerate()
+            {
+                let archive_path = temp.path().join(format!("archive{}{}", i, j));
+                create_archive(
+                    &source,
+                    Path::new("exercise"),
+                    &archive_path,
+                    *format,
+                    *reproducibility,
+                )
+                .unwrap();
+                assert_eq!(ArchiveFormat::detect(&archive_path).unwrap(), *format);
+
+                let target = temp.path().join(format!("target{}{}", i, j));
+                file_util::extract_archive(
+                    &archive_path,
+                    &target,
+                    |_| false,
+                    UnzipLimits::default(),
+                )
+                .unwrap();
+                assert_eq!(
+                    fs::read_to_string(target.join("exercise/src/main.c")).unwrap(),
+                    "int main() {}\n"
+                );
+                let mode = fs::metadata(target.join("exercise/run.sh"))
+                    .unwrap()
+                    .permissions()
+                    .mode();
+                assert_eq!(mode & 0o777, 0o755, "{:?}", format);
+            }
+        }
+    }
+
+    #[test]
+    fn checks_limits_for_tar() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("source");
+        fs::create_dir(&source).unwrap();
+        fs::write(
+            source.join("zeros"),
+            vec![0; 2 * COMPRESSION_RATIO_MIN_SIZE as usize],
+        )
+        .unwrap();
+        let archive_path = temp.path().join("archive.tar.gz");
+        create_archive(
+            &source,
+            Path::new(""),
+            &archive_path,
+            ArchiveFormat::TarGz,
+            Reproducibility::Deterministic,
+        )
+        .unwrap();
+
+        let res = Archive::open(&archive_path, UnzipLimits::default())
+            .unwrap()
+            .list_entries();
+        assert!(matches!(
+            res,
+            Err(TmcError::ArchiveCompressionRatioTooHigh(_, 200))
+        ));
+        let limits = UnzipLimits {
+            max_file_size: 1024,
+            ..UnzipLimits::default()
+        };
+        let res = Archive::open(&archive_path, limits).unwrap().list_entries();
+        assert!(matches!(res, Err(TmcError::ArchiveFileTooLarge(_, 1024))));
+
+        // extracting checks the ratio before anything is written
+        let target = temp.path().join("target");
+        let res =
+            file_util::extract_archive(&archive_path, &target, |_| false, UnzipLimits::default());
+        assert!(matches!(
+            res,
+            Err(TmcError::ArchiveCompressionRatioTooHigh(_, 200))
+        ));
+        assert!(!target.join("zeros").exists());
+    }
+}
@@ -1,42 +1,50 @@
 //! Various utility functions, primarily wrapping the standard library's IO and filesystem functions
 
+use crate::io::archive::{Archive, ArchiveEntry};
+use crate::io::path_util;
+use crate::io::tmc_zip::UnzipLimits;
 use crate::TmcError;
 use std::ffi::OsStr;
 use std::fs::{self, File};
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
-use zip::{read::ZipFile, ZipArchive};
 
-pub fn unzip<P: AsRef<Path>, Q: AsRef<Path>, F>(
-    zip_path: P,
+/// Extracts the archive to the target directory. See `archive::ArchiveFormat` for the supported formats.
+/// Skips the entries for which the filter returns true.
+/// Returns an error if the archive exceeds the given limits or if an entry's path points outside of the target directory.
+pub fn extract_archive<P: AsRef<Path>, Q: AsRef<Path>, F>(
+    archive_path: P,
     target: Q,
     filter: F,
+    limits: UnzipLimits,
 ) -> Result<(), TmcError>
 where
-    F: Fn(&ZipFile) -> bool,
+    F: Fn(&ArchiveEntry) -> bool,
 {
-    let zip_path = zip_path.as_ref();
+    let archive_path = archive_path.as_ref();
 
     let target = target.as_ref();
-    log::debug!("unzip from {} to {}", zip_path.display(), target.display());
-
-    let archive = open_file(zip_path)?;
-    let mut archive = ZipArchive::new(archive)?;
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        if filter(&file) {
-            continue;
+    log::debug!(
+        "extracting {} to {}",
+        archive_path.display(),
+        target.display()
+    );
+
+    let mut archive = Archive::open(archive_path, limits)?;
+    let mut limiter = archive.limiter();
+    archive.for_each_entry(|file| {
+        if filter(file) {
+            return Ok(());
         }
 
-        let target_path = target.join(file.sanitized_name());
+        let target_path = path_util::safe_join(target, &file.path()?)?;
         if file.is_dir() {
-            create_dir(target_path)?;
+            create_dir(target_path)
         } else {
-            write_to_file(&mut file, target_path)?;
+            limiter.extract_file(file, &target_path)
         }
-    }
-    Ok(())
+    })
 }
 
 pub fn create_file<P: AsRef<Path>>(path: P) -> Result<File, TmcError> {
@@ -116,3 +124,52 @@ pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(source: P, target: Q) -> Result<(),
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn extract_archive_rejects_escaping_paths() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("a/b");
+        let res = extract_archive(
+            "tests/data/zip/path-traversal.zip",
+            &target,
+            |_| false,
+            UnzipLimits::default(),
+        );
+        assert!(matches!(res, Err(TmcError::PathEscapesRoot(_))));
+        assert!(!temp.path().join("escaped.txt").exists());
+
+        let res = extract_archive(
+            "tests/data/zip/absolute-path.zip",
+            &target,
+            |_| false,
+            UnzipLimits::default(),
+        );
+        assert!(matches!(res, Err(TmcError::PathNotRelative(_))));
+    }
+
+    #[test]
+    fn extract_archive_skips_symlinks() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        extract_archive(
+            "tests/data/zip/symlink.zip",
+            temp.path(),
+            |_| false,
+            UnzipLimits::default(),
+        )
+        .unwrap();
+        assert!(temp.path().join("exercise/src/Main.java").exists());
+        assert!(fs::symlink_metadata(temp.path().join("exercise/src/link")).is_err());
+    }
+}
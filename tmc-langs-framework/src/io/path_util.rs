@@ -0,0 +1,118 @@
+//! Contains functions for handling untrusted relative paths, such as the paths of archive entries and the paths in .tmcproject.yml.
+
+use crate::{Result, TmcError};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Normalizes a relative path from an untrusted source by removing `.` components and resolving `..` components lexically.
+/// Returns an error if the path is absolute or if it escapes the directory it is relative to.
+pub fn sanitize_relative_path(path: &Path) -> Result<PathBuf> {
+    let mut sanitized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => sanitized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !sanitized.pop() {
+                    return Err(TmcError::PathEscapesRoot(path.to_path_buf()));
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(TmcError::PathNotRelative(path.to_path_buf()))
+            }
+        }
+    }
+    Ok(sanitized)
+}
+
+/// Sanitizes the relative path with `sanitize_relative_path` and joins it to the root directory.
+/// Any symlinks that already exist along the joined path are resolved,
+/// and an error is returned if one of them does not resolve to a path within the root directory.
+pub fn safe_join(root: &Path, relative: &Path) -> Result<PathBuf> {
+    let relative = sanitize_relative_path(relative)?;
+    let joined = root.join(&relative);
+    let mut canonical_root = None;
+    let mut current = root.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        let metadata = match fs::symlink_metadata(&current) {
+            Ok(metadata) => metadata,
+            // the rest of the path does not exist yet, so it can't contain symlinks
+            Err(_) => break,
+        };
+        if metadata.file_type().is_symlink() {
+            if canonical_root.is_none() {
+                canonical_root = Some(
+                    root.canonicalize()
+                        .map_err(|e| TmcError::Canonicalize(root.to_path_buf(), e))?,
+                );
+            }
+            // dangling symlinks can't be resolved, and writing through one could create a file anywhere
+            let resolved = current.canonicalize().ok();
+            match (resolved, &canonical_root) {
+                (Some(resolved), Some(canonical_root)) if resolved.starts_with(canonical_root) => {}
+                _ => return Err(TmcError::SymlinkEscapesRoot(current, root.to_path_buf())),
+            }
+        }
+    }
+    Ok(joined)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn sanitizes_relative_paths() {
+        init();
+
+        assert_eq!(
+            sanitize_relative_path(Path::new("./src/../test/Test.java")).unwrap(),
+            Path::new("test/Test.java")
+        );
+        assert!(matches!(
+            sanitize_relative_path(Path::new("src/../../etc/passwd")),
+            Err(TmcError::PathEscapesRoot(_))
+        ));
+        assert!(matches!(
+            sanitize_relative_path(Path::new("/etc/passwd")),
+            Err(TmcError::PathNotRelative(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_escaping_symlinks() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("root");
+        let outside = temp.path().join("outside");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir(&outside).unwrap();
+        std::os::unix::fs::symlink(root.join("src"), root.join("inside_link")).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("outside_link")).unwrap();
+        std::os::unix::fs::symlink(root.join("missing"), root.join("dangling_link")).unwrap();
+
+        assert_eq!(
+            safe_join(&root, Path::new("src/new/file")).unwrap(),
+            root.join("src/new/file")
+        );
+        assert_eq!(
+            safe_join(&root, Path::new("inside_link/file")).unwrap(),
+            root.join("inside_link/file")
+        );
+        assert!(matches!(
+            safe_join(&root, Path::new("outside_link/file")),
+            Err(TmcError::SymlinkEscapesRoot(_, _))
+        ));
+        assert!(matches!(
+            safe_join(&root, Path::new("dangling_link")),
+            Err(TmcError::SymlinkEscapesRoot(_, _))
+        ));
+    }
+}
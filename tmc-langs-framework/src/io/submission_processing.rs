@@ -3,10 +3,12 @@
 use crate::policy::StudentFilePolicy;
 use crate::{Result, TmcError};
 
-use crate::domain::meta_syntax::{MetaString, MetaSyntaxParser};
+use crate::domain::meta_syntax::{CommentSyntaxes, MetaString, MetaSyntaxLint};
+use crate::domain::{FilePreview, TmcProjectYml};
 use lazy_static::lazy_static;
 use log::{debug, info};
 use regex::Regex;
+use similar::TextDiff;
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -100,6 +102,7 @@ fn copy_file<F: Fn(&MetaString) -> bool>(
     entry: &DirEntry,
     source_root: &Path,
     dest_root: &Path,
+    comment_syntaxes: &CommentSyntaxes,
     filter: &mut F,
 ) -> Result<()> {
     let is_dir = entry.metadata().map(|e| e.is_dir()).unwrap_or_default();
@@ -136,35 +139,63 @@ fn copy_file<F: Fn(&MetaString) -> bool>(
             dest_path
         );
 
-        let source_file = File::open(entry.path())
-            .map_err(|e| TmcError::OpenFile(entry.path().to_path_buf(), e))?;
-
+        let filtered = filter_text_file(
+            entry.path(),
+            comment_syntaxes,
+            extension.unwrap_or_default(),
+            filter,
+        )?;
         let mut target_file = File::create(&dest_path)
             .map_err(|e| TmcError::CreateFile(entry.path().to_path_buf(), e))?;
-
-        let parser = MetaSyntaxParser::new(source_file, extension.unwrap_or_default());
-
-        // todo: reduce collection?
-        // filtered metastrings
-        let filtered: Vec<MetaString> = parser
-            .collect::<Result<Vec<_>>>()?
-            .into_iter()
-            .filter(filter)
-            .collect();
-        // collects the filtered lines into a byte vector
-        let write_lines: Vec<u8> = filtered
-            .iter()
-            .flat_map(|l| l.as_str().as_bytes())
-            .copied()
-            .collect();
-        // writes all lines
         target_file
-            .write_all(&write_lines)
+            .write_all(filtered.as_bytes())
             .map_err(|e| TmcError::Write(dest_path, e))?;
     }
     Ok(())
 }
 
+// Parses the text file and returns the lines that pass the filter
+fn filter_text_file<F: Fn(&MetaString) -> bool>(
+    path: &Path,
+    comment_syntaxes: &CommentSyntaxes,
+    extension: &str,
+    filter: &mut F,
+) -> Result<String> {
+    let source_file = File::open(path).map_err(|e| TmcError::OpenFile(path.to_path_buf(), e))?;
+    let parser = comment_syntaxes.parser(source_file, extension);
+
+    let mut filtered = String::new();
+    for meta_string in parser {
+        let meta_string = meta_string?;
+        if filter(&meta_string) {
+            filtered.push_str(meta_string.as_str());
+        }
+    }
+    Ok(filtered)
+}
+
+// The extension of the file if it is a text file processed with the meta syntax parser
+fn text_file_extension(entry: &DirEntry) -> Option<&str> {
+    if !entry.file_type().is_file() {
+        return None;
+    }
+    entry
+        .path()
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| !NON_TEXT_TYPES.is_match(e))
+}
+
+// The default comment syntaxes extended with the project's own from .tmcproject.yml
+fn project_comment_syntaxes(path: &Path) -> Result<CommentSyntaxes> {
+    let tmc_project_yml = TmcProjectYml::from(path)?;
+    let mut comment_syntaxes = CommentSyntaxes::default();
+    for (extension, syntaxes) in &tmc_project_yml.comment_syntaxes {
+        comment_syntaxes.insert(extension.as_str(), syntaxes);
+    }
+    Ok(comment_syntaxes)
+}
+
 // Processes all files in path, copying files in directories that are not skipped
 fn process_files<F: Fn(&MetaString) -> bool>(
     path: &Path,
@@ -173,13 +204,15 @@ fn process_files<F: Fn(&MetaString) -> bool>(
 ) -> Result<()> {
     info!("Project: {:?}", path);
 
+    let comment_syntaxes = project_comment_syntaxes(path)?;
+
     let walker = WalkDir::new(path).into_iter();
     // silently skips over errors, for example when there's a directory we don't have permissions for
     for entry in walker
         .filter_entry(|e| !is_hidden_dir(e) && !on_skip_list(e) && !contains_tmcignore(e))
         .filter_map(|e| e.ok())
     {
-        copy_file(&entry, path, dest_root, &mut filter)?;
+        copy_file(&entry, path, dest_root, &comment_syntaxes, &mut filter)?;
     }
     Ok(())
 }
@@ -190,15 +223,13 @@ fn process_files<F: Fn(&MetaString) -> bool>(
 /// files matching patterns defined in ```FILES_TO_SKIP_ALWAYS``` and directories and files named ```private```.
 ///
 /// Binary files are copied without extra processing, while text files are parsed to remove solution tags and stubs.
+/// Hidden blocks are kept.
 pub fn prepare_solutions<'a, I: IntoIterator<Item = &'a PathBuf>>(
     exercise_paths: I,
     dest_root: &Path,
 ) -> Result<()> {
     for path in exercise_paths {
-        process_files(path, dest_root, |meta| match meta {
-            MetaString::Stub(_) => false,
-            _ => true,
-        })?;
+        process_files(path, dest_root, in_solution_version)?;
     }
     Ok(())
 }
@@ -208,20 +239,160 @@ pub fn prepare_solutions<'a, I: IntoIterator<Item = &'a PathBuf>>(
 /// Skips hidden directories, directories that contain a ```.tmcignore``` file in their root, as well as
 /// files matching patterns defined in ```FILES_TO_SKIP_ALWAYS``` and directories and files named ```private```.
 ///
-/// Binary files are copied without extra processing, while text files are parsed to remove stub tags and solutions.
+/// Binary files are copied without extra processing, while text files are parsed to remove stub tags, solutions
+/// and hidden blocks.
 ///
 /// Additionally, copies any shared files with the corresponding language plugins.
 pub fn prepare_stub(exercise_path: &Path, dest_root: &Path) -> Result<()> {
-    process_files(&exercise_path, dest_root, |meta| match meta {
-        MetaString::Solution(_) => false,
-        _ => true,
-    })?;
+    process_files(&exercise_path, dest_root, in_stub_version)?;
     Ok(())
 }
 
+/// Restores the hidden blocks that `prepare_stub` removed from a stub, so that the stub can be used for grading.
+/// Each text file in the stub whose original in the exercise contains hidden blocks is replaced by the original
+/// processed like in `prepare_stub`, except that the hidden blocks are kept.
+pub fn restore_hidden_blocks(exercise_path: &Path, stub_path: &Path) -> Result<()> {
+    let comment_syntaxes = project_comment_syntaxes(exercise_path)?;
+
+    for entry in WalkDir::new(stub_path).into_iter().filter_map(|e| e.ok()) {
+        let extension = match text_file_extension(&entry) {
+            Some(extension) => extension,
+            None => continue,
+        };
+        let relative_path = entry
+            .path()
+            .strip_prefix(stub_path)
+            .unwrap_or_else(|_| entry.path());
+        let original_path = exercise_path.join(relative_path);
+        if !original_path.is_file() {
+            continue;
+        }
+
+        let original_file =
+            File::open(&original_path).map_err(|e| TmcError::OpenFile(original_path.clone(), e))?;
+        let original = comment_syntaxes
+            .parser(original_file, extension)
+            .collect::<Result<Vec<_>>>()?;
+        if !original.iter().any(|m| matches!(m, MetaString::Hidden(_))) {
+            continue;
+        }
+        debug!("restoring hidden blocks to {}", entry.path().display());
+        let restored: String = original
+            .iter()
+            .filter(|m| !matches!(m, MetaString::Solution(_)))
+            .map(MetaString::as_str)
+            .collect();
+        fs::write(entry.path(), restored)
+            .map_err(|e| TmcError::Write(entry.path().to_path_buf(), e))?;
+    }
+    Ok(())
+}
+
+// Whether the line is included in the stub
+fn in_stub_version(meta: &MetaString) -> bool {
+    !matches!(meta, MetaString::Solution(_) | MetaString::Hidden(_))
+}
+
+// Whether the line is included in the solution
+fn in_solution_version(meta: &MetaString) -> bool {
+    !matches!(meta, MetaString::Stub(_))
+}
+
+/// Checks the meta syntax of the text files that `prepare_stub` and `prepare_solutions` would process,
+/// returning any problems that would produce a broken stub or solution. The problems are sorted by file and line.
+pub fn lint_exercise(exercise_path: &Path) -> Result<Vec<MetaSyntaxLint>> {
+    let comment_syntaxes = project_comment_syntaxes(exercise_path)?;
+
+    let mut lints = vec![];
+    let walker = WalkDir::new(exercise_path).into_iter();
+    for entry in walker
+        .filter_entry(|e| !is_hidden_dir(e) && !on_skip_list(e) && !contains_tmcignore(e))
+        .filter_map(|e| e.ok())
+    {
+        let extension = match text_file_extension(&entry) {
+            Some(extension) => extension,
+            None => continue,
+        };
+        let relative_path = entry
+            .path()
+            .strip_prefix(exercise_path)
+            .unwrap_or_else(|_| entry.path());
+        let file = File::open(entry.path())
+            .map_err(|e| TmcError::OpenFile(entry.path().to_path_buf(), e))?;
+        lints.extend(comment_syntaxes.lint(relative_path, file, extension)?);
+    }
+    lints.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+    Ok(lints)
+}
+
+/// Previews what `prepare_stub` and `prepare_solutions` would do to the text files of the exercise.
+/// Each file is diffed against its stub and solution versions as unified diffs, where removed solution blocks
+/// and markers are shown as removed lines and inserted stubs as added lines. Only files that would change are included.
+pub fn preview_exercise(exercise_path: &Path) -> Result<Vec<FilePreview>> {
+    let comment_syntaxes = project_comment_syntaxes(exercise_path)?;
+
+    let mut previews = vec![];
+    let walker = WalkDir::new(exercise_path).into_iter();
+    for entry in walker
+        .filter_entry(|e| !is_hidden_dir(e) && !on_skip_list(e) && !contains_tmcignore(e))
+        .filter_map(|e| e.ok())
+    {
+        let extension = match text_file_extension(&entry) {
+            Some(extension) => extension,
+            None => continue,
+        };
+        let relative_path = entry
+            .path()
+            .strip_prefix(exercise_path)
+            .unwrap_or_else(|_| entry.path());
+
+        let original = fs::read_to_string(entry.path())
+            .map_err(|e| TmcError::FileRead(entry.path().to_path_buf(), e))?;
+        let stub = filter_text_file(
+            entry.path(),
+            &comment_syntaxes,
+            extension,
+            &mut in_stub_version,
+        )?;
+        let solution = filter_text_file(
+            entry.path(),
+            &comment_syntaxes,
+            extension,
+            &mut in_solution_version,
+        )?;
+
+        let stub_diff = unified_diff(relative_path, "stub", &original, &stub);
+        let solution_diff = unified_diff(relative_path, "solution", &original, &solution);
+        if stub_diff.is_some() || solution_diff.is_some() {
+            previews.push(FilePreview {
+                path: relative_path.to_path_buf(),
+                stub_diff,
+                solution_diff,
+            });
+        }
+    }
+    previews.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(previews)
+}
+
+// Diffs the original file against the processed one, returning None if they are equal
+fn unified_diff(path: &Path, version: &str, original: &str, processed: &str) -> Option<String> {
+    if original == processed {
+        return None;
+    }
+    let original_header = format!("a/{}", path.display());
+    let processed_header = format!("b/{} ({})", path.display(), version);
+    let diff = TextDiff::from_lines(original, processed)
+        .unified_diff()
+        .header(&original_header, &processed_header)
+        .to_string();
+    Some(diff)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::domain::meta_syntax::MetaSyntaxLintKind;
     use crate::domain::TmcProjectYml;
     use crate::policy::{EverythingIsStudentFilePolicy, NothingIsStudentFilePolicy};
     use std::collections::HashSet;
@@ -417,6 +588,34 @@ mod test {
         assert_eq!(s, expected, "expected:\n{:#}\nfound:\n{:#}", expected, s);
     }
 
+    #[test]
+    fn handles_hidden_blocks() {
+        init();
+
+        let temp = tempdir().unwrap();
+        let exercise = temp.path().join("exercise");
+        fs::create_dir_all(exercise.join("test")).unwrap();
+        fs::write(
+            exercise.join("test/test_main.py"),
+            "check()\n# BEGIN HIDDEN\nhidden_check()\n# END HIDDEN\n",
+        )
+        .unwrap();
+
+        let stub = temp.path().join("stub");
+        prepare_stub(&exercise, &stub).unwrap();
+        let stub_test = fs::read_to_string(stub.join("test/test_main.py")).unwrap();
+        assert_eq!(stub_test, "check()\n");
+
+        let solution = temp.path().join("solution");
+        prepare_solutions(std::iter::once(&exercise), &solution).unwrap();
+        let solution_test = fs::read_to_string(solution.join("test/test_main.py")).unwrap();
+        assert_eq!(solution_test, "check()\nhidden_check()\n");
+
+        restore_hidden_blocks(&exercise, &stub).unwrap();
+        let stub_test = fs::read_to_string(stub.join("test/test_main.py")).unwrap();
+        assert_eq!(stub_test, "check()\nhidden_check()\n");
+    }
+
     #[test]
     fn tmc_project_yml_parses() {
         let temp = tempdir().unwrap();
@@ -436,4 +635,145 @@ extra_student_files:
         assert!(conf.extra_student_files[0] == PathBuf::from("test/StudentTest.java"));
         assert!(conf.extra_student_files[1] == PathBuf::from("test/OtherTest.java"));
     }
+
+    #[test]
+    fn tmc_project_yml_rejects_escaping_paths() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(".tmcproject.yml");
+        fs::write(&path, "extra_student_files:\n  - ./test/../src\n").unwrap();
+        let conf = TmcProjectYml::from(temp.path()).unwrap();
+        assert_eq!(conf.extra_student_files, &[PathBuf::from("src")]);
+
+        fs::write(&path, "extra_student_files:\n  - ../../etc\n").unwrap();
+        assert!(matches!(
+            TmcProjectYml::from(temp.path()),
+            Err(TmcError::PathEscapesRoot(_))
+        ));
+        fs::write(&path, "force_update:\n  - /etc\n").unwrap();
+        assert!(matches!(
+            TmcProjectYml::from(temp.path()),
+            Err(TmcError::PathNotRelative(_))
+        ));
+    }
+
+    #[test]
+    fn prepares_stubs_with_configured_comment_syntaxes() {
+        init();
+
+        let temp = tempdir().unwrap();
+        let exercise = temp.path().join("exercise");
+        fs::create_dir(&exercise).unwrap();
+        fs::write(
+            exercise.join(".tmcproject.yml"),
+            r#"
+comment_syntaxes:
+  vb:
+    - start: "'"
+  txt:
+    - start: "(*"
+      end: "*)"
+"#,
+        )
+        .unwrap();
+        fs::write(
+            exercise.join("Hello.vb"),
+            "' BEGIN SOLUTION\nsolution\n' END SOLUTION\n' STUB: stub\n",
+        )
+        .unwrap();
+        fs::write(
+            exercise.join("notes.txt"),
+            "(* BEGIN SOLUTION *)\nsolution\n(* END SOLUTION *)\n(* STUB: stub *)\n",
+        )
+        .unwrap();
+        let stub = temp.path().join("stub");
+
+        prepare_stub(&exercise, &stub).unwrap();
+
+        let vb = fs::read_to_string(stub.join("Hello.vb")).unwrap();
+        assert_eq!(vb, "stub\n");
+        let txt = fs::read_to_string(stub.join("notes.txt")).unwrap();
+        assert_eq!(txt, "stub ");
+    }
+
+    #[test]
+    fn lints_exercise() {
+        init();
+
+        let temp = tempdir().unwrap();
+        let exercise = temp.path().join("exercise");
+        fs::create_dir_all(exercise.join("src")).unwrap();
+        fs::write(
+            exercise.join("src/Main.java"),
+            "class Main {\n    // BEGIN SOLUTION\n}\n",
+        )
+        .unwrap();
+        fs::write(exercise.join("src/main.py"), "# END SOLUTION\n").unwrap();
+        fs::write(exercise.join("src/data.bin"), "// BEGIN SOLUTION\n").unwrap();
+
+        let lints = lint_exercise(&exercise).unwrap();
+        let lints: Vec<_> = lints
+            .into_iter()
+            .map(|l| (l.file, l.line, l.kind))
+            .collect();
+        assert_eq!(
+            lints,
+            &[
+                (
+                    PathBuf::from("src/Main.java"),
+                    2,
+                    MetaSyntaxLintKind::UnterminatedSolution
+                ),
+                (
+                    PathBuf::from("src/main.py"),
+                    1,
+                    MetaSyntaxLintKind::UnmatchedSolutionEnd
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn previews_exercise() {
+        init();
+
+        let temp = tempdir().unwrap();
+        let exercise = temp.path().join("exercise");
+        fs::create_dir_all(exercise.join("src")).unwrap();
+        fs::write(
+            exercise.join("src/main.py"),
+            "def f():\n    # BEGIN SOLUTION\n    return 1\n    # END SOLUTION\n    # STUB: return 0\n",
+        )
+        .unwrap();
+        fs::write(exercise.join("src/unchanged.py"), "print(1)\n").unwrap();
+
+        let previews = preview_exercise(&exercise).unwrap();
+        assert_eq!(previews.len(), 1);
+        let preview = &previews[0];
+        assert_eq!(preview.path, PathBuf::from("src/main.py"));
+        assert_eq!(
+            preview.stub_diff.as_deref().unwrap(),
+            r#"--- a/src/main.py
++++ b/src/main.py (stub)
+@@ -1,5 +1,2 @@
+ def f():
+-    # BEGIN SOLUTION
+-    return 1
+-    # END SOLUTION
+-    # STUB: return 0
++    return 0
+"#
+        );
+        assert_eq!(
+            preview.solution_diff.as_deref().unwrap(),
+            r#"--- a/src/main.py
++++ b/src/main.py (solution)
+@@ -1,5 +1,2 @@
+ def f():
+-    # BEGIN SOLUTION
+     return 1
+-    # END SOLUTION
+-    # STUB: return 0
+"#
+        );
+    }
 }
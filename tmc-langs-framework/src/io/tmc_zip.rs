@@ -1,22 +1,262 @@
 //! Contains functions for zipping and unzipping projects.
 
+use crate::io::archive::{self, Archive, ArchiveEntry, EntryKind};
+use crate::io::path_util;
 use crate::policy::StudentFilePolicy;
 use crate::{Result, TmcError};
 use std::collections::HashSet;
-use std::fs::{self, File};
-use std::io::{Cursor, Read, Seek, Write};
+use std::fs::{self, File, Metadata};
+use std::io::{BufReader, Cursor, Read, Write};
 use std::path::Path;
 use std::path::PathBuf;
 use walkdir::{DirEntry, WalkDir};
 pub use zip::result::ZipError;
-use zip::{write::FileOptions, ZipArchive, ZipWriter};
+use zip::{write::FileOptions, CompressionMethod, DateTime, ZipWriter};
+
+/// The compression ratio is only checked for entries larger than this,
+/// as small files can have high ratios without being a problem.
+pub(crate) const COMPRESSION_RATIO_MIN_SIZE: u64 = 1024 * 1024;
+
+/// Limits on the contents of archives that are extracted, protecting against zip bombs and their tar equivalents.
+/// The sizes are counted from the data actually decompressed rather than the sizes declared in the archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnzipLimits {
+    /// The maximum number of entries in the archive.
+    pub max_entries: u64,
+    /// The maximum uncompressed size of a single file in bytes.
+    pub max_file_size: u64,
+    /// The maximum uncompressed size of all of the extracted files in bytes.
+    pub max_total_size: u64,
+    /// The maximum ratio of the uncompressed size of a file to its compressed size.
+    pub max_compression_ratio: u64,
+}
+
+impl Default for UnzipLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 50_000,
+            max_file_size: 100 * 1024 * 1024,
+            max_total_size: 500 * 1024 * 1024,
+            max_compression_ratio: 200,
+        }
+    }
+}
+
+/// Whether creating an archive from the same files always produces the same archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reproducibility {
+    /// The entries are sorted and their metadata is normalised.
+    Deterministic,
+    /// The entries are written in directory order with the default metadata of the format,
+    /// such as the current time as the timestamp.
+    NonDeterministic,
+}
+
+/// Enforces the limits while files are extracted from a single archive, see `Archive::limiter`.
+pub struct UnzipLimiter {
+    archive_path: PathBuf,
+    limits: UnzipLimits,
+    total_size: u64,
+}
+
+impl UnzipLimiter {
+    /// Starts tracking the data extracted from the archive at the given path.
+    pub fn new(archive_path: &Path, limits: UnzipLimits) -> Self {
+        Self {
+            archive_path: archive_path.to_path_buf(),
+            limits,
+            total_size: 0,
+        }
+    }
+
+    /// Streams the entry into a file at the target path, creating its parent directories.
+    /// If a limit is exceeded, the partially written file is removed and an error is returned.
+    /// Entries other than files, such as symlinks, are skipped, as they could point outside of the directory the archive is extracted to.
+    /// On Unix, the permissions stored in the entry are applied to the file, keeping it readable and writable by its owner.
+    pub fn extract_file(&mut self, entry: &mut ArchiveEntry, target: &Path) -> Result<()> {
+        if entry.kind() != EntryKind::File {
+            log::warn!(
+                "skipping {} in archive, only files are extracted",
+                entry.name()
+            );
+            return Ok(());
+        }
+        let entry_path = PathBuf::from(entry.name().as_ref());
+        // the declared size can't be trusted, but an entry that declares a size over the limit can be rejected early
+        if entry.size() > self.limits.max_file_size {
+            return Err(TmcError::ArchiveFileTooLarge(
+                entry_path,
+                self.limits.max_file_size,
+            ));
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| TmcError::CreateDir(parent.to_path_buf(), e))?;
+        }
+        let mut target_file =
+            File::create(target).map_err(|e| TmcError::CreateFile(target.to_path_buf(), e))?;
+        let result = self
+            .copy(entry, &entry_path, &mut target_file, target)
+            .and_then(|_| set_permissions(entry, target));
+        if result.is_err() {
+            drop(target_file);
+            if let Err(e) = fs::remove_file(target) {
+                log::warn!(
+                    "failed to remove partially extracted file {}: {}",
+                    target.display(),
+                    e
+                );
+            }
+        }
+        result
+    }
+
+    /// Extracts the entry like `extract_file`, but only replaces an existing file at the target path
+    /// if its contents differ from the entry's. The entry is streamed into a temporary file next to the target
+    /// for the comparison, as it can only be read once. Returns whether the target was written.
+    pub fn extract_changed_file(
+        &mut self,
+        entry: &mut ArchiveEntry,
+        target: &Path,
+    ) -> Result<bool> {
+        let parent = match target.parent() {
+            Some(parent) if target.exists() && entry.kind() == EntryKind::File => parent,
+            _ => {
+                self.extract_file(entry, target)?;
+                return Ok(true);
+            }
+        };
+        let temp = tempfile::NamedTempFile::new_in(parent)
+            .map_err(|e| TmcError::CreateFile(parent.to_path_buf(), e))?;
+        self.extract_file(entry, temp.path())?;
+        if files_match(temp.path(), target)? {
+            return Ok(false);
+        }
+        temp.persist(target).map_err(|e| {
+            TmcError::Rename(e.file.path().to_path_buf(), target.to_path_buf(), e.error)
+        })?;
+        Ok(true)
+    }
+
+    fn copy<W: Write>(
+        &mut self,
+        entry: &mut ArchiveEntry,
+        entry_path: &Path,
+        writer: &mut W,
+        target: &Path,
+    ) -> Result<()> {
+        let compressed_size = entry.compressed_size();
+        let mut file_size = 0;
+        let mut buf = [0; 8 * 1024];
+        loop {
+            let read = entry
+                .read(&mut buf)
+                .map_err(|e| TmcError::ZipRead(self.archive_path.clone(), e))?;
+            if read == 0 {
+                break;
+            }
+            file_size += read as u64;
+            self.total_size += read as u64;
+            if file_size > self.limits.max_file_size {
+                return Err(TmcError::ArchiveFileTooLarge(
+                    entry_path.to_path_buf(),
+                    self.limits.max_file_size,
+                ));
+            }
+            if self.total_size > self.limits.max_total_size {
+                return Err(TmcError::ArchiveTotalSizeTooLarge(
+                    self.archive_path.clone(),
+                    self.limits.max_total_size,
+                ));
+            }
+            // entries in tar archives aren't compressed separately, the archive's ratio is checked by `Archive::for_each_entry`
+            if let Some(compressed_size) = compressed_size {
+                if file_size > COMPRESSION_RATIO_MIN_SIZE
+                    && file_size / compressed_size.max(1) > self.limits.max_compression_ratio
+                {
+                    return Err(TmcError::ArchiveCompressionRatioTooHigh(
+                        entry_path.to_path_buf(),
+                        self.limits.max_compression_ratio,
+                    ));
+                }
+            }
+            writer
+                .write_all(&buf[..read])
+                .map_err(|e| TmcError::Write(target.to_path_buf(), e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn set_permissions(entry: &ArchiveEntry, target: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    match entry.unix_mode().map(|mode| mode & 0o777) {
+        // some archivers store no permissions at all
+        Some(mode) if mode != 0 => {
+            fs::set_permissions(target, fs::Permissions::from_mode(mode | 0o600))
+                .map_err(|e| TmcError::SetPermissions(target.to_path_buf(), e))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_permissions(_entry: &ArchiveEntry, _target: &Path) -> Result<()> {
+    Ok(())
+}
+
+// checks whether the two files have the same contents without reading either fully into memory
+fn files_match(a: &Path, b: &Path) -> Result<bool> {
+    let open = |path: &Path| -> Result<(BufReader<File>, u64)> {
+        let file = File::open(path).map_err(|e| TmcError::OpenFile(path.to_path_buf(), e))?;
+        let size = file
+            .metadata()
+            .map_err(|e| TmcError::FileRead(path.to_path_buf(), e))?
+            .len();
+        Ok((BufReader::new(file), size))
+    };
+    let (mut a_file, a_size) = open(a)?;
+    let (mut b_file, b_size) = open(b)?;
+    if a_size != b_size {
+        return Ok(false);
+    }
+    let mut a_buf = [0; 8 * 1024];
+    let mut b_buf = [0; 8 * 1024];
+    loop {
+        let read = a_file
+            .read(&mut a_buf)
+            .map_err(|e| TmcError::FileRead(a.to_path_buf(), e))?;
+        if read == 0 {
+            return Ok(true);
+        }
+        match b_file.read_exact(&mut b_buf[..read]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(TmcError::FileRead(b.to_path_buf(), e)),
+        }
+        if a_buf[..read] != b_buf[..read] {
+            return Ok(false);
+        }
+    }
+}
 
 /// Zips the given directory, only including student files according to the given policy.
-pub fn zip<P: StudentFilePolicy>(policy: P, root_directory: &Path) -> Result<Vec<u8>> {
+/// With `Reproducibility::Deterministic`, the entries are sorted and their metadata normalised with `file_options`
+/// so that the same files always produce the same archive.
+pub fn zip<P: StudentFilePolicy>(
+    policy: P,
+    root_directory: &Path,
+    reproducibility: Reproducibility,
+) -> Result<Vec<u8>> {
     let mut writer = ZipWriter::new(Cursor::new(vec![]));
     let tmc_project_yml = policy.get_tmc_project_yml()?;
 
-    for entry in WalkDir::new(root_directory)
+    let mut walker = WalkDir::new(root_directory);
+    if reproducibility == Reproducibility::Deterministic {
+        walker = walker.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+    }
+    for entry in walker
         .into_iter()
         .filter_entry(|e| !contains_tmcnosubmit(e))
         .filter_map(|e| e.ok())
@@ -27,9 +267,11 @@ pub fn zip<P: StudentFilePolicy>(policy: P, root_directory: &Path) -> Result<Vec
                 .parent()
                 .map(|p| entry.path().strip_prefix(p).unwrap())
                 .unwrap_or_else(|| entry.path());
+            let options = file_options(entry.path(), reproducibility)?;
+            let name = archive::zip_entry_name(path)?;
             if entry.path().is_dir() {
                 log::trace!("adding directory {}", path.display());
-                writer.add_directory_from_path(path, FileOptions::default())?;
+                writer.add_directory(name, options)?;
             } else {
                 let mut file = File::open(entry.path())
                     .map_err(|e| TmcError::FileOpen(entry.path().to_path_buf(), e))?;
@@ -37,7 +279,7 @@ pub fn zip<P: StudentFilePolicy>(policy: P, root_directory: &Path) -> Result<Vec
                 file.read_to_end(&mut bytes)
                     .map_err(|e| TmcError::FileRead(entry.path().to_path_buf(), e))?;
                 log::trace!("writing file {}", path.display());
-                writer.start_file_from_path(path, FileOptions::default())?;
+                writer.start_file(name, options)?;
                 writer
                     .write_all(&bytes)
                     .map_err(|e| TmcError::Write(path.to_path_buf(), e))?;
@@ -48,21 +290,54 @@ pub fn zip<P: StudentFilePolicy>(policy: P, root_directory: &Path) -> Result<Vec
     Ok(cursor.into_inner())
 }
 
+/// Returns the options for adding the file or directory at the path to a zip archive.
+/// With `Reproducibility::Deterministic`, the files are deflated, the timestamp is set to 1980-01-01,
+/// the earliest date zip archives support, and the permissions are normalised with `normalized_mode`.
+/// Otherwise the default options are used.
+pub fn file_options(path: &Path, reproducibility: Reproducibility) -> Result<FileOptions> {
+    if reproducibility == Reproducibility::NonDeterministic {
+        return Ok(FileOptions::default());
+    }
+    let metadata = fs::metadata(path).map_err(|e| TmcError::FileRead(path.to_path_buf(), e))?;
+    Ok(FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(normalized_mode(&metadata)))
+}
+
+/// Returns 0o755 for directories and files executable by their owner and 0o644 for other files,
+/// which matches the normalisation done by tar's deterministic header mode.
+pub fn normalized_mode(metadata: &Metadata) -> u32 {
+    #[cfg(unix)]
+    let executable = {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o100 == 0o100
+    };
+    #[cfg(not(unix))]
+    let executable = false;
+
+    if metadata.is_dir() || executable {
+        0o755
+    } else {
+        0o644
+    }
+}
+
 // todo: remove
 /// Finds a project directory in the given zip and unzips it according to the given student policy. Also cleans unnecessary non-student files.
 ///
 /// First a project directory is found within the directory. Only files within the project directory are unzipped.
 ///
-pub fn unzip<P>(policy: P, zip: &Path, target: &Path) -> Result<()>
+pub fn unzip<P>(policy: P, zip: &Path, target: &Path, limits: UnzipLimits) -> Result<()>
 where
     P: StudentFilePolicy,
 {
     log::debug!("Unzipping {} to {}", zip.display(), target.display());
 
-    let file = File::open(zip).map_err(|e| TmcError::OpenFile(zip.to_path_buf(), e))?;
-    let mut zip_archive = ZipArchive::new(file)?;
+    let mut archive = Archive::open(zip, limits)?;
+    let mut limiter = archive.limiter();
 
-    let project_dir = find_project_dir(&mut zip_archive)?;
+    let project_dir = find_project_dir(&mut archive)?;
     log::debug!("Project dir in zip: {}", project_dir.display());
 
     let tmc_project_yml = policy.get_tmc_project_yml()?;
@@ -71,72 +346,50 @@ where
     // used to clean non-student files not in the zip later
     let mut unzip_paths = HashSet::new();
 
-    for i in 0..zip_archive.len() {
-        let mut file = zip_archive.by_index(i)?;
-        let file_path = file.sanitized_name();
+    archive.for_each_entry(|file| {
+        let file_path = file.path()?;
         let relative = match file_path.strip_prefix(&project_dir) {
-            Ok(relative) => relative,
+            Ok(relative) => relative.to_path_buf(),
             _ => {
                 log::trace!("skip {}, not in project dir", file.name());
-                continue;
+                return Ok(());
             }
         };
-        let path_in_target = target.join(&relative);
+        let path_in_target = path_util::safe_join(target, &relative)?;
         log::trace!("processing {:?} -> {:?}", file_path, path_in_target);
 
         if file.is_dir() {
             log::trace!("creating {:?}", path_in_target);
             fs::create_dir_all(&path_in_target)
                 .map_err(|e| TmcError::CreateDir(path_in_target.clone(), e))?;
-            unzip_paths.insert(
-                path_in_target
-                    .canonicalize()
-                    .map_err(|e| TmcError::Canonicalize(path_in_target.clone(), e))?,
-            );
         } else {
-            let mut write = true;
-            let mut file_contents = vec![];
-            file.read_to_end(&mut file_contents)
-                .map_err(|e| TmcError::FileRead(file_path.clone(), e))?;
             // always overwrite .tmcproject.yml
-            if path_in_target.exists()
-                && !path_in_target
-                    .file_name()
-                    .map(|o| o == ".tmcproject.yml")
-                    .unwrap_or_default()
+            let is_tmcproject_yml = path_in_target
+                .file_name()
+                .map(|o| o == ".tmcproject.yml")
+                .unwrap_or_default();
+            // existing student files are kept
+            if !is_tmcproject_yml
+                && path_in_target.exists()
+                && policy.is_student_file(&path_in_target, &target, &tmc_project_yml)?
+                && !policy.is_updating_forced(&relative, &tmc_project_yml)?
             {
-                let mut target_file = File::open(&path_in_target)
-                    .map_err(|e| TmcError::OpenFile(path_in_target.clone(), e))?;
-                let mut target_file_contents = vec![];
-                target_file
-                    .read_to_end(&mut target_file_contents)
-                    .map_err(|e| TmcError::FileRead(path_in_target.clone(), e))?;
-                if file_contents == target_file_contents
-                    || (policy.is_student_file(&path_in_target, &target, &tmc_project_yml)?
-                        && !policy.is_updating_forced(&relative, &tmc_project_yml)?)
-                {
-                    write = false;
-                }
-            }
-            if write {
-                log::trace!("writing to {}", path_in_target.display());
-                if let Some(parent) = path_in_target.parent() {
-                    fs::create_dir_all(parent)
-                        .map_err(|e| TmcError::CreateDir(parent.to_path_buf(), e))?;
-                }
-                let mut overwrite_target = File::create(&path_in_target)
-                    .map_err(|e| TmcError::CreateFile(path_in_target.clone(), e))?;
-                overwrite_target
-                    .write_all(&file_contents)
-                    .map_err(|e| TmcError::Write(path_in_target.clone(), e))?;
+                log::trace!("keeping {}", path_in_target.display());
+            } else if limiter.extract_changed_file(file, &path_in_target)? {
+                log::trace!("wrote {}", path_in_target.display());
             }
         }
+        if !path_in_target.exists() {
+            // entries such as symlinks are skipped by the limiter
+            return Ok(());
+        }
         unzip_paths.insert(
             path_in_target
                 .canonicalize()
                 .map_err(|e| TmcError::Canonicalize(path_in_target.clone(), e))?,
         );
-    }
+        Ok(())
+    })?;
 
     // delete non-student files that were not in zip
     log::debug!("deleting non-student files not in zip");
@@ -169,11 +422,8 @@ where
     Ok(())
 }
 
-fn find_project_dir<R: Read + Seek>(zip_archive: &mut ZipArchive<R>) -> Result<PathBuf> {
-    for i in 0..zip_archive.len() {
-        let file = zip_archive.by_index(i)?;
-        let file_path = file.sanitized_name();
-
+fn find_project_dir(archive: &mut Archive) -> Result<PathBuf> {
+    for (file_path, kind) in archive.list_entries()? {
         // directories may not have entries in the zip, e.g. it may only have
         // exercise/src/main... without an entry for src, so we need to check
         // the path components to find src
@@ -196,8 +446,9 @@ fn find_project_dir<R: Read + Seek>(zip_archive: &mut ZipArchive<R>) -> Result<P
         }
 
         let file_name = file_path.file_name().unwrap_or_default();
-        if file.is_dir() && (file_name == "nbproject" || file_name == "src" || file_name == "test")
-            || file.is_file()
+        if kind == EntryKind::Directory
+            && (file_name == "nbproject" || file_name == "src" || file_name == "test")
+            || kind == EntryKind::File
                 && (file_name == "pom.xml" || file_name == ".idea" || file_name == "Makefile")
         {
             let parent = file_path.parent().unwrap_or_else(|| Path::new(""));
@@ -224,6 +475,155 @@ fn contains_tmcnosubmit(entry: &DirEntry) -> bool {
 
 #[cfg(test)]
 mod test {
+    use super::*;
+    use crate::policy::EverythingIsStudentFilePolicy;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        for (name, contents) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    fn extract_all(zip_path: &Path, target: &Path, limits: UnzipLimits) -> Result<()> {
+        let mut archive = Archive::open(zip_path, limits)?;
+        let mut limiter = archive.limiter();
+        archive.for_each_entry(|file| {
+            let path = target.join(file.path()?);
+            limiter.extract_file(file, &path)
+        })
+    }
+
+    #[test]
+    fn enforces_unzip_limits() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let zip_path = temp.path().join("archive.zip");
+        let target = temp.path().join("target");
+        write_zip(&zip_path, &[("a", b"12345"), ("b", b"67890")]);
+
+        extract_all(&zip_path, &target, UnzipLimits::default()).unwrap();
+        assert_eq!(fs::read(target.join("b")).unwrap(), b"67890");
+
+        let limits = UnzipLimits {
+            max_entries: 1,
+            ..UnzipLimits::default()
+        };
+        let res = extract_all(&zip_path, &target, limits);
+        assert!(matches!(res, Err(TmcError::ArchiveTooManyEntries(_, 1))));
+
+        let limits = UnzipLimits {
+            max_file_size: 4,
+            ..UnzipLimits::default()
+        };
+        let res = extract_all(&zip_path, &target, limits);
+        assert!(matches!(res, Err(TmcError::ArchiveFileTooLarge(_, 4))));
+
+        let limits = UnzipLimits {
+            max_total_size: 8,
+            ..UnzipLimits::default()
+        };
+        fs::remove_dir_all(&target).unwrap();
+        let res = extract_all(&zip_path, &target, limits);
+        assert!(matches!(res, Err(TmcError::ArchiveTotalSizeTooLarge(_, 8))));
+        // the partially written file is removed
+        assert!(target.join("a").exists());
+        assert!(!target.join("b").exists());
+
+        let zeros = vec![0; 2 * COMPRESSION_RATIO_MIN_SIZE as usize];
+        write_zip(&zip_path, &[("zeros", &zeros)]);
+        let res = extract_all(&zip_path, &target, UnzipLimits::default());
+        assert!(matches!(
+            res,
+            Err(TmcError::ArchiveCompressionRatioTooHigh(_, 200))
+        ));
+    }
+
+    #[test]
+    fn zips_deterministically() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let mut zips = vec![];
+        // the files are created in a different order in each directory
+        for files in &[["a", "b/c"], ["b/c", "a"]] {
+            let root = tempfile::tempdir_in(temp.path()).unwrap();
+            let exercise = root.path().join("exercise");
+            for file in files {
+                let path = exercise.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, *file).unwrap();
+            }
+            let policy = EverythingIsStudentFilePolicy::new(exercise.clone());
+            zips.push(zip(policy, &exercise, Reproducibility::Deterministic).unwrap());
+        }
+        assert!(zips[0] == zips[1]);
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(&zips[0])).unwrap();
+        let names: Vec<_> = (0..archive.len())
+            .map(|i| archive.by_index(i).unwrap().name().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            &["exercise/", "exercise/a", "exercise/b/", "exercise/b/c"]
+        );
+        let file = archive.by_name("exercise/a").unwrap();
+        assert_eq!(file.unix_mode(), Some(0o100_644));
+        assert_eq!(file.last_modified().year(), 1980);
+    }
+
+    #[test]
+    fn extracts_only_changed_files() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let zip_path = temp.path().join("archive.zip");
+        write_zip(&zip_path, &[("a", b"12345")]);
+        let mut archive = Archive::open(&zip_path, UnzipLimits::default()).unwrap();
+        let mut limiter = archive.limiter();
+
+        let file_path = temp.path().join("file");
+        let mut extract = |contents: &str| {
+            fs::write(&file_path, contents).unwrap();
+            let mut written = None;
+            archive
+                .for_each_entry(|file| {
+                    written = Some(limiter.extract_changed_file(file, &file_path)?);
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(fs::read(&file_path).unwrap(), b"12345");
+            written.unwrap()
+        };
+        assert!(!extract("12345"));
+        assert!(extract("12346"));
+        assert!(extract("123456"));
+    }
+
+    #[test]
+    fn finds_project_dir_with_entry_paths() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let zip_path = temp.path().join("archive.zip");
+        write_zip(&zip_path, &[("./exercise/src/main.py", b"")]);
+        let mut archive = Archive::open(&zip_path, UnzipLimits::default()).unwrap();
+        let project_dir = find_project_dir(&mut archive).unwrap();
+        assert_eq!(project_dir, Path::new("exercise"));
+
+        write_zip(&zip_path, &[("../exercise/src/main.py", b"")]);
+        let mut archive = Archive::open(&zip_path, UnzipLimits::default()).unwrap();
+        let res = find_project_dir(&mut archive);
+        assert!(matches!(res, Err(TmcError::PathEscapesRoot(_))));
+    }
+
     /*
     use super::*;
     use crate::policy::EverythingIsStudentFilePolicy;
@@ -264,7 +664,7 @@ mod test {
             &path,
         )
         .unwrap();
-        let mut archive = ZipArchive::new(Cursor::new(zipped)).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(zipped)).unwrap();
         for i in 0..archive.len() {
             log::debug!("{:?}", archive.by_index(i).unwrap().name());
         }
@@ -282,6 +682,7 @@ mod test {
             EverythingIsStudentFilePolicy::new(PathBuf::new()),
             Path::new("nonexistent"),
             Path::new(""),
+            UnzipLimits::default(),
         )
         .is_err())
     }
@@ -295,6 +696,7 @@ mod test {
             EverythingIsStudentFilePolicy::new(temp.path().to_path_buf()),
             Path::new("tests/data/zip/module-trivial.zip"),
             temp.path(),
+            UnzipLimits::default(),
         )
         .unwrap();
 
@@ -312,6 +714,7 @@ mod test {
             EverythingIsStudentFilePolicy::new(temp.path().to_path_buf()),
             Path::new("tests/data/zip/course-module-trivial.zip"),
             temp.path(),
+            UnzipLimits::default(),
         )
         .unwrap();
 
@@ -329,6 +732,7 @@ mod test {
             EverythingIsStudentFilePolicy::new(temp.path().to_path_buf()),
             Path::new("tests/data/zip/no-src-entry.zip"),
             temp.path(),
+            UnzipLimits::default(),
         )
         .unwrap();
         assert!(temp.path().join("src").exists());
@@ -6,6 +6,7 @@ pub mod error;
 pub mod io;
 pub mod plugin;
 pub mod policy;
+pub mod report;
 
 pub use error::TmcError;
 pub use plugin::LanguagePlugin;
@@ -2,23 +2,26 @@
 
 pub use isolang::Language;
 
+use super::command::ResourceLimits;
 use super::domain::{
-    ExerciseDesc, ExercisePackagingConfiguration, RunResult, RunStatus, TestResult, TmcProjectYml,
-    ValidationResult,
+    ExerciseDesc, ExercisePackagingConfiguration, RunResult, RunStatus, TestFilter, TestResult,
+    TmcProjectYml, ValidationResult,
+};
+use super::io::{
+    archive::Archive,
+    path_util, submission_processing,
+    tmc_zip::{self, Reproducibility, UnzipLimits},
 };
-use super::io::{submission_processing, tmc_zip};
 use super::policy::StudentFilePolicy;
 use super::Result;
 use crate::TmcError;
 
 use log::debug;
 use std::collections::{HashMap, HashSet};
-use std::fs::{self, File};
-use std::io::{Read, Seek, Write};
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use walkdir::WalkDir;
-use zip::ZipArchive;
 
 /// The trait that each language plug-in must implement.
 ///
@@ -68,25 +71,55 @@ pub trait LanguagePlugin {
     /// this language.
     fn scan_exercise(&self, path: &Path, exercise_name: String) -> Result<ExerciseDesc>;
 
-    /// Runs the tests for the exercise.
+    /// Runs the tests for the exercise with the timeout and resource limits configured in its .tmcproject.yml.
     fn run_tests(&self, path: &Path) -> Result<RunResult> {
-        let timeout = Self::get_student_file_policy(path)
+        self.run_tests_filtered(path, &TestFilter::default())
+    }
+
+    /// Runs the tests selected by the filter with the timeout and resource limits configured in its .tmcproject.yml.
+    fn run_tests_filtered(&self, path: &Path, filter: &TestFilter) -> Result<RunResult> {
+        self.run_tests_with_limits(path, ResourceLimits::default(), filter)
+    }
+
+    /// Runs the tests selected by the filter with the timeout and resource limits configured in its .tmcproject.yml.
+    /// The given resource limits, such as the ones in the exercise's metadata on the server,
+    /// are used for the limits that are not set in .tmcproject.yml.
+    fn run_tests_with_limits(
+        &self,
+        path: &Path,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
+    ) -> Result<RunResult> {
+        let tmc_project_yml = Self::get_student_file_policy(path)
             .get_tmc_project_yml()
-            .ok()
+            .ok();
+        let timeout = tmc_project_yml
+            .as_ref()
             .and_then(|t| t.tests_timeout_ms.map(Duration::from_millis));
-        let result = self.run_tests_with_timeout(path, timeout)?;
+        let resource_limits = tmc_project_yml
+            .and_then(|t| t.resource_limits)
+            .unwrap_or_default()
+            .or(resource_limits);
+        let result = self.run_tests_with_timeout(path, timeout, resource_limits, filter)?;
 
         // override success on no test cases
         if result.status == RunStatus::Passed && result.test_results.is_empty() {
+            let message = if filter.is_empty() {
+                "No tests found. Did you terminate your program with an exit() command?\nYou can also try submitting the exercise to the server."
+            } else {
+                "No tests matched the test filter."
+            };
             Ok(RunResult {
                 status: RunStatus::TestsFailed,
                 test_results: vec![TestResult {
                     name: "Tests found test".to_string(),
                     successful: false,
                     points: vec![],
-                    message: "No tests found. Did you terminate your program with an exit() command?\nYou can also try submitting the exercise to the server."
-                        .to_string(),
+                    message: message.to_string(),
                     exception: vec![],
+                    duration_ms: None,
+                    stdout: None,
+                    stderr: None,
                 }],
                 logs: HashMap::new(),
             })
@@ -96,7 +129,15 @@ pub trait LanguagePlugin {
     }
 
     /// Runs the tests for the exercise with the given timeout.
-    fn run_tests_with_timeout(&self, path: &Path, timeout: Option<Duration>) -> Result<RunResult>;
+    /// The resource limits should be applied to the processes running the tests.
+    /// Only the tests selected by the filter should be ran, using the test runner's own filtering where possible.
+    fn run_tests_with_timeout(
+        &self,
+        path: &Path,
+        timeout: Option<Duration>,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
+    ) -> Result<RunResult>;
 
     /// Prepares a submission for processing in the sandbox.
     ///
@@ -147,9 +188,10 @@ pub trait LanguagePlugin {
     }
 
     /// Compress a given project so that it can be sent to the TestMyCode server.
-    fn compress_project(&self, path: &Path) -> Result<Vec<u8>> {
+    /// With `Reproducibility::Deterministic`, the same files always produce the same archive. See `tmc_zip::zip`.
+    fn compress_project(&self, path: &Path, reproducibility: Reproducibility) -> Result<Vec<u8>> {
         let policy = Self::get_student_file_policy(path);
-        Ok(tmc_zip::zip(policy, path)?)
+        Ok(tmc_zip::zip(policy, path, reproducibility)?)
     }
 
     fn get_student_file_policy(project_path: &Path) -> Self::StudentFilePolicy;
@@ -158,11 +200,14 @@ pub trait LanguagePlugin {
     ///
     /// This will overwrite any existing files as long as they are not specified as student files
     /// by the language dependent student file policy.
+    /// The format of the archive is detected with `archive::ArchiveFormat::detect`,
+    /// and the archive is checked against the given limits.
     fn extract_project(
         &self,
         compressed_project: &Path,
         target_location: &Path,
         clean: bool,
+        limits: UnzipLimits,
     ) -> Result<()> {
         let policy = Self::get_student_file_policy(target_location);
 
@@ -172,12 +217,11 @@ pub trait LanguagePlugin {
             target_location.display()
         );
 
-        let file = File::open(compressed_project)
-            .map_err(|e| TmcError::OpenFile(compressed_project.to_path_buf(), e))?;
-        let mut zip_archive = ZipArchive::new(file)?;
+        let mut archive = Archive::open(compressed_project, limits)?;
+        let mut limiter = archive.limiter();
 
         // find the exercise root directory inside the archive
-        let project_dir = Self::find_project_dir_in_zip(&mut zip_archive)?;
+        let project_dir = Self::find_project_dir_in_archive(&mut archive)?;
         log::debug!("Project dir in zip: {}", project_dir.display());
 
         let tmc_project_yml = policy.get_tmc_project_yml()?;
@@ -185,17 +229,16 @@ pub trait LanguagePlugin {
         // used to clean non-student files not in the zip later
         let mut unzip_paths = HashSet::new();
 
-        for i in 0..zip_archive.len() {
-            let mut file = zip_archive.by_index(i)?;
-            let file_path = file.sanitized_name();
+        archive.for_each_entry(|file| {
+            let file_path = file.path()?;
             let relative = match file_path.strip_prefix(&project_dir) {
                 Ok(relative) => relative,
                 _ => {
                     log::trace!("skip {}, not in project dir", file.name());
-                    continue;
+                    return Ok(());
                 }
             };
-            let path_in_target = target_location.join(&relative);
+            let path_in_target = path_util::safe_join(target_location, relative)?;
             log::trace!("processing {:?} -> {:?}", file_path, path_in_target);
             unzip_paths.insert(path_in_target.clone());
 
@@ -204,47 +247,28 @@ pub trait LanguagePlugin {
                 fs::create_dir_all(&path_in_target)
                     .map_err(|e| TmcError::CreateDir(path_in_target.clone(), e))?;
             } else {
-                let mut write = true;
-                let mut file_contents = vec![];
-                file.read_to_end(&mut file_contents)
-                    .map_err(|e| TmcError::FileRead(file_path.clone(), e))?;
                 // always overwrite .tmcproject.yml
-                if path_in_target.exists()
-                    && !path_in_target
-                        .file_name()
-                        .map(|o| o == ".tmcproject.yml")
-                        .unwrap_or_default()
+                let is_tmcproject_yml = path_in_target
+                    .file_name()
+                    .map(|o| o == ".tmcproject.yml")
+                    .unwrap_or_default();
+                // existing student files are kept, and unchanged files are not written
+                if !is_tmcproject_yml
+                    && path_in_target.exists()
+                    && policy.is_student_file(
+                        &path_in_target,
+                        &target_location,
+                        &tmc_project_yml,
+                    )?
+                    && !policy.is_updating_forced(&relative, &tmc_project_yml)?
                 {
-                    let mut target_file = File::open(&path_in_target)
-                        .map_err(|e| TmcError::OpenFile(path_in_target.clone(), e))?;
-                    let mut target_file_contents = vec![];
-                    target_file
-                        .read_to_end(&mut target_file_contents)
-                        .map_err(|e| TmcError::FileRead(path_in_target.clone(), e))?;
-                    if file_contents == target_file_contents
-                        || (policy.is_student_file(
-                            &path_in_target,
-                            &target_location,
-                            &tmc_project_yml,
-                        )? && !policy.is_updating_forced(&relative, &tmc_project_yml)?)
-                    {
-                        write = false;
-                    }
-                }
-                if write {
-                    log::trace!("writing to {}", path_in_target.display());
-                    if let Some(parent) = path_in_target.parent() {
-                        fs::create_dir_all(parent)
-                            .map_err(|e| TmcError::CreateDir(parent.to_path_buf(), e))?;
-                    }
-                    let mut overwrite_target = File::create(&path_in_target)
-                        .map_err(|e| TmcError::CreateFile(path_in_target.clone(), e))?;
-                    overwrite_target
-                        .write_all(&file_contents)
-                        .map_err(|e| TmcError::Write(path_in_target.clone(), e))?;
+                    log::trace!("keeping {}", path_in_target.display());
+                } else if limiter.extract_changed_file(file, &path_in_target)? {
+                    log::trace!("wrote {}", path_in_target.display());
                 }
             }
-        }
+            Ok(())
+        })?;
 
         if clean {
             // delete non-student files that were not in zip
@@ -291,6 +315,7 @@ pub trait LanguagePlugin {
         &self,
         compressed_project: &Path,
         target_location: &Path,
+        limits: UnzipLimits,
     ) -> Result<()> {
         let policy = Self::get_student_file_policy(target_location);
 
@@ -300,74 +325,48 @@ pub trait LanguagePlugin {
             target_location.display()
         );
 
-        let file = File::open(compressed_project)
-            .map_err(|e| TmcError::OpenFile(compressed_project.to_path_buf(), e))?;
-        let mut zip_archive = ZipArchive::new(file)?;
+        let mut archive = Archive::open(compressed_project, limits)?;
+        let mut limiter = archive.limiter();
 
         // find the exercise root directory inside the archive
-        let project_dir = Self::find_project_dir_in_zip(&mut zip_archive)?;
+        let project_dir = Self::find_project_dir_in_archive(&mut archive)?;
         log::debug!("Project dir in zip: {}", project_dir.display());
 
         let tmc_project_yml = policy.get_tmc_project_yml()?;
 
-        // used to clean non-student files not in the zip later
-        let mut unzip_paths = HashSet::new();
-
-        for i in 0..zip_archive.len() {
-            let mut file = zip_archive.by_index(i)?;
-            let file_path = file.sanitized_name();
+        archive.for_each_entry(|file| {
+            let file_path = file.path()?;
             let relative = match file_path.strip_prefix(&project_dir) {
                 Ok(relative) => relative,
                 _ => {
                     log::trace!("skip {}, not in project dir", file.name());
-                    continue;
+                    return Ok(());
                 }
             };
-            let path_in_target = target_location.join(&relative);
+            let path_in_target = path_util::safe_join(target_location, relative)?;
             log::trace!("processing {:?} -> {:?}", file_path, path_in_target);
-            unzip_paths.insert(path_in_target.clone());
 
             if file.is_dir() {
                 log::trace!("creating {:?}", path_in_target);
                 fs::create_dir_all(&path_in_target)
                     .map_err(|e| TmcError::CreateDir(path_in_target.clone(), e))?;
-            } else {
-                let mut write = true;
-                // always overwrite .tmcproject.yml
-                if !policy.is_student_file(&path_in_target, &target_location, &tmc_project_yml)? {
-                    write = false;
-                }
-                if write {
-                    let mut file_contents = vec![];
-                    file.read_to_end(&mut file_contents)
-                        .map_err(|e| TmcError::FileRead(file_path.clone(), e))?;
-                    log::trace!("writing to {}", path_in_target.display());
-                    if let Some(parent) = path_in_target.parent() {
-                        fs::create_dir_all(parent)
-                            .map_err(|e| TmcError::CreateDir(parent.to_path_buf(), e))?;
-                    }
-                    let mut overwrite_target = File::create(&path_in_target)
-                        .map_err(|e| TmcError::CreateFile(path_in_target.clone(), e))?;
-                    overwrite_target
-                        .write_all(&file_contents)
-                        .map_err(|e| TmcError::Write(path_in_target.clone(), e))?;
-                }
+            } else if policy.is_student_file(&path_in_target, &target_location, &tmc_project_yml)?
+                && limiter.extract_changed_file(file, &path_in_target)?
+            {
+                log::trace!("wrote {}", path_in_target.display());
             }
-        }
-
-        Ok(())
+            Ok(())
+        })
     }
 
-    /// Searches the zip for a valid project directory.
-    /// Note that the returned path may not actually have an entry in the zip.
+    /// Searches the archive for a valid project directory.
+    /// Note that the returned path may not actually have an entry in the archive.
     /// The default implementation tries to find a directory that contains a "src" directory,
     /// which may be sufficient for some languages.
-    fn find_project_dir_in_zip<R: Read + Seek>(zip_archive: &mut ZipArchive<R>) -> Result<PathBuf> {
-        for i in 0..zip_archive.len() {
-            // zips don't necessarily contain entries for intermediate directories,
-            // so we need to check every path for src
-            let file = zip_archive.by_index(i)?;
-            let file_path = file.sanitized_name();
+    fn find_project_dir_in_archive(archive: &mut Archive) -> Result<PathBuf> {
+        // archives don't necessarily contain entries for intermediate directories,
+        // so we need to check every path for src
+        for (file_path, _kind) in archive.list_entries()? {
             // todo: do in one pass somehow
             if file_path.components().any(|c| c.as_os_str() == "src") {
                 let path: PathBuf = file_path
@@ -431,14 +430,21 @@ pub trait LanguagePlugin {
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::cell::Cell;
 
-    struct MockPlugin {}
+    #[derive(Default)]
+    struct MockPlugin {
+        // the resource limits of the latest test run
+        resource_limits: Cell<Option<ResourceLimits>>,
+    }
 
-    struct MockPolicy {}
+    struct MockPolicy {
+        project_path: PathBuf,
+    }
 
     impl StudentFilePolicy for MockPolicy {
         fn get_config_file_parent_path(&self) -> &Path {
-            Path::new("")
+            &self.project_path
         }
         fn is_student_source_file(&self, _path: &Path) -> bool {
             unimplemented!()
@@ -449,13 +455,13 @@ mod test {
         const PLUGIN_NAME: &'static str = "mock_plugin";
         type StudentFilePolicy = MockPolicy;
 
-        fn get_student_file_policy(_project_path: &Path) -> Self::StudentFilePolicy {
-            Self::StudentFilePolicy {}
+        fn get_student_file_policy(project_path: &Path) -> Self::StudentFilePolicy {
+            Self::StudentFilePolicy {
+                project_path: project_path.to_path_buf(),
+            }
         }
 
-        fn find_project_dir_in_zip<R: Read + Seek>(
-            _zip_archive: &mut ZipArchive<R>,
-        ) -> Result<PathBuf> {
+        fn find_project_dir_in_archive(_archive: &mut Archive) -> Result<PathBuf> {
             todo!()
         }
 
@@ -467,7 +473,10 @@ mod test {
             &self,
             _path: &Path,
             _timeout: Option<Duration>,
+            resource_limits: ResourceLimits,
+            _filter: &TestFilter,
         ) -> Result<RunResult> {
+            self.resource_limits.set(Some(resource_limits));
             Ok(RunResult {
                 status: RunStatus::Passed,
                 test_results: vec![],
@@ -486,7 +495,7 @@ mod test {
 
     #[test]
     fn finds_exercises() {
-        let plugin = MockPlugin {};
+        let plugin = MockPlugin::default();
         let exercises = plugin.find_exercises(&PathBuf::from("tests/data"));
         assert!(
             exercises.contains(&PathBuf::from("tests/data/dir")),
@@ -515,7 +524,7 @@ mod test {
         use std::fs::File;
         use std::io::Write;
 
-        let plugin = MockPlugin {};
+        let plugin = MockPlugin::default();
         let temp = tempfile::tempdir().unwrap();
         let mut path = temp.path().to_owned();
         path.push(".tmcproject.yml");
@@ -553,9 +562,60 @@ extra_exercise_files:
 
     #[test]
     fn empty_run_result_is_err() {
-        let plugin = MockPlugin {};
+        let plugin = MockPlugin::default();
         let res = plugin.run_tests(Path::new("")).unwrap();
         assert_eq!(res.status, RunStatus::TestsFailed);
         assert_eq!(res.test_results[0].name, "Tests found test")
     }
+
+    #[test]
+    fn passes_resource_limits_to_test_runs() {
+        let plugin = MockPlugin::default();
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(
+            temp.path().join(".tmcproject.yml"),
+            r#"
+resource_limits:
+  address_space_bytes: 1000
+  max_processes: 10
+"#,
+        )
+        .unwrap();
+
+        plugin.run_tests(temp.path()).unwrap();
+        assert_eq!(
+            plugin.resource_limits.take(),
+            Some(ResourceLimits {
+                address_space_bytes: Some(1000),
+                max_processes: Some(10),
+                ..ResourceLimits::default()
+            })
+        );
+
+        // the limits in .tmcproject.yml take precedence over the given ones
+        let given = ResourceLimits {
+            address_space_bytes: Some(2000),
+            cpu_time_secs: Some(5),
+            ..ResourceLimits::default()
+        };
+        plugin
+            .run_tests_with_limits(temp.path(), given, &TestFilter::default())
+            .unwrap();
+        assert_eq!(
+            plugin.resource_limits.take(),
+            Some(ResourceLimits {
+                address_space_bytes: Some(1000),
+                cpu_time_secs: Some(5),
+                max_processes: Some(10),
+                ..ResourceLimits::default()
+            })
+        );
+
+        // without a .tmcproject.yml, the given limits are used as they are
+        let temp = tempfile::tempdir().unwrap();
+        plugin
+            .run_tests_with_limits(temp.path(), given, &TestFilter::default())
+            .unwrap();
+        assert_eq!(plugin.resource_limits.take(), Some(given));
+    }
 }
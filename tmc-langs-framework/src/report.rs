@@ -0,0 +1,390 @@
+//! Contains functions for converting test run results into report formats understood by other tools.
+
+use crate::domain::{RunResult, RunStatus, TestResult};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Converts the run result into a JUnit XML report with a single test suite with the given name.
+///
+/// Failed tests are reported as failures. If the run did not finish normally, e.g. due to a compilation failure,
+/// the status is additionally reported as an error. The points of each test are reported as a property
+/// of the test case, and the stdout and stderr logs of the run as the output of the test suite.
+pub fn to_junit_xml(run_result: &RunResult, suite_name: &str) -> String {
+    let failures = run_result
+        .test_results
+        .iter()
+        .filter(|t| !t.successful)
+        .count();
+    let run_error = status_error(&run_result.status);
+    let errors = if run_error.is_some() { 1 } else { 0 };
+    let tests = run_result.test_results.len() + errors;
+    let time = if run_result
+        .test_results
+        .iter()
+        .any(|t| t.duration_ms.is_some())
+    {
+        let total_ms: u64 = run_result
+            .test_results
+            .iter()
+            .filter_map(|t| t.duration_ms)
+            .sum();
+        format!(" time=\"{}\"", format_secs(total_ms))
+    } else {
+        String::new()
+    };
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    // writing to a String cannot fail
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"{name}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\"{time}>",
+        name = escape_xml_attr(suite_name),
+        tests = tests,
+        failures = failures,
+        errors = errors,
+        time = time,
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{name}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\"{time}>",
+        name = escape_xml_attr(suite_name),
+        tests = tests,
+        failures = failures,
+        errors = errors,
+        time = time,
+    );
+    xml.push_str("    <properties>\n");
+    let _ = writeln!(
+        xml,
+        "      <property name=\"status\" value=\"{}\"/>",
+        status_name(&run_result.status)
+    );
+    for (key, value) in sorted_logs(run_result) {
+        if key != "stdout" && key != "stderr" {
+            let _ = writeln!(
+                xml,
+                "      <property name=\"log.{}\" value=\"{}\"/>",
+                escape_xml_attr(key),
+                escape_xml_attr(value)
+            );
+        }
+    }
+    xml.push_str("    </properties>\n");
+
+    for test_result in &run_result.test_results {
+        write_junit_test_case(&mut xml, test_result, suite_name);
+    }
+    if let Some(message) = run_error {
+        let _ = writeln!(
+            xml,
+            "    <testcase name=\"{status}\" classname=\"{class}\">",
+            status = status_name(&run_result.status),
+            class = escape_xml_attr(suite_name),
+        );
+        let _ = writeln!(
+            xml,
+            "      <error message=\"{}\" type=\"{}\"/>",
+            message,
+            status_name(&run_result.status)
+        );
+        xml.push_str("    </testcase>\n");
+    }
+
+    if let Some(stdout) = run_result.logs.get("stdout") {
+        let _ = writeln!(xml, "    <system-out>{}</system-out>", escape_xml(stdout));
+    }
+    if let Some(stderr) = run_result.logs.get("stderr") {
+        let _ = writeln!(xml, "    <system-err>{}</system-err>", escape_xml(stderr));
+    }
+    xml.push_str("  </testsuite>\n");
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn write_junit_test_case(xml: &mut String, test_result: &TestResult, suite_name: &str) {
+    let time = test_result
+        .duration_ms
+        .map(|ms| format!(" time=\"{}\"", format_secs(ms)))
+        .unwrap_or_default();
+    let _ = writeln!(
+        xml,
+        "    <testcase name=\"{name}\" classname=\"{class}\"{time}>",
+        name = escape_xml_attr(&test_result.name),
+        class = escape_xml_attr(suite_name),
+        time = time,
+    );
+    if !test_result.points.is_empty() {
+        xml.push_str("      <properties>\n");
+        let _ = writeln!(
+            xml,
+            "        <property name=\"points\" value=\"{}\"/>",
+            escape_xml_attr(&test_result.points.join(" "))
+        );
+        xml.push_str("      </properties>\n");
+    }
+    if !test_result.successful {
+        let _ = writeln!(
+            xml,
+            "      <failure message=\"{}\">{}</failure>",
+            escape_xml_attr(&test_result.message),
+            escape_xml(&test_result.exception.join("\n"))
+        );
+    }
+    if let Some(stdout) = &test_result.stdout {
+        let _ = writeln!(xml, "      <system-out>{}</system-out>", escape_xml(stdout));
+    }
+    if let Some(stderr) = &test_result.stderr {
+        let _ = writeln!(xml, "      <system-err>{}</system-err>", escape_xml(stderr));
+    }
+    xml.push_str("    </testcase>\n");
+}
+
+/// Converts the run result into a TAP version 13 report.
+///
+/// Each test is reported as a test point with its points, message, exception and captured output
+/// in a YAML diagnostic block. If the run did not finish normally, e.g. due to a compilation failure,
+/// the report ends with a bail out. The logs of the run are included as comments.
+pub fn to_tap(run_result: &RunResult) -> String {
+    let mut tap = String::new();
+    tap.push_str("TAP version 13\n");
+    // writing to a String cannot fail
+    let _ = writeln!(tap, "1..{}", run_result.test_results.len());
+
+    for (i, test_result) in run_result.test_results.iter().enumerate() {
+        let _ = writeln!(
+            tap,
+            "{} {} - {}",
+            if test_result.successful {
+                "ok"
+            } else {
+                "not ok"
+            },
+            i + 1,
+            escape_tap_description(&test_result.name)
+        );
+
+        let diagnostics = TapDiagnostics {
+            points: &test_result.points,
+            message: Some(test_result.message.as_str()).filter(|m| !m.is_empty()),
+            exception: &test_result.exception,
+            duration_ms: test_result.duration_ms,
+            stdout: test_result.stdout.as_deref(),
+            stderr: test_result.stderr.as_deref(),
+        };
+        if !diagnostics.is_empty() {
+            // the YAML document starts with "---", which also marks the beginning of the block in TAP
+            if let Ok(yaml) = serde_yaml::to_string(&diagnostics) {
+                for line in yaml.lines() {
+                    let _ = writeln!(tap, "  {}", line);
+                }
+                tap.push_str("  ...\n");
+            }
+        }
+    }
+
+    for (key, value) in sorted_logs(run_result) {
+        let _ = writeln!(tap, "# {}:", key);
+        for line in value.lines() {
+            let _ = writeln!(tap, "# {}", line);
+        }
+    }
+    if let Some(message) = status_error(&run_result.status) {
+        let _ = writeln!(tap, "Bail out! {}", message);
+    }
+    tap
+}
+
+#[derive(Serialize)]
+struct TapDiagnostics<'a> {
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    points: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    exception: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stdout: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stderr: Option<&'a str>,
+}
+
+impl TapDiagnostics<'_> {
+    fn is_empty(&self) -> bool {
+        self.points.is_empty()
+            && self.message.is_none()
+            && self.exception.is_empty()
+            && self.duration_ms.is_none()
+            && self.stdout.is_none()
+            && self.stderr.is_none()
+    }
+}
+
+/// Returns an error message for statuses that mean the tests were not run to completion.
+fn status_error(status: &RunStatus) -> Option<&'static str> {
+    match status {
+        RunStatus::Passed | RunStatus::TestsFailed => None,
+        RunStatus::CompileFailed => Some("Compilation failed"),
+        RunStatus::TestrunInterrupted => Some("Test run was interrupted"),
+        RunStatus::GenericError => Some("Test run failed"),
+    }
+}
+
+fn status_name(status: &RunStatus) -> &'static str {
+    match status {
+        RunStatus::Passed => "PASSED",
+        RunStatus::TestsFailed => "TESTS_FAILED",
+        RunStatus::CompileFailed => "COMPILE_FAILED",
+        RunStatus::TestrunInterrupted => "TESTRUN_INTERRUPTED",
+        RunStatus::GenericError => "GENERIC_ERROR",
+    }
+}
+
+/// Returns the logs sorted by key so that the reports are deterministic.
+fn sorted_logs(run_result: &RunResult) -> Vec<(&str, &str)> {
+    let mut logs: Vec<_> = run_result
+        .logs
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    logs.sort_unstable();
+    logs
+}
+
+fn format_secs(ms: u64) -> String {
+    format!("{}.{:03}", ms / 1000, ms % 1000)
+}
+
+/// Escapes text for use in XML element content.
+fn escape_xml(s: &str) -> String {
+    escape_xml_chars(s, false)
+}
+
+/// Escapes text for use in an XML attribute value, preserving line breaks.
+fn escape_xml_attr(s: &str) -> String {
+    escape_xml_chars(s, true)
+}
+
+fn escape_xml_chars(s: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            '\n' if attribute => escaped.push_str("&#10;"),
+            '\r' if attribute => escaped.push_str("&#13;"),
+            '\t' if attribute => escaped.push_str("&#9;"),
+            '\n' | '\r' | '\t' => escaped.push(c),
+            // characters that are not allowed in XML 1.0
+            c if (c as u32) < 0x20 => escaped.push('\u{FFFD}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_tap_description(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('#', "\\#")
+        .replace(&['\n', '\r'][..], " ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn run_result() -> RunResult {
+        let mut logs = HashMap::new();
+        logs.insert("stdout".to_string(), "out & about".to_string());
+        RunResult::new(
+            RunStatus::TestsFailed,
+            vec![
+                TestResult {
+                    name: "Test#one".to_string(),
+                    successful: true,
+                    points: vec!["1.1".to_string()],
+                    message: String::new(),
+                    exception: vec![],
+                    duration_ms: Some(1500),
+                    stdout: None,
+                    stderr: None,
+                },
+                TestResult {
+                    name: "Test <two>".to_string(),
+                    successful: false,
+                    points: vec!["1.2".to_string(), "1.3".to_string()],
+                    message: "expected \"a\"".to_string(),
+                    exception: vec!["at line 1".to_string(), "at line 2".to_string()],
+                    duration_ms: None,
+                    stdout: Some("printed".to_string()),
+                    stderr: None,
+                },
+            ],
+            logs,
+        )
+    }
+
+    #[test]
+    fn converts_to_junit_xml() {
+        init();
+
+        let xml = to_junit_xml(&run_result(), "exercise");
+        log::debug!("{}", xml);
+        assert!(xml.contains(
+            r#"<testsuite name="exercise" tests="2" failures="1" errors="0" time="1.500">"#
+        ));
+        assert!(xml.contains(r#"<property name="status" value="TESTS_FAILED"/>"#));
+        assert!(xml.contains(r#"<testcase name="Test#one" classname="exercise" time="1.500">"#));
+        assert!(xml.contains(r#"<property name="points" value="1.2 1.3"/>"#));
+        assert!(xml.contains(
+            "<failure message=\"expected &quot;a&quot;\">at line 1\nat line 2</failure>"
+        ));
+        assert!(xml.contains("<system-out>printed</system-out>"));
+        assert!(xml.contains("<system-out>out &amp; about</system-out>"));
+    }
+
+    #[test]
+    fn converts_compile_failure_to_junit_error() {
+        init();
+
+        let run_result = RunResult::new(RunStatus::CompileFailed, vec![], HashMap::new());
+        let xml = to_junit_xml(&run_result, "exercise");
+        assert!(xml.contains(r#"tests="1" failures="0" errors="1""#));
+        assert!(xml.contains(r#"<error message="Compilation failed" type="COMPILE_FAILED"/>"#));
+    }
+
+    #[test]
+    fn converts_to_tap() {
+        init();
+
+        let tap = to_tap(&run_result());
+        log::debug!("{}", tap);
+        let lines: Vec<_> = tap.lines().collect();
+        assert_eq!(lines[0], "TAP version 13");
+        assert_eq!(lines[1], "1..2");
+        assert_eq!(lines[2], "ok 1 - Test\\#one");
+        assert!(lines.contains(&"not ok 2 - Test <two>"));
+        assert!(lines.contains(&"  message: \"expected \\\"a\\\"\""));
+        assert!(lines.contains(&"# stdout:"));
+        assert!(lines.contains(&"# out & about"));
+        assert!(!tap.contains("Bail out!"));
+    }
+
+    #[test]
+    fn bails_out_on_compile_failure() {
+        init();
+
+        let run_result = RunResult::new(RunStatus::CompileFailed, vec![], HashMap::new());
+        let tap = to_tap(&run_result);
+        assert_eq!(tap, "TAP version 13\n1..0\nBail out! Compilation failed\n");
+    }
+}
@@ -0,0 +1,6 @@
+#!/bin/sh
+# starts a child process that keeps running and holding stdout open after this script is killed,
+# writing its pid to the file given as the first argument
+sleep 30 &
+echo $! > "$1"
+wait
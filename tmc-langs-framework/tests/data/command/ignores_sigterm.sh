@@ -0,0 +1,4 @@
+#!/bin/sh
+# ignores SIGTERM, the ignore is inherited by the sleep as well
+trap '' TERM
+sleep 30
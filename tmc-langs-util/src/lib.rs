@@ -3,10 +3,12 @@
 pub mod task_executor;
 
 pub use tmc_langs_framework::{
+    command::ResourceLimits,
     domain::{
-        ExerciseDesc, ExercisePackagingConfiguration, RunResult, RunStatus, Strategy,
+        ExerciseDesc, ExercisePackagingConfiguration, RunResult, RunStatus, Strategy, TestFilter,
         ValidationResult,
     },
+    io::tmc_zip::{Reproducibility, UnzipLimits},
     plugin::Language,
     TmcError,
 };
@@ -1,26 +1,42 @@
 //! Module for calling different tasks of TMC-langs language plug-ins.
 
+mod checksum;
+mod course_audit;
+mod plugin_registry;
+mod sandbox;
+mod student_file_watcher;
 mod submission_packaging;
 mod tar_helper;
+mod verification;
 
+pub use checksum::{calculate_exercise_checksum, calculate_exercise_digest};
+pub use course_audit::audit_course;
+pub use plugin_registry::{
+    detect_plugins, register_plugin, registered_plugins, DynLanguagePlugin, PluginDescriptor,
+};
+pub use sandbox::run_sandboxed;
+pub use student_file_watcher::StudentFileWatcher;
 pub use submission_packaging::TmcParams;
+pub use verification::{verify_exercise, verify_exercises};
 
-use crate::{ExerciseDesc, ExercisePackagingConfiguration, RunResult, TmcError, ValidationResult};
-use log::info;
+use crate::{
+    ExerciseDesc, ExercisePackagingConfiguration, ResourceLimits, RunResult, TmcError,
+    ValidationResult,
+};
+use plugin_registry::get_language_plugin;
+use std::error::Error;
 use std::path::{Path, PathBuf};
-use tmc_langs_csharp::CSharpPlugin;
 use tmc_langs_framework::{
-    domain::TmcProjectYml,
-    io::{self, submission_processing},
-    plugin::{Language, LanguagePlugin},
+    domain::{meta_syntax::MetaSyntaxLint, ExercisePoints, FilePreview, TestFilter, TmcProjectYml},
+    io::{
+        self,
+        archive::ArchiveFormat,
+        submission_processing,
+        tmc_zip::{Reproducibility, UnzipLimits},
+    },
+    plugin::Language,
     policy::NothingIsStudentFilePolicy,
 };
-use tmc_langs_java::AntPlugin;
-use tmc_langs_java::MavenPlugin;
-use tmc_langs_make::MakePlugin;
-use tmc_langs_notests::NoTestsPlugin;
-use tmc_langs_python3::Python3Plugin;
-use tmc_langs_r::RPlugin;
 use walkdir::WalkDir;
 
 /// See `domain::prepare_solutions`.
@@ -32,6 +48,16 @@ pub fn prepare_solutions<'a, I: IntoIterator<Item = &'a PathBuf>>(
     Ok(())
 }
 
+/// See `submission_processing::lint_exercise`.
+pub fn lint_exercise(exercise_path: &Path) -> Result<Vec<MetaSyntaxLint>, TmcError> {
+    submission_processing::lint_exercise(exercise_path)
+}
+
+/// See `submission_processing::preview_exercise`.
+pub fn preview_exercise(exercise_path: &Path) -> Result<Vec<FilePreview>, TmcError> {
+    submission_processing::preview_exercise(exercise_path)
+}
+
 /// See `LanguagePlugin::prepare_stubs`.
 pub fn prepare_stubs<I: IntoIterator<Item = PathBuf>>(
     exercise_paths: I,
@@ -45,8 +71,11 @@ pub fn prepare_stubs<I: IntoIterator<Item = PathBuf>>(
     Ok(())
 }
 
-/// Takes a submission zip and turns it into a tar suitable for processing
-/// by among other things resetting the test files
+/// Takes a submission archive and turns it into an archive in the given format suitable for processing
+/// by among other things resetting the test files.
+/// The submission and stub zips are checked against the given limits when they are unzipped.
+/// With `Reproducibility::Deterministic`, the same submission always produces the same archive.
+#[allow(clippy::too_many_arguments)]
 pub fn prepare_submission(
     zip_path: &Path,
     target_path: &Path,
@@ -54,7 +83,9 @@ pub fn prepare_submission(
     tmc_params: TmcParams,
     clone_path: &Path,
     stub_zip_path: Option<&Path>,
-    output_zip: bool,
+    output_format: ArchiveFormat,
+    unzip_limits: UnzipLimits,
+    reproducibility: Reproducibility,
 ) -> Result<(), TmcError> {
     submission_packaging::prepare_submission(
         zip_path,
@@ -63,7 +94,9 @@ pub fn prepare_submission(
         tmc_params,
         clone_path,
         stub_zip_path,
-        output_zip,
+        output_format,
+        unzip_limits,
+        reproducibility,
     )
 }
 
@@ -80,6 +113,34 @@ pub fn run_tests(path: &Path) -> Result<RunResult, TmcError> {
     get_language_plugin(path)?.run_tests(&path)
 }
 
+/// See `LanguagePlugin::run_tests_filtered`.
+pub fn run_tests_filtered(path: &Path, filter: &TestFilter) -> Result<RunResult, TmcError> {
+    get_language_plugin(path)?.run_tests_filtered(path, filter)
+}
+
+/// See `LanguagePlugin::run_tests_with_limits`.
+pub fn run_tests_with_limits(
+    path: &Path,
+    resource_limits: ResourceLimits,
+    filter: &TestFilter,
+) -> Result<RunResult, TmcError> {
+    get_language_plugin(path)?.run_tests_with_limits(path, resource_limits, filter)
+}
+
+/// Runs the tests for the exercise and calculates the points the test run would award.
+/// See `ExercisePoints::new`.
+pub fn calculate_points(path: &Path, exercise_name: String) -> Result<ExercisePoints, TmcError> {
+    let plugin = get_language_plugin(path)?;
+    let exercise_desc = plugin.scan_exercise(path, exercise_name)?;
+    let run_result = plugin.run_tests(path)?;
+    let no_tests = TmcProjectYml::from(path)?.no_tests;
+    Ok(ExercisePoints::new(
+        &exercise_desc,
+        &run_result,
+        no_tests.as_ref(),
+    ))
+}
+
 /// See `LanguagePlugin::scan_exercise`.
 pub fn scan_exercise(path: &Path, exercise_name: String) -> Result<ExerciseDesc, TmcError> {
     Ok(get_language_plugin(path)?.scan_exercise(path, exercise_name)?)
@@ -96,15 +157,16 @@ pub fn extract_project(
     compressed_project: &Path,
     target_location: &Path,
     clean: bool,
+    limits: UnzipLimits,
 ) -> Result<(), TmcError> {
     if let Ok(plugin) = get_language_plugin(target_location) {
-        plugin.extract_project(compressed_project, target_location, clean)?;
+        plugin.extract_project(compressed_project, target_location, clean, limits)?;
     } else {
         log::debug!(
             "no matching language plugin found for {}, overwriting",
             compressed_project.display()
         );
-        extract_project_overwrite(compressed_project, target_location)?;
+        extract_project_overwrite(compressed_project, target_location, limits)?;
     }
     Ok(())
 }
@@ -114,11 +176,13 @@ pub fn extract_project(
 pub fn extract_project_overwrite(
     compressed_project: &Path,
     target_location: &Path,
+    limits: UnzipLimits,
 ) -> Result<(), TmcError> {
     io::tmc_zip::unzip(
         NothingIsStudentFilePolicy {},
         compressed_project,
         target_location,
+        limits,
     )?;
     Ok(())
 }
@@ -126,22 +190,26 @@ pub fn extract_project_overwrite(
 pub fn extract_student_files(
     compressed_project: &Path,
     target_location: &Path,
+    limits: UnzipLimits,
 ) -> Result<(), TmcError> {
     if let Ok(plugin) = get_language_plugin(target_location) {
-        plugin.extract_student_files(compressed_project, target_location)?;
+        plugin.extract_student_files(compressed_project, target_location, limits)?;
     } else {
         log::debug!(
             "no matching language plugin found for {}, overwriting",
             compressed_project.display()
         );
-        extract_project_overwrite(compressed_project, target_location)?;
+        extract_project_overwrite(compressed_project, target_location, limits)?;
     }
     Ok(())
 }
 
 /// See `LanguagePlugin::compress_project`.
-pub fn compress_project(path: &Path) -> Result<Vec<u8>, TmcError> {
-    Ok(get_language_plugin(path)?.compress_project(path)?)
+pub fn compress_project(
+    path: &Path,
+    reproducibility: Reproducibility,
+) -> Result<Vec<u8>, TmcError> {
+    Ok(get_language_plugin(path)?.compress_project(path, reproducibility)?)
 }
 
 /// See `LanguagePlugin::get_exercise_packaging_configuration`.
@@ -169,202 +237,41 @@ pub fn clean(path: &Path) -> Result<(), TmcError> {
     Ok(())
 }
 
+/// Finds the exercise directories in the given directory, skipping hidden directories,
+/// directories named `private` and directories that contain a `.tmcignore` file.
+/// The directories inside exercise directories are not searched.
 pub fn find_exercise_directories(exercise_path: &Path) -> Vec<PathBuf> {
     let mut paths = vec![];
-    for entry in WalkDir::new(exercise_path)
+    let mut walker = WalkDir::new(exercise_path)
         .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(submission_processing::is_hidden_dir)
-        .filter(|e| e.file_name() == "private")
-        .filter(submission_processing::contains_tmcignore)
-    {
+        // the root directory is always searched
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !(submission_processing::is_hidden_dir(e)
+                    || e.file_name() == "private"
+                    || submission_processing::contains_tmcignore(e))
+        });
+    while let Some(entry) = walker.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
         // TODO: Java implementation doesn't scan root directories
-        if is_exercise_root_directory(entry.path()) {
-            paths.push(entry.into_path())
+        if entry.file_type().is_dir() && is_exercise_root_directory(entry.path()) {
+            paths.push(entry.into_path());
+            walker.skip_current_dir();
         }
     }
     paths
 }
 
-enum Plugin {
-    CSharp(CSharpPlugin),
-    Make(MakePlugin),
-    Maven(MavenPlugin),
-    NoTests(NoTestsPlugin),
-    Python3(Python3Plugin),
-    R(RPlugin),
-    Ant(AntPlugin),
-}
-
-// TODO: write proc macro
-impl Plugin {
-    fn clean(&self, path: &Path) -> Result<(), TmcError> {
-        match self {
-            Self::CSharp(plugin) => plugin.clean(path),
-            Self::Make(plugin) => plugin.clean(path),
-            Self::Maven(plugin) => plugin.clean(path),
-            Self::NoTests(plugin) => plugin.clean(path),
-            Self::Python3(plugin) => plugin.clean(path),
-            Self::R(plugin) => plugin.clean(path),
-            Self::Ant(plugin) => plugin.clean(path),
-        }
-    }
-
-    fn get_exercise_packaging_configuration(
-        &self,
-        path: &Path,
-    ) -> Result<ExercisePackagingConfiguration, TmcError> {
-        match self {
-            Self::CSharp(plugin) => plugin.get_exercise_packaging_configuration(path),
-            Self::Make(plugin) => plugin.get_exercise_packaging_configuration(path),
-            Self::Maven(plugin) => plugin.get_exercise_packaging_configuration(path),
-            Self::NoTests(plugin) => plugin.get_exercise_packaging_configuration(path),
-            Self::Python3(plugin) => plugin.get_exercise_packaging_configuration(path),
-            Self::R(plugin) => plugin.get_exercise_packaging_configuration(path),
-            Self::Ant(plugin) => plugin.get_exercise_packaging_configuration(path),
-        }
-    }
-
-    fn compress_project(&self, path: &Path) -> Result<Vec<u8>, TmcError> {
-        match self {
-            Self::CSharp(plugin) => plugin.compress_project(path),
-            Self::Make(plugin) => plugin.compress_project(path),
-            Self::Maven(plugin) => plugin.compress_project(path),
-            Self::NoTests(plugin) => plugin.compress_project(path),
-            Self::Python3(plugin) => plugin.compress_project(path),
-            Self::R(plugin) => plugin.compress_project(path),
-            Self::Ant(plugin) => plugin.compress_project(path),
-        }
-    }
-
-    fn extract_project(
-        &self,
-        cmpressed_project: &Path,
-        target_location: &Path,
-        clean: bool,
-    ) -> Result<(), TmcError> {
-        match self {
-            Self::CSharp(plugin) => {
-                plugin.extract_project(cmpressed_project, target_location, clean)
-            }
-            Self::Make(plugin) => plugin.extract_project(cmpressed_project, target_location, clean),
-            Self::Maven(plugin) => {
-                plugin.extract_project(cmpressed_project, target_location, clean)
-            }
-            Self::NoTests(plugin) => {
-                plugin.extract_project(cmpressed_project, target_location, clean)
-            }
-            Self::Python3(plugin) => {
-                plugin.extract_project(cmpressed_project, target_location, clean)
-            }
-            Self::R(plugin) => plugin.extract_project(cmpressed_project, target_location, clean),
-            Self::Ant(plugin) => plugin.extract_project(cmpressed_project, target_location, clean),
-        }
-    }
-
-    fn extract_student_files(
-        &self,
-        cmpressed_project: &Path,
-        target_location: &Path,
-    ) -> Result<(), TmcError> {
-        match self {
-            Self::CSharp(plugin) => {
-                plugin.extract_student_files(cmpressed_project, target_location)
-            }
-            Self::Make(plugin) => plugin.extract_student_files(cmpressed_project, target_location),
-            Self::Maven(plugin) => plugin.extract_student_files(cmpressed_project, target_location),
-            Self::NoTests(plugin) => {
-                plugin.extract_student_files(cmpressed_project, target_location)
-            }
-            Self::Python3(plugin) => {
-                plugin.extract_student_files(cmpressed_project, target_location)
-            }
-            Self::R(plugin) => plugin.extract_student_files(cmpressed_project, target_location),
-            Self::Ant(plugin) => plugin.extract_student_files(cmpressed_project, target_location),
-        }
-    }
-
-    fn scan_exercise(&self, path: &Path, exercise_name: String) -> Result<ExerciseDesc, TmcError> {
-        match self {
-            Self::CSharp(plugin) => plugin.scan_exercise(path, exercise_name),
-            Self::Make(plugin) => plugin.scan_exercise(path, exercise_name),
-            Self::Maven(plugin) => plugin.scan_exercise(path, exercise_name),
-            Self::NoTests(plugin) => plugin.scan_exercise(path, exercise_name),
-            Self::Python3(plugin) => plugin.scan_exercise(path, exercise_name),
-            Self::R(plugin) => plugin.scan_exercise(path, exercise_name),
-            Self::Ant(plugin) => plugin.scan_exercise(path, exercise_name),
-        }
-    }
-
-    fn run_tests(&self, path: &Path) -> Result<RunResult, TmcError> {
-        match self {
-            Self::CSharp(plugin) => plugin.run_tests(path),
-            Self::Make(plugin) => plugin.run_tests(path),
-            Self::Maven(plugin) => plugin.run_tests(path),
-            Self::NoTests(plugin) => plugin.run_tests(path),
-            Self::Python3(plugin) => plugin.run_tests(path),
-            Self::R(plugin) => plugin.run_tests(path),
-            Self::Ant(plugin) => plugin.run_tests(path),
-        }
-    }
-
-    fn check_code_style(&self, path: &Path, locale: Language) -> Option<ValidationResult> {
-        match self {
-            Self::CSharp(plugin) => plugin.check_code_style(path, locale),
-            Self::Make(plugin) => plugin.check_code_style(path, locale),
-            Self::Maven(plugin) => plugin.check_code_style(path, locale),
-            Self::NoTests(plugin) => plugin.check_code_style(path, locale),
-            Self::Python3(plugin) => plugin.check_code_style(path, locale),
-            Self::R(plugin) => plugin.check_code_style(path, locale),
-            Self::Ant(plugin) => plugin.check_code_style(path, locale),
-        }
-    }
-
-    fn prepare_stub(
-        &self,
-        exercise_path: &Path,
-        repo_path: &Path,
-        dest_path: &Path,
-    ) -> Result<(), TmcError> {
-        match self {
-            Self::CSharp(plugin) => plugin.prepare_stub(exercise_path, repo_path, dest_path),
-            Self::Make(plugin) => plugin.prepare_stub(exercise_path, repo_path, dest_path),
-            Self::Maven(plugin) => plugin.prepare_stub(exercise_path, repo_path, dest_path),
-            Self::NoTests(plugin) => plugin.prepare_stub(exercise_path, repo_path, dest_path),
-            Self::Python3(plugin) => plugin.prepare_stub(exercise_path, repo_path, dest_path),
-            Self::R(plugin) => plugin.prepare_stub(exercise_path, repo_path, dest_path),
-            Self::Ant(plugin) => plugin.prepare_stub(exercise_path, repo_path, dest_path),
-        }
-    }
-}
-
-// Get language plugin for the given path.
-fn get_language_plugin(path: &Path) -> Result<Plugin, TmcError> {
-    if CSharpPlugin::is_exercise_type_correct(path) {
-        let csharp = CSharpPlugin::new();
-        info!("Detected project as {}", CSharpPlugin::PLUGIN_NAME);
-        Ok(Plugin::CSharp(csharp))
-    } else if MakePlugin::is_exercise_type_correct(path) {
-        let make = MakePlugin::new();
-        info!("Detected project as {}", MakePlugin::PLUGIN_NAME);
-        Ok(Plugin::Make(make))
-    } else if NoTestsPlugin::is_exercise_type_correct(path) {
-        info!("Detected project as {}", NoTestsPlugin::PLUGIN_NAME);
-        Ok(Plugin::NoTests(NoTestsPlugin::new()))
-    } else if Python3Plugin::is_exercise_type_correct(path) {
-        info!("Detected project as {}", Python3Plugin::PLUGIN_NAME);
-        Ok(Plugin::Python3(Python3Plugin::new()))
-    } else if RPlugin::is_exercise_type_correct(path) {
-        info!("Detected project as {}", RPlugin::PLUGIN_NAME);
-        Ok(Plugin::R(RPlugin::new()))
-    } else if MavenPlugin::is_exercise_type_correct(path) {
-        info!("Detected project as {}", MavenPlugin::PLUGIN_NAME);
-        Ok(Plugin::Maven(MavenPlugin::new()?))
-    } else if AntPlugin::is_exercise_type_correct(path) {
-        // TODO: currently, ant needs to be last because any project with src and test are recognized as ant
-        info!("Detected project as {}", AntPlugin::PLUGIN_NAME);
-        Ok(Plugin::Ant(AntPlugin::new()?))
-    } else {
-        Err(TmcError::PluginNotFound(path.to_path_buf()))
+// Formats the error with all of its causes for reports that collect errors instead of returning them
+fn format_error_chain(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
     }
+    message
 }
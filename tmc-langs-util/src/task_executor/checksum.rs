@@ -0,0 +1,145 @@
+//! Contains functions for calculating exercise checksums and digests.
+
+use super::get_language_plugin;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tmc_langs_framework::{domain::TmcProjectYml, TmcError};
+use walkdir::WalkDir;
+
+/// Calculates the MD5 checksum of the exercise directory for comparing a local exercise to the version on the TMC server,
+/// for example to find the checksums for `TmcCore::get_exercise_updates`.
+/// Hidden files and directories are excluded. For every other entry, in the order of the paths, the digest is updated
+/// with the entry's path relative to the exercise, with forward slashes, followed by the contents for files.
+/// The paths and contents are not separated, as the server's `Exercise::checksum` is calculated the same way.
+pub fn calculate_exercise_checksum(exercise_path: &Path) -> Result<String, TmcError> {
+    let mut context = md5::Context::new();
+    for (relative, path) in non_hidden_entries(exercise_path)? {
+        context.consume(relative.as_bytes());
+        if path.is_file() {
+            let contents = fs::read(&path).map_err(|e| TmcError::FileRead(path, e))?;
+            context.consume(&contents);
+        }
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+
+/// Calculates an MD5 digest of the exercise's files that are not student files according to the plugin's
+/// `StudentFilePolicy`, including any `extra_student_files` in .tmcproject.yml, for detecting changes to the test files.
+/// Hidden files and directories are excluded. The path and contents of each file are prefixed with their lengths
+/// so that moving bytes between them changes the digest.
+pub fn calculate_exercise_digest(exercise_path: &Path) -> Result<String, TmcError> {
+    let plugin = get_language_plugin(exercise_path)?;
+    let policy = plugin.get_student_file_policy(exercise_path);
+    let tmc_project_yml = TmcProjectYml::from(exercise_path)?;
+
+    let mut context = md5::Context::new();
+    for (relative, path) in non_hidden_entries(exercise_path)? {
+        if !path.is_file() || policy.is_student_file(&path, exercise_path, &tmc_project_yml)? {
+            continue;
+        }
+        let contents = fs::read(&path).map_err(|e| TmcError::FileRead(path, e))?;
+        context.consume((relative.len() as u64).to_le_bytes());
+        context.consume(relative.as_bytes());
+        context.consume((contents.len() as u64).to_le_bytes());
+        context.consume(&contents);
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+
+// returns the files and directories in the exercise that are not hidden or in a hidden directory,
+// sorted by their relative paths with forward slashes
+fn non_hidden_entries(exercise_path: &Path) -> Result<Vec<(String, PathBuf)>, TmcError> {
+    let mut entries = vec![];
+    for entry in WalkDir::new(exercise_path)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| {
+            !e.file_name()
+                .to_str()
+                .map(|s| s.starts_with('.'))
+                .unwrap_or_default()
+        })
+    {
+        let entry = entry?;
+        let relative = entry
+            .path()
+            .strip_prefix(exercise_path)
+            .expect("the walked path is always inside the exercise path");
+        // the server uses forward slashes on all platforms
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        entries.push((relative, entry.into_path()));
+    }
+    // sorted by the relative path strings like on the server, not by the walk order
+    entries.sort();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tmc_langs_framework::io::file_util;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn calculates_checksum() {
+        init();
+
+        // calculated with a separate Python script from the sorted entries setup.py, src, src/__init__.py, src/main.py,
+        // test, test/__init__.py, test/test_extra.py and test/test_main.py; the hidden .tmcproject.yml is excluded
+        let expected = "c2b3b61393624609fbf58f8413709ed7";
+        let checksum =
+            calculate_exercise_checksum(Path::new("tests/data/ChecksumExercise")).unwrap();
+        assert_eq!(checksum, expected);
+
+        let temp = tempfile::tempdir().unwrap();
+        file_util::copy("tests/data/ChecksumExercise", temp.path()).unwrap();
+        let exercise = temp.path().join("ChecksumExercise");
+
+        // hidden files don't affect the checksum
+        fs::write(exercise.join(".hidden"), "hidden").unwrap();
+        fs::create_dir(exercise.join(".git")).unwrap();
+        fs::write(exercise.join(".git/HEAD"), "ref").unwrap();
+        assert_eq!(calculate_exercise_checksum(&exercise).unwrap(), expected);
+
+        // student files do
+        fs::write(exercise.join("src/main.py"), "changed\n").unwrap();
+        assert_ne!(calculate_exercise_checksum(&exercise).unwrap(), expected);
+    }
+
+    #[test]
+    fn calculates_digest() {
+        init();
+
+        // calculated with a separate Python script from setup.py, test/__init__.py and test/test_main.py,
+        // each path and contents prefixed with their lengths as little-endian 64-bit integers;
+        // the hidden .tmcproject.yml, the student files in src and the extra student file test/test_extra.py are excluded
+        let expected = "5c077f6b4b459e3504c3ca49d9392427";
+        let digest = calculate_exercise_digest(Path::new("tests/data/ChecksumExercise")).unwrap();
+        assert_eq!(digest, expected);
+
+        let temp = tempfile::tempdir().unwrap();
+        file_util::copy("tests/data/ChecksumExercise", temp.path()).unwrap();
+        let exercise = temp.path().join("ChecksumExercise");
+
+        // student files and hidden files don't affect the digest
+        fs::write(exercise.join("src/main.py"), "changed\n").unwrap();
+        fs::write(exercise.join("test/test_extra.py"), "changed\n").unwrap();
+        fs::write(exercise.join(".hidden"), "hidden").unwrap();
+        assert_eq!(calculate_exercise_digest(&exercise).unwrap(), expected);
+
+        // test files do, even if the bytes only move from the contents of a file to its path
+        let test_main = exercise.join("test/test_main.py");
+        let contents = fs::read(&test_main).unwrap();
+        assert_eq!(contents[0], b'i');
+        fs::remove_file(&test_main).unwrap();
+        fs::write(exercise.join("test/test_main.pyi"), &contents[1..]).unwrap();
+        assert_ne!(calculate_exercise_digest(&exercise).unwrap(), expected);
+    }
+}
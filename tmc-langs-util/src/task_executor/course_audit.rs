@@ -0,0 +1,233 @@
+//! Contains functions for checking the points and test names of all the exercises in a course for consistency.
+
+use super::{
+    detect_plugins, find_exercise_directories, format_error_chain, get_language_plugin,
+    registered_plugins,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use tmc_langs_framework::{
+    domain::{CourseAuditIssue, CourseAuditIssueKind, ExerciseDesc, TmcProjectYml},
+    plugin::LanguagePlugin,
+    TmcError,
+};
+use tmc_langs_notests::NoTestsPlugin;
+
+lazy_static! {
+    // letters, numbers and the separators commonly used in point names such as "1.2" or "week1-ex2_a"
+    static ref USUAL_POINT_NAME: Regex = Regex::new(r"^[\p{L}\p{N}_.\-]+$").unwrap();
+}
+
+/// Finds the exercises in the course directory with `find_exercise_directories`, scans each of them
+/// and reports points that are awarded by more than one exercise, tests and exercises without points, `no-tests` points
+/// that clash with the points of tests, unusual point names and exercises that more than one language plugin recognises.
+pub fn audit_course(course_path: &Path) -> Vec<CourseAuditIssue> {
+    let mut issues = vec![];
+    // all of the exercises that award each point
+    let mut point_exercises: BTreeMap<String, BTreeSet<PathBuf>> = BTreeMap::new();
+
+    for exercise_path in find_exercise_directories(course_path) {
+        log::debug!("auditing exercise at {}", exercise_path.display());
+        match audit_exercise(&exercise_path, &mut issues) {
+            Ok(points) => {
+                for point in points {
+                    point_exercises
+                        .entry(point)
+                        .or_default()
+                        .insert(exercise_path.clone());
+                }
+            }
+            Err(e) => issues.push(issue(
+                CourseAuditIssueKind::ScanFailed,
+                &exercise_path,
+                format!("failed to scan the exercise: {}", format_error_chain(&e)),
+            )),
+        }
+    }
+
+    for (point, exercise_paths) in point_exercises {
+        if exercise_paths.len() > 1 {
+            issues.push(CourseAuditIssue {
+                kind: CourseAuditIssueKind::DuplicatePoint,
+                message: format!(
+                    "the point {:?} is awarded by {} exercises",
+                    point,
+                    exercise_paths.len()
+                ),
+                exercise_paths: exercise_paths.into_iter().collect(),
+            });
+        }
+    }
+    issues
+}
+
+// Checks the exercise for issues that concern it alone and returns all of its points
+fn audit_exercise(
+    exercise_path: &Path,
+    issues: &mut Vec<CourseAuditIssue>,
+) -> Result<BTreeSet<String>, TmcError> {
+    let detection = detect_plugins(exercise_path)?;
+    // the no-tests plugin is meant to be used together with a language plugin, so it doesn't make the detection ambiguous
+    let language_plugins: Vec<_> = detection
+        .matching_plugins
+        .iter()
+        .filter(|p| *p != NoTestsPlugin::PLUGIN_NAME)
+        .map(String::as_str)
+        .collect();
+    if detection.configured_plugin.is_none() && language_plugins.len() > 1 {
+        issues.push(issue(
+            CourseAuditIssueKind::AmbiguousPlugin,
+            exercise_path,
+            format!(
+                "the exercise is recognised by the plugins {}, {} is used",
+                language_plugins.join(", "),
+                detection.selected_plugin.as_deref().unwrap_or_default()
+            ),
+        ));
+    }
+
+    let exercise_name = exercise_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let exercise_desc =
+        get_language_plugin(exercise_path)?.scan_exercise(exercise_path, exercise_name.clone())?;
+    for test in &exercise_desc.tests {
+        if test.points.is_empty() {
+            issues.push(issue(
+                CourseAuditIssueKind::TestWithoutPoints,
+                exercise_path,
+                format!("the test {} does not award any points", test.name),
+            ));
+        }
+    }
+    let mut points = all_points(&exercise_desc);
+
+    if let Some(no_tests) = TmcProjectYml::from(exercise_path)?.no_tests {
+        // the no-tests plugin awards the no-tests points, so the points of the tests are found with the other plugin
+        let test_points =
+            if detection.selected_plugin.as_deref() == Some(NoTestsPlugin::PLUGIN_NAME) {
+                let other_plugin = registered_plugins().into_iter().find(|p| {
+                    p.name() != NoTestsPlugin::PLUGIN_NAME
+                        && detection.matching_plugins.iter().any(|m| m == p.name())
+                });
+                match other_plugin {
+                    Some(other_plugin) => all_points(
+                        &other_plugin
+                            .instantiate()?
+                            .scan_exercise(exercise_path, exercise_name)?,
+                    ),
+                    None => BTreeSet::new(),
+                }
+            } else {
+                points.clone()
+            };
+        for point in no_tests.points {
+            if test_points.contains(&point) {
+                issues.push(issue(
+                    CourseAuditIssueKind::NoTestsPointClash,
+                    exercise_path,
+                    format!("the no-tests point {:?} is also awarded by a test", point),
+                ));
+            }
+            points.insert(point);
+        }
+    }
+
+    if points.is_empty() {
+        issues.push(issue(
+            CourseAuditIssueKind::NoPoints,
+            exercise_path,
+            "the exercise does not award any points".to_string(),
+        ));
+    }
+    for point in &points {
+        if !USUAL_POINT_NAME.is_match(point) {
+            issues.push(issue(
+                CourseAuditIssueKind::UnusualPointName,
+                exercise_path,
+                format!("the point name {:?} is unusual", point),
+            ));
+        }
+    }
+    Ok(points)
+}
+
+fn all_points(exercise_desc: &ExerciseDesc) -> BTreeSet<String> {
+    exercise_desc
+        .tests
+        .iter()
+        .flat_map(|t| t.points.iter().cloned())
+        .collect()
+}
+
+fn issue(kind: CourseAuditIssueKind, exercise_path: &Path, message: String) -> CourseAuditIssue {
+    CourseAuditIssue {
+        kind,
+        exercise_paths: vec![exercise_path.to_path_buf()],
+        message,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tmc_langs_framework::io::file_util;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn audits_course() {
+        init();
+
+        // scanning the exercises writes files into them, so the course is copied
+        // along with the shared python test runner
+        let temp = tempfile::tempdir().unwrap();
+        file_util::copy("tests/data/AuditCourse", temp.path()).unwrap();
+        let course = temp.path().join("AuditCourse");
+        for exercise in &["first", "second", "third", "fourth"] {
+            file_util::copy("tests/data/PythonTestRunner/tmc", course.join(exercise)).unwrap();
+        }
+
+        let issues = audit_course(&course);
+        let issues: Vec<_> = issues
+            .into_iter()
+            .map(|i| {
+                let names: Vec<_> = i
+                    .exercise_paths
+                    .iter()
+                    .map(|p| p.file_name().unwrap().to_str().unwrap().to_string())
+                    .collect();
+                (i.kind, names)
+            })
+            .collect();
+        let expected = &[
+            (
+                CourseAuditIssueKind::DuplicatePoint,
+                vec!["first", "second"],
+            ),
+            (CourseAuditIssueKind::TestWithoutPoints, vec!["second"]),
+            (CourseAuditIssueKind::UnusualPointName, vec!["second"]),
+            // third is recognised by the python3 and no-tests plugins, which is not ambiguous
+            (CourseAuditIssueKind::NoTestsPointClash, vec!["third"]),
+            // fourth is recognised by the python3 and r plugins
+            (CourseAuditIssueKind::AmbiguousPlugin, vec!["fourth"]),
+            (CourseAuditIssueKind::TestWithoutPoints, vec!["fourth"]),
+            (CourseAuditIssueKind::NoPoints, vec!["fourth"]),
+        ];
+        assert_eq!(issues.len(), expected.len(), "{:?}", issues);
+        for (kind, names) in expected {
+            assert!(
+                issues.iter().any(|(k, n)| k == kind && n == names),
+                "{:?} not found in {:?}",
+                kind,
+                issues
+            );
+        }
+    }
+}
@@ -0,0 +1,472 @@
+//! Contains the registry of language plugins used to find the plugin for an exercise.
+
+use crate::{ExerciseDesc, ExercisePackagingConfiguration, RunResult, TmcError, ValidationResult};
+use lazy_static::lazy_static;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tmc_langs_csharp::CSharpPlugin;
+use tmc_langs_external::ExternalPlugin;
+use tmc_langs_framework::{
+    command::ResourceLimits,
+    domain::{PluginDetection, TestFilter, TmcProjectYml},
+    io::tmc_zip::{Reproducibility, UnzipLimits},
+    plugin::{Language, LanguagePlugin},
+    policy::StudentFilePolicy,
+};
+use tmc_langs_java::{AntPlugin, MavenPlugin};
+use tmc_langs_make::MakePlugin;
+use tmc_langs_notests::NoTestsPlugin;
+use tmc_langs_python3::Python3Plugin;
+use tmc_langs_r::RPlugin;
+
+lazy_static! {
+    static ref PLUGINS: RwLock<Vec<PluginDescriptor>> = RwLock::new(vec![
+        // external plugins are configured explicitly for the exercise, so they take precedence
+        PluginDescriptor::new(80, || Ok::<_, TmcError>(ExternalPlugin::new())),
+        PluginDescriptor::new(70, || Ok::<_, TmcError>(CSharpPlugin::new())),
+        PluginDescriptor::new(60, || Ok::<_, TmcError>(MakePlugin::new())),
+        PluginDescriptor::new(50, || Ok::<_, TmcError>(NoTestsPlugin::new())),
+        PluginDescriptor::new(40, || Ok::<_, TmcError>(Python3Plugin::new())),
+        PluginDescriptor::new(30, || Ok::<_, TmcError>(RPlugin::new())),
+        PluginDescriptor::new(20, MavenPlugin::new),
+        // ant needs to be last because any project with src and test are recognized as ant
+        PluginDescriptor::new(10, AntPlugin::new),
+    ]);
+}
+
+/// An object-safe version of `LanguagePlugin`, implemented for every language plugin.
+pub trait DynLanguagePlugin {
+    /// See `LanguagePlugin::PLUGIN_NAME`.
+    fn name(&self) -> &'static str;
+
+    /// See `LanguagePlugin::clean`.
+    fn clean(&self, path: &Path) -> Result<(), TmcError>;
+
+    /// See `LanguagePlugin::get_exercise_packaging_configuration`.
+    fn get_exercise_packaging_configuration(
+        &self,
+        path: &Path,
+    ) -> Result<ExercisePackagingConfiguration, TmcError>;
+
+    /// See `LanguagePlugin::compress_project`.
+    fn compress_project(
+        &self,
+        path: &Path,
+        reproducibility: Reproducibility,
+    ) -> Result<Vec<u8>, TmcError>;
+
+    /// See `LanguagePlugin::extract_project`.
+    fn extract_project(
+        &self,
+        compressed_project: &Path,
+        target_location: &Path,
+        clean: bool,
+        limits: UnzipLimits,
+    ) -> Result<(), TmcError>;
+
+    /// See `LanguagePlugin::extract_student_files`.
+    fn extract_student_files(
+        &self,
+        compressed_project: &Path,
+        target_location: &Path,
+        limits: UnzipLimits,
+    ) -> Result<(), TmcError>;
+
+    /// See `LanguagePlugin::scan_exercise`.
+    fn scan_exercise(&self, path: &Path, exercise_name: String) -> Result<ExerciseDesc, TmcError>;
+
+    /// See `LanguagePlugin::run_tests`.
+    fn run_tests(&self, path: &Path) -> Result<RunResult, TmcError>;
+
+    /// See `LanguagePlugin::run_tests_filtered`.
+    fn run_tests_filtered(&self, path: &Path, filter: &TestFilter) -> Result<RunResult, TmcError>;
+
+    /// See `LanguagePlugin::run_tests_with_limits`.
+    fn run_tests_with_limits(
+        &self,
+        path: &Path,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
+    ) -> Result<RunResult, TmcError>;
+
+    /// See `LanguagePlugin::check_code_style`.
+    fn check_code_style(&self, path: &Path, locale: Language) -> Option<ValidationResult>;
+
+    /// See `LanguagePlugin::prepare_stub`.
+    fn prepare_stub(
+        &self,
+        exercise_path: &Path,
+        repo_path: &Path,
+        dest_path: &Path,
+    ) -> Result<(), TmcError>;
+
+    /// See `LanguagePlugin::prepare_solution`.
+    fn prepare_solution(
+        &self,
+        exercise_paths: Vec<PathBuf>,
+        dest_path: &Path,
+    ) -> Result<(), TmcError>;
+
+    /// See `LanguagePlugin::get_student_file_policy`.
+    fn get_student_file_policy(&self, project_path: &Path) -> Box<dyn StudentFilePolicy>;
+}
+
+impl<P: LanguagePlugin> DynLanguagePlugin for P {
+    fn name(&self) -> &'static str {
+        P::PLUGIN_NAME
+    }
+
+    fn clean(&self, path: &Path) -> Result<(), TmcError> {
+        LanguagePlugin::clean(self, path)
+    }
+
+    fn get_exercise_packaging_configuration(
+        &self,
+        path: &Path,
+    ) -> Result<ExercisePackagingConfiguration, TmcError> {
+        LanguagePlugin::get_exercise_packaging_configuration(self, path)
+    }
+
+    fn compress_project(
+        &self,
+        path: &Path,
+        reproducibility: Reproducibility,
+    ) -> Result<Vec<u8>, TmcError> {
+        LanguagePlugin::compress_project(self, path, reproducibility)
+    }
+
+    fn extract_project(
+        &self,
+        compressed_project: &Path,
+        target_location: &Path,
+        clean: bool,
+        limits: UnzipLimits,
+    ) -> Result<(), TmcError> {
+        LanguagePlugin::extract_project(self, compressed_project, target_location, clean, limits)
+    }
+
+    fn extract_student_files(
+        &self,
+        compressed_project: &Path,
+        target_location: &Path,
+        limits: UnzipLimits,
+    ) -> Result<(), TmcError> {
+        LanguagePlugin::extract_student_files(self, compressed_project, target_location, limits)
+    }
+
+    fn scan_exercise(&self, path: &Path, exercise_name: String) -> Result<ExerciseDesc, TmcError> {
+        LanguagePlugin::scan_exercise(self, path, exercise_name)
+    }
+
+    fn run_tests(&self, path: &Path) -> Result<RunResult, TmcError> {
+        LanguagePlugin::run_tests(self, path)
+    }
+
+    fn run_tests_filtered(&self, path: &Path, filter: &TestFilter) -> Result<RunResult, TmcError> {
+        LanguagePlugin::run_tests_filtered(self, path, filter)
+    }
+
+    fn run_tests_with_limits(
+        &self,
+        path: &Path,
+        resource_limits: ResourceLimits,
+        filter: &TestFilter,
+    ) -> Result<RunResult, TmcError> {
+        LanguagePlugin::run_tests_with_limits(self, path, resource_limits, filter)
+    }
+
+    fn check_code_style(&self, path: &Path, locale: Language) -> Option<ValidationResult> {
+        LanguagePlugin::check_code_style(self, path, locale)
+    }
+
+    fn prepare_stub(
+        &self,
+        exercise_path: &Path,
+        repo_path: &Path,
+        dest_path: &Path,
+    ) -> Result<(), TmcError> {
+        LanguagePlugin::prepare_stub(self, exercise_path, repo_path, dest_path)
+    }
+
+    fn prepare_solution(
+        &self,
+        exercise_paths: Vec<PathBuf>,
+        dest_path: &Path,
+    ) -> Result<(), TmcError> {
+        LanguagePlugin::prepare_solution(self, exercise_paths, dest_path)
+    }
+
+    fn get_student_file_policy(&self, project_path: &Path) -> Box<dyn StudentFilePolicy> {
+        Box::new(P::get_student_file_policy(project_path))
+    }
+}
+
+type PluginConstructor = dyn Fn() -> Result<Box<dyn DynLanguagePlugin>, TmcError> + Send + Sync;
+
+/// Describes a language plugin in the registry.
+#[derive(Clone)]
+pub struct PluginDescriptor {
+    name: &'static str,
+    priority: i32,
+    is_exercise_type_correct: fn(&Path) -> bool,
+    constructor: Arc<PluginConstructor>,
+}
+
+impl PluginDescriptor {
+    /// Creates a descriptor for the plugin P, constructed with the given function when an exercise is detected as its type.
+    /// Plugins with a higher priority are checked first when detecting the plugin for an exercise.
+    pub fn new<P, E, F>(priority: i32, constructor: F) -> Self
+    where
+        P: LanguagePlugin + 'static,
+        E: Into<TmcError>,
+        F: Fn() -> Result<P, E> + Send + Sync + 'static,
+    {
+        Self {
+            name: P::PLUGIN_NAME,
+            priority,
+            is_exercise_type_correct: P::is_exercise_type_correct,
+            constructor: Arc::new(move || {
+                let plugin = constructor().map_err(Into::into)?;
+                Ok(Box::new(plugin) as Box<dyn DynLanguagePlugin>)
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// See `LanguagePlugin::is_exercise_type_correct`.
+    pub fn is_exercise_type_correct(&self, path: &Path) -> bool {
+        (self.is_exercise_type_correct)(path)
+    }
+
+    /// Constructs the plugin.
+    pub fn instantiate(&self) -> Result<Box<dyn DynLanguagePlugin>, TmcError> {
+        (self.constructor)()
+    }
+}
+
+/// Adds the plugin to the registry, making it available for all tasks.
+/// Plugins with equal priorities are checked in the order they were registered in.
+pub fn register_plugin(descriptor: PluginDescriptor) {
+    log::debug!(
+        "registering plugin {} with priority {}",
+        descriptor.name,
+        descriptor.priority
+    );
+    PLUGINS
+        .write()
+        .expect("plugin registry poisoned")
+        .push(descriptor);
+}
+
+/// Returns the registered plugins in the order they are checked in.
+pub fn registered_plugins() -> Vec<PluginDescriptor> {
+    let mut plugins = PLUGINS.read().expect("plugin registry poisoned").clone();
+    // stable sort, keeps the registration order for equal priorities
+    plugins.sort_by_key(|p| -p.priority);
+    plugins
+}
+
+/// Lists the plugins that match the given path and the plugin that is selected for it.
+/// A plugin set with the `plugin` key in the exercise's `.tmcproject.yml` takes precedence over detection.
+/// Only the `plugin` key is read, so the rest of the file being invalid does not affect plugin selection.
+pub fn detect_plugins(path: &Path) -> Result<PluginDetection, TmcError> {
+    let configured_plugin = TmcProjectYml::read_plugin(path)?;
+    let plugins = registered_plugins();
+
+    let matching_plugins: Vec<String> = plugins
+        .iter()
+        .filter(|p| p.is_exercise_type_correct(path))
+        .map(|p| p.name.to_string())
+        .collect();
+    let selected_plugin = if let Some(configured) = &configured_plugin {
+        if !plugins.iter().any(|p| p.name == configured) {
+            return Err(TmcError::UnknownPlugin(configured.clone()));
+        }
+        Some(configured.clone())
+    } else {
+        matching_plugins.first().cloned()
+    };
+
+    Ok(PluginDetection {
+        matching_plugins,
+        configured_plugin,
+        selected_plugin,
+    })
+}
+
+// Get language plugin for the given path.
+pub(super) fn get_language_plugin(path: &Path) -> Result<Box<dyn DynLanguagePlugin>, TmcError> {
+    let plugins = registered_plugins();
+
+    if let Some(configured) = TmcProjectYml::read_plugin(path)? {
+        let descriptor = plugins
+            .into_iter()
+            .find(|p| p.name == configured)
+            .ok_or(TmcError::UnknownPlugin(configured))?;
+        log::info!("Using plugin {} set in .tmcproject.yml", descriptor.name);
+        return descriptor.instantiate();
+    }
+
+    let mut matching = plugins
+        .into_iter()
+        .filter(|p| p.is_exercise_type_correct(path));
+    if let Some(descriptor) = matching.next() {
+        let others: Vec<_> = matching.map(|p| p.name).collect();
+        if !others.is_empty() {
+            log::warn!(
+                "{} also matches plugins {:?}, set the plugin in .tmcproject.yml to choose one explicitly",
+                path.display(),
+                others
+            );
+        }
+        log::info!("Detected project as {}", descriptor.name);
+        return descriptor.instantiate();
+    }
+    Err(TmcError::PluginNotFound(path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tmc_langs_framework::{
+        command::ResourceLimits, domain::RunStatus, policy::EverythingIsStudentFilePolicy,
+    };
+
+    struct MockPlugin {}
+
+    impl LanguagePlugin for MockPlugin {
+        const PLUGIN_NAME: &'static str = "mock_plugin";
+        type StudentFilePolicy = EverythingIsStudentFilePolicy;
+
+        fn get_student_file_policy(project_path: &Path) -> Self::StudentFilePolicy {
+            EverythingIsStudentFilePolicy::new(project_path.to_path_buf())
+        }
+
+        fn scan_exercise(
+            &self,
+            _path: &Path,
+            exercise_name: String,
+        ) -> Result<ExerciseDesc, TmcError> {
+            Ok(ExerciseDesc::new(exercise_name, vec![]))
+        }
+
+        fn run_tests_with_timeout(
+            &self,
+            _path: &Path,
+            _timeout: Option<Duration>,
+            _resource_limits: ResourceLimits,
+            _filter: &TestFilter,
+        ) -> Result<RunResult, TmcError> {
+            Ok(RunResult::new(RunStatus::Passed, vec![], HashMap::new()))
+        }
+
+        fn is_exercise_type_correct(path: &Path) -> bool {
+            path.join("mock_plugin_marker").exists()
+        }
+
+        fn clean(&self, _path: &Path) -> Result<(), TmcError> {
+            Ok(())
+        }
+    }
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn orders_by_priority() {
+        init();
+
+        let plugins = registered_plugins();
+        let priorities = plugins.iter().map(|p| p.priority()).collect::<Vec<_>>();
+        let mut sorted = priorities.clone();
+        sorted.sort_by(|a, b| b.cmp(a));
+        assert_eq!(priorities, sorted);
+        assert_eq!(plugins.last().unwrap().name(), AntPlugin::PLUGIN_NAME);
+    }
+
+    #[test]
+    fn finds_builtin_plugin() {
+        init();
+
+        let plugin = get_language_plugin(Path::new("tests/data/PythonExercise")).unwrap();
+        assert_eq!(plugin.name(), Python3Plugin::PLUGIN_NAME);
+    }
+
+    #[test]
+    fn finds_registered_plugin() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("mock_plugin_marker"), "").unwrap();
+        // would otherwise be detected as python
+        std::fs::write(temp.path().join("setup.py"), "").unwrap();
+        assert_eq!(
+            get_language_plugin(temp.path()).unwrap().name(),
+            Python3Plugin::PLUGIN_NAME
+        );
+
+        register_plugin(PluginDescriptor::new(100, || {
+            Ok::<_, TmcError>(MockPlugin {})
+        }));
+        let plugin = get_language_plugin(temp.path()).unwrap();
+        assert_eq!(plugin.name(), MockPlugin::PLUGIN_NAME);
+        let desc = plugin
+            .scan_exercise(temp.path(), "name".to_string())
+            .unwrap();
+        assert_eq!(desc.name, "name");
+    }
+
+    #[test]
+    fn uses_configured_plugin() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("setup.py"), "").unwrap();
+        std::fs::create_dir(temp.path().join("src")).unwrap();
+        std::fs::create_dir(temp.path().join("test")).unwrap();
+        let detection = detect_plugins(temp.path()).unwrap();
+        assert_eq!(
+            detection.matching_plugins,
+            &[Python3Plugin::PLUGIN_NAME, AntPlugin::PLUGIN_NAME]
+        );
+        assert_eq!(detection.configured_plugin, None);
+        assert_eq!(
+            detection.selected_plugin.as_deref(),
+            Some(Python3Plugin::PLUGIN_NAME)
+        );
+
+        std::fs::write(temp.path().join(".tmcproject.yml"), "plugin: apache-ant\n").unwrap();
+        let detection = detect_plugins(temp.path()).unwrap();
+        assert_eq!(detection.configured_plugin.as_deref(), Some("apache-ant"));
+        assert_eq!(detection.selected_plugin.as_deref(), Some("apache-ant"));
+        let plugin = get_language_plugin(temp.path()).unwrap();
+        assert_eq!(plugin.name(), AntPlugin::PLUGIN_NAME);
+    }
+
+    #[test]
+    fn errors_on_unknown_configured_plugin() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("setup.py"), "").unwrap();
+        std::fs::write(temp.path().join(".tmcproject.yml"), "plugin: cobol\n").unwrap();
+        assert!(matches!(
+            detect_plugins(temp.path()),
+            Err(TmcError::UnknownPlugin(_))
+        ));
+        assert!(matches!(
+            get_language_plugin(temp.path()),
+            Err(TmcError::UnknownPlugin(_))
+        ));
+    }
+}
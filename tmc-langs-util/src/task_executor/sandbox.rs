@@ -0,0 +1,274 @@
+//! Contains functions for running prepared submissions locally in the same way as the grading sandbox.
+
+use crate::RunResult;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tmc_langs_framework::{
+    command::{OutputWithTimeout, ResourceLimits, TmcCommand},
+    domain::{SandboxResult, SandboxStatus},
+    io::{file_util, tmc_zip::UnzipLimits},
+    TmcError,
+};
+
+/// Sources the parameters and runs the command given as the script's arguments.
+const RUN_SCRIPT: &str = r#". ./.tmcparams && exec "$@""#;
+
+/// Isolates the test run from the network and from the other processes of the host.
+#[cfg(target_os = "linux")]
+const UNSHARE_ARGS: &[&str] = &[
+    "--user",
+    "--map-root-user",
+    "--net",
+    "--ipc",
+    "--uts",
+    "--pid",
+    "--fork",
+    "--kill-child",
+];
+
+/// Extracts a submission prepared with `prepare_submission` in any of the archive formats into a temporary directory and runs its tests
+/// like the grading sandbox does: the `.tmcparams` file is sourced, and the tests are ran with the given
+/// tmc-langs CLI executable's `run-tests` command with the given timeout and resource limits.
+/// On Linux, the run is isolated from the network and from other processes with namespaces if they are available.
+pub fn run_sandboxed(
+    archive_path: &Path,
+    tmc_langs_cli: &Path,
+    timeout: Option<Duration>,
+    resource_limits: ResourceLimits,
+) -> Result<SandboxResult, TmcError> {
+    log::debug!("running {} in sandbox", archive_path.display());
+
+    let temp = tempfile::tempdir().map_err(TmcError::TempDir)?;
+    let sandbox_dir = temp.path().join("sandbox");
+    fs::create_dir(&sandbox_dir).map_err(|e| TmcError::CreateDir(sandbox_dir.clone(), e))?;
+    file_util::extract_archive(
+        archive_path,
+        &sandbox_dir,
+        |_| false,
+        UnzipLimits::default(),
+    )?;
+    let project_dir = find_project_dir(&sandbox_dir)?;
+    let test_output_path = temp.path().join("test_output.json");
+
+    let mut command = sandbox_command();
+    command
+        .current_dir(&project_dir)
+        .arg("-c")
+        .arg(RUN_SCRIPT)
+        .arg("bash")
+        .arg(tmc_langs_cli)
+        .arg("run-tests")
+        .arg("--exercise-path")
+        .arg(&project_dir)
+        .arg("--output-path")
+        .arg(&test_output_path);
+    command.resource_limits(resource_limits);
+    let output = command.output_with_timeout(timeout)?;
+
+    let stdout = String::from_utf8_lossy(output.stdout()).into_owned();
+    let stderr = String::from_utf8_lossy(output.stderr()).into_owned();
+    let (status, exit_code) = match &output {
+        OutputWithTimeout::Timeout { .. } => (SandboxStatus::Timeout, None),
+        OutputWithTimeout::Output(output) if output.status.success() => {
+            (SandboxStatus::Finished, output.status.code())
+        }
+        OutputWithTimeout::Output(output) => (SandboxStatus::Failed, output.status.code()),
+    };
+    let test_output = if status == SandboxStatus::Timeout {
+        None
+    } else {
+        read_test_output(&test_output_path)
+    };
+
+    Ok(SandboxResult {
+        status,
+        exit_code,
+        test_output,
+        stdout,
+        stderr,
+    })
+}
+
+/// Finds the directory with the .tmcparams file, which is either the root of the archive
+/// or its top level directory.
+fn find_project_dir(sandbox_dir: &Path) -> Result<PathBuf, TmcError> {
+    if sandbox_dir.join(".tmcparams").exists() {
+        return Ok(sandbox_dir.to_path_buf());
+    }
+    let entries =
+        fs::read_dir(sandbox_dir).map_err(|e| TmcError::FileRead(sandbox_dir.to_path_buf(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| TmcError::FileRead(sandbox_dir.to_path_buf(), e))?;
+        if entry.path().join(".tmcparams").exists() {
+            return Ok(entry.path());
+        }
+    }
+    Err(TmcError::NoProjectDirInZip)
+}
+
+fn read_test_output(path: &Path) -> Option<RunResult> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            log::warn!("no test output found at {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    match serde_json::from_reader(file) {
+        Ok(run_result) => Some(run_result),
+        Err(e) => {
+            log::warn!("invalid test output at {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn sandbox_command() -> TmcCommand {
+    let namespaces_available = TmcCommand::new("unshare")
+        .args(UNSHARE_ARGS)
+        .arg("true")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    if namespaces_available {
+        let mut command = TmcCommand::new("unshare");
+        command.args(UNSHARE_ARGS).arg("bash");
+        command
+    } else {
+        log::warn!("namespaces are not available, running the tests without isolation");
+        TmcCommand::new("bash")
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sandbox_command() -> TmcCommand {
+    log::warn!(
+        "namespaces are not supported on this platform, running the tests without isolation"
+    );
+    TmcCommand::new("bash")
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod test {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    // writes a submission tar with .tmcparams and a fake tmc-langs that writes a result with the parameter
+    fn setup(runner_script: &str) -> (TempDir, PathBuf, PathBuf) {
+        let temp = tempfile::tempdir().unwrap();
+        let project = temp.path().join("project");
+        fs::create_dir(&project).unwrap();
+        fs::write(
+            project.join(".tmcparams"),
+            "export TEST_PARAM=param-value\n",
+        )
+        .unwrap();
+
+        let archive_path = temp.path().join("submission.tar");
+        let mut archive = tar::Builder::new(File::create(&archive_path).unwrap());
+        archive.append_dir_all("exercise", &project).unwrap();
+        archive.finish().unwrap();
+
+        let runner = temp.path().join("tmc-langs-cli");
+        fs::write(&runner, runner_script).unwrap();
+        fs::set_permissions(&runner, fs::Permissions::from_mode(0o755)).unwrap();
+        (temp, archive_path, runner)
+    }
+
+    #[test]
+    fn runs_submission() {
+        init();
+
+        let (_temp, archive_path, runner) = setup(
+            r#"#!/bin/sh
+# arguments: run-tests --exercise-path <path> --output-path <path>
+test -f "$3/.tmcparams" || exit 2
+echo "running with $TEST_PARAM"
+printf '{"status":"PASSED","testResults":[{"name":"%s","successful":true,"points":["1"],"message":""}],"logs":{}}' "$TEST_PARAM" > "$5"
+"#,
+        );
+        let result =
+            run_sandboxed(&archive_path, &runner, None, ResourceLimits::default()).unwrap();
+        log::debug!("{:?}", result);
+        assert_eq!(result.status, SandboxStatus::Finished);
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(result.stdout, "running with param-value\n");
+        let test_output = result.test_output.unwrap();
+        assert_eq!(test_output.test_results[0].name, "param-value");
+    }
+
+    #[test]
+    fn reports_failure_and_timeout() {
+        init();
+
+        let (_temp, archive_path, runner) = setup("#!/bin/sh\necho failed >&2\nexit 3\n");
+        let result =
+            run_sandboxed(&archive_path, &runner, None, ResourceLimits::default()).unwrap();
+        assert_eq!(result.status, SandboxStatus::Failed);
+        assert_eq!(result.exit_code, Some(3));
+        assert_eq!(result.stderr, "failed\n");
+        assert!(result.test_output.is_none());
+
+        let (_temp, archive_path, runner) = setup("#!/bin/sh\nsleep 10\n");
+        let result = run_sandboxed(
+            &archive_path,
+            &runner,
+            Some(Duration::from_millis(200)),
+            ResourceLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(result.status, SandboxStatus::Timeout);
+        assert_eq!(result.exit_code, None);
+    }
+
+    #[test]
+    fn rejects_unsafe_archives() {
+        init();
+
+        let (temp, _archive_path, runner) =
+            setup("#!/bin/sh\ntest -e \"$3/link\" && exit 5\nexit 0\n");
+        let escaping = temp.path().join("escaping.tar");
+        let mut archive = tar::Builder::new(File::create(&escaping).unwrap());
+        let mut header = tar::Header::new_gnu();
+        // set_path rejects .. so the name is written directly
+        header.as_old_mut().name[..13].copy_from_slice(b"../escape.txt");
+        header.set_size(4);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append(&header, &b"data"[..]).unwrap();
+        archive.finish().unwrap();
+        assert!(matches!(
+            run_sandboxed(&escaping, &runner, None, ResourceLimits::default()),
+            Err(TmcError::PathEscapesRoot(_))
+        ));
+        assert!(!temp.path().join("escape.txt").exists());
+
+        // symlinks are skipped when extracting
+        let symlink = temp.path().join("symlink.tar");
+        let mut archive = tar::Builder::new(File::create(&symlink).unwrap());
+        archive
+            .append_path_with_name(
+                temp.path().join("project/.tmcparams"),
+                "exercise/.tmcparams",
+            )
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        archive
+            .append_link(&mut header, "exercise/link", "/etc")
+            .unwrap();
+        archive.finish().unwrap();
+        let result = run_sandboxed(&symlink, &runner, None, ResourceLimits::default()).unwrap();
+        assert_eq!(result.exit_code, Some(0));
+    }
+}
@@ -0,0 +1,164 @@
+//! Contains a watcher that detects changes to the student source files of an exercise.
+
+use super::get_language_plugin;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tmc_langs_framework::{policy::StudentFilePolicy, TmcError};
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// Watches the student source files of an exercise, as determined by `StudentFilePolicy::is_student_source_file`,
+/// by polling their modification times and sizes.
+pub struct StudentFileWatcher {
+    exercise_path: PathBuf,
+    policy: Box<dyn StudentFilePolicy>,
+    files: HashMap<PathBuf, FileState>,
+}
+
+impl StudentFileWatcher {
+    /// Starts watching the exercise using the student file policy of its language plugin.
+    pub fn new(exercise_path: &Path) -> Result<Self, TmcError> {
+        let plugin = get_language_plugin(exercise_path)?;
+        let policy = plugin.get_student_file_policy(exercise_path);
+        let mut watcher = Self {
+            exercise_path: exercise_path.to_path_buf(),
+            policy,
+            files: HashMap::new(),
+        };
+        watcher.refresh();
+        Ok(watcher)
+    }
+
+    /// Returns the currently watched files.
+    pub fn student_source_files(&self) -> Vec<&Path> {
+        let mut files: Vec<_> = self.files.keys().map(PathBuf::as_path).collect();
+        files.sort();
+        files
+    }
+
+    /// Updates the state of the watched files without reporting changes.
+    /// Any changes made since the last call to `wait_for_change` are discarded.
+    pub fn refresh(&mut self) {
+        self.files = self.snapshot();
+    }
+
+    /// Blocks until a student source file is created, modified or removed, polling at the given interval.
+    /// Changes are detected relative to the state at the end of the previous call, so changes made
+    /// between the calls, such as while running the tests, are reported immediately.
+    /// After the first change, waits until the files stop changing so that saving several files at once
+    /// is only reported once. Returns the changed files.
+    pub fn wait_for_change(&mut self, poll_interval: Duration) -> Vec<PathBuf> {
+        let mut changed = loop {
+            thread::sleep(poll_interval);
+            let snapshot = self.snapshot();
+            let changed = changed_files(&self.files, &snapshot);
+            self.files = snapshot;
+            if !changed.is_empty() {
+                break changed;
+            }
+        };
+        loop {
+            thread::sleep(poll_interval);
+            let snapshot = self.snapshot();
+            let more_changed = changed_files(&self.files, &snapshot);
+            self.files = snapshot;
+            if more_changed.is_empty() {
+                break;
+            }
+            changed.extend(more_changed);
+        }
+        changed.sort();
+        changed.dedup();
+        log::debug!("detected changes in {:?}", changed);
+        changed
+    }
+
+    fn snapshot(&self) -> HashMap<PathBuf, FileState> {
+        let mut files = HashMap::new();
+        // files can be removed while walking, so errors are skipped
+        for entry in WalkDir::new(&self.exercise_path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let relative = match entry.path().strip_prefix(&self.exercise_path) {
+                Ok(relative) => relative,
+                Err(_) => continue,
+            };
+            if !self.policy.is_student_source_file(relative) {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata() {
+                let state = FileState {
+                    modified: metadata.modified().ok(),
+                    len: metadata.len(),
+                };
+                files.insert(entry.into_path(), state);
+            }
+        }
+        files
+    }
+}
+
+fn changed_files(
+    old: &HashMap<PathBuf, FileState>,
+    new: &HashMap<PathBuf, FileState>,
+) -> Vec<PathBuf> {
+    let modified_or_created = new
+        .iter()
+        .filter(|(path, state)| old.get(*path) != Some(state))
+        .map(|(path, _)| path.clone());
+    let removed = old.keys().filter(|path| !new.contains_key(*path)).cloned();
+    modified_or_created.chain(removed).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn detects_student_file_changes() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("setup.py"), "").unwrap();
+        fs::create_dir(temp.path().join("src")).unwrap();
+        fs::create_dir(temp.path().join("test")).unwrap();
+        fs::write(temp.path().join("src/main.py"), "print(1)").unwrap();
+        fs::write(temp.path().join("test/test_main.py"), "").unwrap();
+
+        let mut watcher = StudentFileWatcher::new(temp.path()).unwrap();
+        assert_eq!(
+            watcher.student_source_files(),
+            &[temp.path().join("src/main.py")]
+        );
+
+        let exercise_path = temp.path().to_path_buf();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            // not a student source file
+            fs::write(exercise_path.join("test/test_main.py"), "changed").unwrap();
+            fs::write(exercise_path.join("src/main.py"), "print(12)").unwrap();
+        });
+        let changed = watcher.wait_for_change(Duration::from_millis(20));
+        handle.join().unwrap();
+        assert_eq!(changed, &[temp.path().join("src/main.py")]);
+
+        // a change made between waits, e.g. during a test run, is reported by the next wait
+        fs::write(temp.path().join("src/main.py"), "print(123)").unwrap();
+        let changed = watcher.wait_for_change(Duration::from_millis(20));
+        assert_eq!(changed, &[temp.path().join("src/main.py")]);
+    }
+}
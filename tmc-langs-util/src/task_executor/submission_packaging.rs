@@ -1,20 +1,31 @@
-use super::{get_language_plugin, Plugin, TmcError, TmcProjectYml};
+use super::{get_language_plugin, TmcError, TmcProjectYml};
 
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::fs;
 use std::io::Write;
+use std::iter::Peekable;
 use std::path::Path;
-use tmc_langs_framework::io::file_util;
+use std::str::Chars;
+use tmc_langs_framework::{
+    io::{
+        archive::{self, ArchiveEntry, ArchiveFormat},
+        file_util, path_util, submission_processing,
+        tmc_zip::{Reproducibility, UnzipLimits},
+    },
+    plugin::LanguagePlugin,
+};
+use tmc_langs_java::MavenPlugin;
+use tmc_langs_make::MakePlugin;
 use walkdir::WalkDir;
-use zip::{read::ZipFile, write::FileOptions, ZipWriter};
 
 /// TmcParams is used to safely construct data for a .tmcparams file, which contains lines in the form of
-/// export A=B
-/// export C=(D, E, F)
-/// the keys and values of the inner hashmap are validated to make sure they are valid as bash variables
-#[derive(Debug, Default)]
+/// export A='B'
+/// export C=('D' 'E' 'F')
+/// the keys are validated to make sure they are valid as bash variables, and the values are quoted with POSIX shell single quotes.
+/// Files with only string parameters can be sourced by any POSIX shell, while arrays require bash.
+#[derive(Debug, Default, PartialEq, Eq)]
 pub struct TmcParams(HashMap<ShellString, TmcParam>);
 
 impl TmcParams {
@@ -79,30 +90,101 @@ impl TmcParams {
         Ok(())
     }
 
+    /// Parses the contents of a .tmcparams file written by TmcParams.
+    /// Supports `export KEY=value`, `KEY=value`, `KEY=(values...)` and `export KEY` statements separated by newlines or semicolons,
+    /// with values quoted with single quotes, double quotes or backslashes. Comments and empty lines are skipped.
+    /// Returns an error for anything that would require expanding variables or running commands.
+    pub fn parse(contents: &str) -> Result<Self, TmcError> {
+        let mut params = Self::new();
+        let mut parser = ParamParser {
+            chars: contents.chars().peekable(),
+        };
+        loop {
+            parser.skip_separators();
+            if parser.chars.peek().is_none() {
+                break;
+            }
+
+            let mut key = parser.read_name();
+            if key == "export" && matches!(parser.chars.peek(), Some(c) if c.is_whitespace()) {
+                parser.skip_blanks();
+                key = parser.read_name();
+                if parser.chars.peek() != Some(&'=') {
+                    // export without an assignment, such as the ones used for arrays in bash
+                    if !Self::is_valid_key(&key) {
+                        return Err(TmcError::InvalidParam(key));
+                    }
+                    parser.expect_statement_end()?;
+                    continue;
+                }
+            }
+            if parser.chars.next() != Some('=') {
+                return Err(TmcError::InvalidParam(format!(
+                    "expected an assignment to {:?} in .tmcparams",
+                    key
+                )));
+            }
+
+            if parser.chars.peek() == Some(&'(') {
+                parser.chars.next();
+                let mut values = vec![];
+                loop {
+                    parser.skip_whitespace();
+                    match parser.chars.peek() {
+                        Some(')') => {
+                            parser.chars.next();
+                            break;
+                        }
+                        Some(_) => values.push(parser.read_word()?),
+                        None => {
+                            return Err(TmcError::InvalidParam(format!(
+                                "unterminated array {:?} in .tmcparams",
+                                key
+                            )))
+                        }
+                    }
+                }
+                params.insert_array(key, values)?;
+            } else {
+                let value = parser.read_word()?;
+                params.insert_string(key, value)?;
+            }
+            parser.expect_statement_end()?;
+        }
+        Ok(params)
+    }
+
     fn is_valid_key<S: AsRef<str>>(string: S) -> bool {
-        if string.as_ref().is_empty() {
-            return false;
+        let string = string.as_ref();
+        match string.chars().next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+            _ => return false,
         }
 
-        for c in string.as_ref().chars() {
-            if !c.is_ascii_alphabetic() && c != '_' {
+        for c in string.chars() {
+            if !c.is_ascii_alphanumeric() && c != '_' {
                 return false;
             }
         }
         true
     }
 
+    // any string can be single quoted, but shell variables can't contain null bytes
     fn is_valid_value<S: AsRef<str>>(string: S) -> bool {
-        if string.as_ref().is_empty() {
-            return false;
-        }
+        !string.as_ref().contains('\0')
+    }
+}
 
-        for c in string.as_ref().chars() {
-            if !c.is_ascii_alphabetic() && c != '_' && c != '-' {
-                return false;
-            }
+// writes the parameters sorted by key so that the file is the same for the same parameters
+impl Display for TmcParams {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let mut params = self.0.iter().collect::<Vec<_>>();
+        params.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        for (key, value) in params {
+            // the key is validated, so it does not need to be quoted
+            writeln!(f, "export {}={}", key.0, value)?;
         }
-        true
+        Ok(())
     }
 }
 
@@ -110,20 +192,20 @@ impl TmcParams {
 struct ShellString(String);
 
 /// .tmcparams variables can be strings or arrays
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 enum TmcParam {
     String(ShellString),
     Array(Vec<ShellString>),
 }
 
-// the Display impl escapes the inner strings with shellwords
+// the Display impl quotes the inner strings and uses the bash syntax for arrays
 impl Display for TmcParam {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
             Self::String(s) => s.fmt(f),
             Self::Array(v) => write!(
                 f,
-                "( {} )",
+                "({})",
                 v.iter()
                     .map(|s| s.to_string())
                     .collect::<Vec<_>>()
@@ -133,13 +215,156 @@ impl Display for TmcParam {
     }
 }
 
-// the Display impl escapes the inner string with shellwords
+// the Display impl quotes the inner string with single quotes,
+// which prevent the shell from interpreting any characters except for single quotes themselves.
+// a single quote is written by closing the quotes, writing an escaped quote and reopening the quotes
 impl Display for ShellString {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "{}", shellwords::escape(&self.0))
+        write!(f, "'{}'", self.0.replace('\'', r"'\''"))
     }
 }
 
+// a small parser for the subset of the shell syntax used in .tmcparams files
+struct ParamParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl ParamParser<'_> {
+    fn skip_blanks(&mut self) {
+        while let Some(' ') | Some('\t') = self.chars.peek() {
+            self.chars.next();
+        }
+    }
+
+    // skips whitespace, line continuations and comments, which are allowed between the values of an array
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.chars.next();
+                }
+                Some('#') => self.skip_comment(),
+                Some('\\') => {
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    if lookahead.next() != Some('\n') {
+                        break;
+                    }
+                    self.chars = lookahead;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() == Some(&';') {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        for c in self.chars.by_ref() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn expect_statement_end(&mut self) -> Result<(), TmcError> {
+        self.skip_blanks();
+        match self.chars.peek() {
+            None | Some('\n') | Some(';') | Some('#') => Ok(()),
+            Some(c) => Err(TmcError::InvalidParam(format!(
+                "unexpected character {:?} in .tmcparams",
+                c
+            ))),
+        }
+    }
+
+    fn read_name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !c.is_ascii_alphanumeric() && c != '_' {
+                break;
+            }
+            name.push(c);
+            self.chars.next();
+        }
+        name
+    }
+
+    // reads a single shell word, removing the quotes and escapes
+    fn read_word(&mut self) -> Result<String, TmcError> {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '\'' => {
+                    self.chars.next();
+                    loop {
+                        match self.chars.next() {
+                            Some('\'') => break,
+                            Some(c) => word.push(c),
+                            None => return Err(Self::unterminated("single quote")),
+                        }
+                    }
+                }
+                '"' => {
+                    self.chars.next();
+                    loop {
+                        match self.chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match self.chars.next() {
+                                Some('\n') => {}
+                                Some(c @ '$') | Some(c @ '`') | Some(c @ '"') | Some(c @ '\\') => {
+                                    word.push(c)
+                                }
+                                Some(c) => {
+                                    word.push('\\');
+                                    word.push(c);
+                                }
+                                None => return Err(Self::unterminated("double quote")),
+                            },
+                            Some(c @ '$') | Some(c @ '`') => return Err(Self::expansion(c)),
+                            Some(c) => word.push(c),
+                            None => return Err(Self::unterminated("double quote")),
+                        }
+                    }
+                }
+                '\\' => {
+                    self.chars.next();
+                    match self.chars.next() {
+                        Some('\n') => {}
+                        Some(c) => word.push(c),
+                        None => word.push('\\'),
+                    }
+                }
+                '$' | '`' => return Err(Self::expansion(c)),
+                c if c.is_whitespace() || c == ';' || c == '(' || c == ')' => break,
+                c => {
+                    word.push(c);
+                    self.chars.next();
+                }
+            }
+        }
+        Ok(word)
+    }
+
+    fn unterminated(quote: &str) -> TmcError {
+        TmcError::InvalidParam(format!("unterminated {} in .tmcparams", quote))
+    }
+
+    fn expansion(c: char) -> TmcError {
+        TmcError::InvalidParam(format!("unsupported expansion {:?} in .tmcparams", c))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn prepare_submission(
     zip_path: &Path,
     target_path: &Path,
@@ -147,11 +372,13 @@ pub fn prepare_submission(
     tmc_params: TmcParams,
     clone_path: &Path,
     stub_zip_path: Option<&Path>,
-    output_zip: bool,
+    output_format: ArchiveFormat,
+    unzip_limits: UnzipLimits,
+    reproducibility: Reproducibility,
 ) -> Result<(), TmcError> {
     log::debug!("preparing submission for {}", zip_path.display());
 
-    fn useless_file_filter(entry: &ZipFile) -> bool {
+    fn useless_file_filter(entry: &ArchiveEntry) -> bool {
         let files_to_filter = &[
             OsStr::new(".DS_Store"),
             OsStr::new("desktop.ini"),
@@ -159,7 +386,10 @@ pub fn prepare_submission(
             OsStr::new(".directory"),
             OsStr::new("__MACOSX"),
         ];
-        files_to_filter.contains(&entry.sanitized_name().as_os_str())
+        entry
+            .path()
+            .map(|path| files_to_filter.contains(&path.as_os_str()))
+            .unwrap_or_default()
     }
 
     let temp = tempfile::tempdir().map_err(TmcError::TempDir)?;
@@ -168,7 +398,7 @@ pub fn prepare_submission(
 
     // unzip submission to received dir
     log::debug!("unzipping submission");
-    file_util::unzip(zip_path, &received_dir, useless_file_filter)?;
+    file_util::extract_archive(zip_path, &received_dir, useless_file_filter, unzip_limits)?;
 
     // find project dir in unzipped files
     let project_root = file_util::find_project_root(&received_dir)?;
@@ -186,14 +416,11 @@ pub fn prepare_submission(
     log::debug!("writing .tmcparams");
     let tmc_params_path = dest.join(".tmcparams");
     let mut tmc_params_file = file_util::create_file(&tmc_params_path)?;
-    for (key, value) in tmc_params.0 {
-        // todo handle arrays, shell escapes
-        let export = format!("export {}={}\n", key, value);
-        log::debug!("{}", export);
-        tmc_params_file
-            .write_all(export.as_bytes())
-            .map_err(|e| TmcError::Write(tmc_params_path.clone(), e))?;
-    }
+    let tmc_params = tmc_params.to_string();
+    log::debug!("{}", tmc_params);
+    tmc_params_file
+        .write_all(tmc_params.as_bytes())
+        .map_err(|e| TmcError::Write(tmc_params_path.clone(), e))?;
 
     // copy IDE files
     log::debug!("copying IDE files");
@@ -219,8 +446,13 @@ pub fn prepare_submission(
     // if stub zip path was given, unzip and find its project root
     let stub_project_root = if let Some(stub_zip_path) = stub_zip_path {
         let stub_dir = temp.path().join("stub");
-        file_util::unzip(stub_zip_path, &stub_dir, useless_file_filter)?;
-        file_util::find_project_root(stub_dir)?
+        file_util::extract_archive(stub_zip_path, &stub_dir, useless_file_filter, unzip_limits)?;
+        let stub_project_root = file_util::find_project_root(stub_dir)?;
+        if let Some(stub_project_root) = &stub_project_root {
+            // the stub's tests are missing the hidden checks
+            submission_processing::restore_hidden_blocks(clone_path, stub_project_root)?;
+        }
+        stub_project_root
     } else {
         None
     };
@@ -228,8 +460,8 @@ pub fn prepare_submission(
     // copy files
     log::debug!("copying language specific files");
     let tests_dir = stub_project_root.as_deref().unwrap_or(clone_path);
-    match plugin {
-        Plugin::Maven(_) => {
+    match plugin.name() {
+        MavenPlugin::PLUGIN_NAME => {
             // maven
 
             // copy pom
@@ -280,7 +512,7 @@ pub fn prepare_submission(
                 file_util::copy(entry.path(), dest.join(stripped))?;
             }
         }
-        Plugin::Make(_) => {
+        MakePlugin::PLUGIN_NAME => {
             // make
 
             // copy src and test
@@ -346,10 +578,9 @@ pub fn prepare_submission(
     log::debug!("copying extra student files");
     let tmc_project_yml = TmcProjectYml::from(clone_path)?;
     for extra_student_file in tmc_project_yml.extra_student_files {
-        // todo secure path
-        let source = received_dir.join(&extra_student_file);
+        let source = path_util::safe_join(&received_dir, &extra_student_file)?;
         if source.exists() {
-            let target = dest.join(&extra_student_file);
+            let target = path_util::safe_join(&dest, &extra_student_file)?;
             file_util::copy(source, target)?;
         }
     }
@@ -360,38 +591,7 @@ pub fn prepare_submission(
         .as_ref()
         .map(Path::new)
         .unwrap_or_else(|| Path::new(""));
-    let archive_file = file_util::create_file(target_path)?;
-    if output_zip {
-        let mut archive = ZipWriter::new(archive_file);
-        for entry in WalkDir::new(&dest).into_iter().skip(1) {
-            let entry = entry?;
-            let entry_path = entry.path();
-            let stripped = prefix.join(entry_path.strip_prefix(&dest).unwrap());
-            log::debug!(
-                "adding {} to zip at {}",
-                entry_path.display(),
-                stripped.display()
-            );
-            if entry_path.is_dir() {
-                archive.add_directory_from_path(&stripped, FileOptions::default())?;
-            } else {
-                archive.start_file_from_path(&stripped, FileOptions::default())?;
-                let mut file = file_util::open_file(entry_path)?;
-                std::io::copy(&mut file, &mut archive).unwrap();
-            }
-        }
-        archive.finish()?;
-    } else {
-        let mut archive = tar::Builder::new(archive_file);
-        log::debug!(
-            "appending \"{}\" at \"{}\"",
-            dest.display(),
-            prefix.display()
-        );
-        archive
-            .append_dir_all(prefix, dest)
-            .map_err(TmcError::TarAppend)?;
-    }
+    archive::create_archive(&dest, prefix, target_path, output_format, reproducibility)?;
     Ok(())
 }
 
@@ -436,7 +636,9 @@ mod test {
             tmc_params,
             Path::new(clone),
             None,
-            false,
+            ArchiveFormat::Tar,
+            UnzipLimits::default(),
+            Reproducibility::Deterministic,
         )
         .unwrap();
         assert!(output_archive.exists());
@@ -504,8 +706,119 @@ mod test {
         log::debug!("tmcparams {}", conts);
         let lines: Vec<_> = conts.lines().collect();
         assert_eq!(lines.len(), 2);
-        assert!(lines.contains(&"export param_one=value_one"));
-        assert!(lines.contains(&"export param_two=( value_two value_three )"));
+        assert!(lines.contains(&"export param_one='value_one'"));
+        assert!(lines.contains(&"export param_two=('value_two' 'value_three')"));
+    }
+
+    // sources the params with the shell and prints the values of the variables given as arguments separated by null bytes
+    fn source_params(shell: &str, tmc_params: &TmcParams, print: &str) -> Vec<String> {
+        let temp = tempfile::tempdir().unwrap();
+        let param_file = temp.path().join(".tmcparams");
+        fs::write(&param_file, tmc_params.to_string()).unwrap();
+        let script = format!(r#". "$1" && printf '%s\0' {}"#, print);
+        let output = std::process::Command::new(shell)
+            .arg("-c")
+            .arg(script)
+            .arg(shell)
+            .arg(&param_file)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        stdout.split_terminator('\0').map(str::to_string).collect()
+    }
+
+    const TRICKY_VALUES: &[&str] = &[
+        "two words",
+        "it's",
+        r#"say "hi""#,
+        "$HOME `ls` $(ls)",
+        "back\\slash\\",
+        "new\nline",
+        "",
+        "'",
+        "!*?;&|<>#~",
+    ];
+
+    #[test]
+    fn tmcparams_strings_can_be_sourced_with_sh() {
+        init();
+        let mut tmc_params = TmcParams::new();
+        for (i, value) in TRICKY_VALUES.iter().enumerate() {
+            tmc_params
+                .insert_string(format!("param_{}", i), value)
+                .unwrap();
+        }
+        let print = (0..TRICKY_VALUES.len())
+            .map(|i| format!(r#""$param_{}""#, i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let values = source_params("sh", &tmc_params, &print);
+        assert_eq!(values, TRICKY_VALUES);
+    }
+
+    #[test]
+    fn tmcparams_arrays_can_be_sourced_with_bash() {
+        init();
+        let mut tmc_params = TmcParams::new();
+        tmc_params
+            .insert_array("array", TRICKY_VALUES.to_vec())
+            .unwrap();
+        tmc_params.insert_string("string", "it's a string").unwrap();
+        let values = source_params("bash", &tmc_params, r#""${array[@]}" "$string""#);
+        assert_eq!(&values[..TRICKY_VALUES.len()], TRICKY_VALUES);
+        assert_eq!(values[TRICKY_VALUES.len()], "it's a string");
+        // the array has the same number of elements
+        let len = source_params("bash", &tmc_params, r#""${#array[@]}""#);
+        assert_eq!(len, &[TRICKY_VALUES.len().to_string()]);
+    }
+
+    #[test]
+    fn parses_tmcparams() {
+        init();
+        let mut tmc_params = TmcParams::new();
+        tmc_params.insert_string("string", "it's").unwrap();
+        tmc_params
+            .insert_array("array", TRICKY_VALUES.to_vec())
+            .unwrap();
+        tmc_params
+            .insert_array("empty", Vec::<&str>::new())
+            .unwrap();
+        let parsed = TmcParams::parse(&tmc_params.to_string()).unwrap();
+        assert_eq!(parsed, tmc_params);
+
+        let parsed = TmcParams::parse(
+            r#"# comment
+a=plain; export b="double \"quoted\" \$"
+c=( one\ two 'three'
+  four ) # comment
+export c
+"#,
+        )
+        .unwrap();
+        let mut expected = TmcParams::new();
+        expected.insert_string("a", "plain").unwrap();
+        expected.insert_string("b", r#"double "quoted" $"#).unwrap();
+        expected
+            .insert_array("c", vec!["one two", "three", "four"])
+            .unwrap();
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn rejects_invalid_tmcparams() {
+        init();
+        assert!(TmcParams::parse("a='unterminated").is_err());
+        assert!(TmcParams::parse("a=$HOME").is_err());
+        assert!(TmcParams::parse(r#"a="$(ls)""#).is_err());
+        assert!(TmcParams::parse("a=b c").is_err());
+        assert!(TmcParams::parse("1a=b").is_err());
+        assert!(TmcParams::parse("a=(b").is_err());
+        assert!(TmcParams::parse("ls -la").is_err());
     }
 
     #[test]
@@ -523,7 +836,9 @@ mod test {
             TmcParams::new(),
             Path::new(MAVEN_CLONE),
             None,
-            false,
+            ArchiveFormat::Tar,
+            UnzipLimits::default(),
+            Reproducibility::Deterministic,
         )
         .unwrap();
         assert!(output.exists());
@@ -556,7 +871,9 @@ mod test {
             TmcParams::new(),
             Path::new(MAVEN_CLONE),
             None,
-            true,
+            ArchiveFormat::Zip,
+            UnzipLimits::default(),
+            Reproducibility::Deterministic,
         )
         .unwrap();
         assert!(output.exists());
@@ -583,7 +900,9 @@ mod test {
             TmcParams::new(),
             Path::new(MAVEN_CLONE),
             None,
-            true,
+            ArchiveFormat::Zip,
+            UnzipLimits::default(),
+            Reproducibility::Deterministic,
         )
         .unwrap();
         assert!(output.exists());
@@ -596,6 +915,38 @@ mod test {
         archive.by_name("toplevel/pom.xml").unwrap();
     }
 
+    #[test]
+    fn packages_deterministically() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        for format in &[
+            ArchiveFormat::Zip,
+            ArchiveFormat::Tar,
+            ArchiveFormat::TarGz,
+            ArchiveFormat::TarZstd,
+        ] {
+            let mut archives = vec![];
+            for i in 0..2 {
+                let output = temp.path().join(format!("output{:?}-{}", format, i));
+                prepare_submission(
+                    Path::new(MAVEN_ZIP),
+                    &output,
+                    Some("toplevel".to_string()),
+                    TmcParams::new(),
+                    Path::new(MAVEN_CLONE),
+                    None,
+                    *format,
+                    UnzipLimits::default(),
+                    Reproducibility::Deterministic,
+                )
+                .unwrap();
+                archives.push(fs::read(output).unwrap());
+            }
+            assert!(archives[0] == archives[1]);
+        }
+    }
+
     #[test]
     fn package_with_stub_tests() {
         init();
@@ -611,7 +962,9 @@ mod test {
             TmcParams::new(),
             Path::new(MAVEN_CLONE),
             Some(Path::new("tests/data/MavenStub.zip")),
-            false,
+            ArchiveFormat::Tar,
+            UnzipLimits::default(),
+            Reproducibility::Deterministic,
         )
         .unwrap();
         assert!(output_arch.exists());
@@ -0,0 +1,225 @@
+//! Contains functions for verifying that the stubs and solutions of exercises behave as expected before publishing them.
+
+use super::{
+    find_exercise_directories, format_error_chain, get_language_plugin, DynLanguagePlugin,
+};
+use crate::ExerciseDesc;
+use std::collections::BTreeSet;
+use std::path::Path;
+use tmc_langs_framework::{
+    domain::{ExerciseVerification, RunStatus, VerificationProblem, VerificationProblemKind},
+    TmcError,
+};
+
+/// Finds the exercises in the course directory with `find_exercise_directories` and verifies each of them.
+/// See `verify_exercise`.
+pub fn verify_exercises(course_path: &Path) -> Result<Vec<ExerciseVerification>, TmcError> {
+    find_exercise_directories(course_path)
+        .iter()
+        .map(|exercise_path| verify_exercise(exercise_path))
+        .collect()
+}
+
+/// Prepares the stub and solution of the exercise into temporary directories and runs the tests on both.
+/// Problems are reported if the stub doesn't compile or passes all of the tests, if the solution fails any tests,
+/// or if scanning the stub or solution finds different points than scanning the original exercise.
+/// Failures to prepare, scan or test the exercise are reported as problems rather than returned as errors.
+pub fn verify_exercise(exercise_path: &Path) -> Result<ExerciseVerification, TmcError> {
+    log::debug!("verifying exercise at {}", exercise_path.display());
+
+    let mut verification = ExerciseVerification {
+        exercise_path: exercise_path.to_path_buf(),
+        stub_status: None,
+        solution_status: None,
+        problems: vec![],
+    };
+    let plugin = match get_language_plugin(exercise_path) {
+        Ok(plugin) => plugin,
+        Err(e) => {
+            verification
+                .problems
+                .push(error("no language plugin found", e));
+            return Ok(verification);
+        }
+    };
+    let exercise_name = exercise_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let original_points = match plugin.scan_exercise(exercise_path, exercise_name.clone()) {
+        Ok(exercise_desc) => points(&exercise_desc),
+        Err(e) => {
+            verification
+                .problems
+                .push(error("failed to scan the exercise", e));
+            return Ok(verification);
+        }
+    };
+
+    let temp = tempfile::tempdir().map_err(TmcError::TempDir)?;
+    let stub_path = temp.path().join("stub").join(&exercise_name);
+    let solution_path = temp.path().join("solution").join(&exercise_name);
+
+    match plugin.prepare_stub(exercise_path, exercise_path, &stub_path) {
+        Ok(()) => {
+            let stub_status = check_version(
+                plugin.as_ref(),
+                &stub_path,
+                &exercise_name,
+                "stub",
+                &original_points,
+                &mut verification.problems,
+            );
+            match stub_status {
+                Some(RunStatus::CompileFailed) => verification.problems.push(problem(
+                    VerificationProblemKind::StubCompileFailed,
+                    "the stub did not compile".to_string(),
+                )),
+                Some(RunStatus::Passed) => verification.problems.push(problem(
+                    VerificationProblemKind::StubPassed,
+                    "all of the tests passed for the stub".to_string(),
+                )),
+                _ => {}
+            }
+            verification.stub_status = stub_status;
+        }
+        Err(e) => verification
+            .problems
+            .push(error("failed to prepare the stub", e)),
+    }
+
+    match plugin.prepare_solution(vec![exercise_path.to_path_buf()], &solution_path) {
+        Ok(()) => {
+            let solution_status = check_version(
+                plugin.as_ref(),
+                &solution_path,
+                &exercise_name,
+                "solution",
+                &original_points,
+                &mut verification.problems,
+            );
+            if let Some(status) = &solution_status {
+                if *status != RunStatus::Passed {
+                    verification.problems.push(problem(
+                        VerificationProblemKind::SolutionFailed,
+                        format!("the tests did not pass for the solution: {:?}", status),
+                    ));
+                }
+            }
+            verification.solution_status = solution_status;
+        }
+        Err(e) => verification
+            .problems
+            .push(error("failed to prepare the solution", e)),
+    }
+
+    Ok(verification)
+}
+
+// Runs the tests on the prepared stub or solution and compares its points to the original exercise.
+// Returns the status of the test run, or None if the tests could not be ran.
+fn check_version(
+    plugin: &dyn DynLanguagePlugin,
+    path: &Path,
+    exercise_name: &str,
+    version: &str,
+    original_points: &BTreeSet<String>,
+    problems: &mut Vec<VerificationProblem>,
+) -> Option<RunStatus> {
+    let run_result = match plugin.run_tests(path) {
+        Ok(run_result) => run_result,
+        Err(e) => {
+            problems.push(error(
+                &format!("failed to run the tests for the {}", version),
+                e,
+            ));
+            return None;
+        }
+    };
+    // scanning may require compiling the exercise
+    if run_result.status != RunStatus::CompileFailed {
+        match plugin.scan_exercise(path, exercise_name.to_string()) {
+            Ok(exercise_desc) => {
+                let version_points = points(&exercise_desc);
+                if &version_points != original_points {
+                    problems.push(problem(
+                        VerificationProblemKind::PointsDiffer,
+                        format!(
+                            "the {} has the points {:?} while the exercise has the points {:?}",
+                            version, version_points, original_points
+                        ),
+                    ));
+                }
+            }
+            Err(e) => problems.push(error(&format!("failed to scan the {}", version), e)),
+        }
+    }
+    Some(run_result.status)
+}
+
+fn points(exercise_desc: &ExerciseDesc) -> BTreeSet<String> {
+    exercise_desc
+        .tests
+        .iter()
+        .flat_map(|t| t.points.iter().cloned())
+        .collect()
+}
+
+fn problem(kind: VerificationProblemKind, message: String) -> VerificationProblem {
+    VerificationProblem { kind, message }
+}
+
+fn error(context: &str, error: TmcError) -> VerificationProblem {
+    problem(
+        VerificationProblemKind::Error,
+        format!("{}: {}", context, format_error_chain(&error)),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tmc_langs_framework::io::file_util;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn verifies_exercises() {
+        init();
+
+        // scanning the exercises writes files into them, so the course is copied
+        // along with the shared python test runner
+        let temp = tempfile::tempdir().unwrap();
+        file_util::copy("tests/data/VerificationCourse", temp.path()).unwrap();
+        let course = temp.path().join("VerificationCourse");
+        for exercise in &["valid", "invalid"] {
+            file_util::copy("tests/data/PythonTestRunner/tmc", course.join(exercise)).unwrap();
+        }
+
+        let mut verifications = verify_exercises(&course).unwrap();
+        verifications.sort_by(|a, b| a.exercise_path.cmp(&b.exercise_path));
+        assert_eq!(verifications.len(), 2);
+
+        let invalid = &verifications[0];
+        assert!(invalid.exercise_path.ends_with("invalid"));
+        let kinds: Vec<_> = invalid.problems.iter().map(|p| p.kind).collect();
+        assert_eq!(
+            kinds,
+            &[
+                VerificationProblemKind::StubPassed,
+                VerificationProblemKind::SolutionFailed
+            ],
+            "{:?}",
+            invalid.problems
+        );
+
+        let valid = &verifications[1];
+        assert!(valid.exercise_path.ends_with("valid"));
+        assert!(valid.problems.is_empty(), "{:?}", valid.problems);
+        assert_eq!(valid.stub_status, Some(RunStatus::TestsFailed));
+        assert_eq!(valid.solution_status, Some(RunStatus::Passed));
+    }
+}
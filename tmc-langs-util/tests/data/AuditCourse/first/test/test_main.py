@@ -0,0 +1,7 @@
+import unittest
+from tmc import points
+
+@points("1.1")
+class TestFirst(unittest.TestCase):
+    def test_a(self):
+        pass
@@ -0,0 +1,5 @@
+import unittest
+
+class TestFourth(unittest.TestCase):
+    def test_a(self):
+        pass
@@ -0,0 +1,10 @@
+import unittest
+from tmc import points
+
+class TestSecond(unittest.TestCase):
+    @points("1.1", "two words")
+    def test_a(self):
+        pass
+
+    def test_b(self):
+        pass
@@ -0,0 +1,7 @@
+import unittest
+from tmc import points
+
+@points("3.1")
+class TestThird(unittest.TestCase):
+    def test_a(self):
+        pass
@@ -0,0 +1 @@
+# written by the student
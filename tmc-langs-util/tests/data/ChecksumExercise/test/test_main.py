@@ -0,0 +1,7 @@
+import unittest
+from src.main import f
+
+
+class TestMain(unittest.TestCase):
+    def test_f(self):
+        self.assertEqual(f(), 1)
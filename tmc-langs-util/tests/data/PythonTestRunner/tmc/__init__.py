@@ -0,0 +1,2 @@
+from .points import points
+from .runner import TMCTestRunner
@@ -0,0 +1,5 @@
+def f():
+    # BEGIN SOLUTION
+    return 2
+    # END SOLUTION
+    # STUB: return 1
@@ -0,0 +1,8 @@
+import unittest
+from tmc import points
+from src.main import f
+
+@points("1")
+class TestMain(unittest.TestCase):
+    def test_f(self):
+        self.assertEqual(f(), 1)